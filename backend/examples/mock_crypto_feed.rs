@@ -0,0 +1,130 @@
+//! Local stand-in for a Binance-style exchange, for running the crypto feed
+//! without network access:
+//!
+//! ```sh
+//! cargo run --example mock_crypto_feed            # listens on 127.0.0.1:9876
+//! CRYPTO_FEED=binance CRYPTO_WS_URL=ws://127.0.0.1:9876 \
+//!     CRYPTO_REST_URL=http://127.0.0.1:9876 cargo run
+//! ```
+//!
+//! Serves `/stream?streams=btcusdt@kline_1m/...` (a kline or trade per stream
+//! every second) and `/api/v3/klines`. Prices are a deterministic function of
+//! the pair and the minute, so the stream and REST backfill agree.
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query,
+    },
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Deterministic OHLCV for `pair` in the minute starting at `minute_ms`.
+fn candle(pair: &str, minute_ms: i64) -> (f64, f64, f64, f64, f64) {
+    let base = match pair.to_ascii_uppercase().as_str() {
+        p if p.starts_with("BTC") => 60_000.0,
+        p if p.starts_with("ETH") => 3_000.0,
+        p if p.starts_with("SOL") => 150.0,
+        _ => 100.0,
+    };
+    let m = (minute_ms / 60_000) as f64;
+    let seed = pair.bytes().map(f64::from).sum::<f64>();
+    let price = |x: f64| base * (1.0 + 0.01 * (x / 37.0 + seed).sin() + 0.002 * (x * 1.3).cos());
+    let (open, close) = (price(m), price(m + 1.0));
+    let high = open.max(close) * 1.0005;
+    let low = open.min(close) * 0.9995;
+    let volume = 10.0 + 5.0 * (m + seed).sin().abs();
+    (open, high, low, close, volume)
+}
+
+fn fmt(x: f64) -> String {
+    format!("{:.2}", x)
+}
+
+async fn stream(ws: WebSocketUpgrade, Query(params): Query<HashMap<String, String>>) -> impl IntoResponse {
+    let streams: Vec<String> = params
+        .get("streams")
+        .map(|s| s.split('/').map(str::to_string).collect())
+        .unwrap_or_default();
+    ws.on_upgrade(move |socket| feed(socket, streams))
+}
+
+async fn feed(mut socket: WebSocket, streams: Vec<String>) {
+    println!("client subscribed to {:?}", streams);
+    let mut trade_id = 0u64;
+    loop {
+        let now = Utc::now().timestamp_millis();
+        let minute = now - now.rem_euclid(60_000);
+        for stream in &streams {
+            let Some((pair, kind)) = stream.split_once('@') else { continue };
+            let symbol = pair.to_ascii_uppercase();
+            let (o, h, l, c, v) = candle(&symbol, minute);
+            // Partial minute: interpolate toward the close.
+            let done = (now - minute) as f64 / 60_000.0;
+            let last = o + (c - o) * done;
+            let data = if kind.starts_with("kline") {
+                serde_json::json!({
+                    "e": "kline", "E": now, "s": symbol,
+                    "k": {
+                        "t": minute, "T": minute + 59_999, "s": symbol, "i": "1m",
+                        "o": fmt(o), "c": fmt(last), "h": fmt(h.max(last)), "l": fmt(l.min(last)),
+                        "v": fmt(v * done), "x": false
+                    }
+                })
+            } else {
+                trade_id += 1;
+                serde_json::json!({
+                    "e": "trade", "E": now, "s": symbol, "t": trade_id,
+                    "p": fmt(last), "q": "0.01", "T": now, "m": false
+                })
+            };
+            let msg = serde_json::json!({ "stream": stream, "data": data }).to_string();
+            if socket.send(Message::Text(msg)).await.is_err() {
+                println!("client disconnected");
+                return;
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KlinesQuery {
+    symbol: String,
+    start_time: i64,
+    end_time: i64,
+    limit: Option<usize>,
+}
+
+async fn klines(Query(q): Query<KlinesQuery>) -> Json<Vec<serde_json::Value>> {
+    let now = Utc::now().timestamp_millis();
+    let first = q.start_time + (60_000 - q.start_time.rem_euclid(60_000)) % 60_000;
+    let rows = (0..)
+        .map(|i| first + i * 60_000)
+        .take_while(|t| *t <= q.end_time && *t + 60_000 <= now)
+        .take(q.limit.unwrap_or(500).min(1000))
+        .map(|t| {
+            let (o, h, l, c, v) = candle(&q.symbol, t);
+            serde_json::json!([t, fmt(o), fmt(h), fmt(l), fmt(c), fmt(v), t + 59_999, "0", 0, "0", "0", "0"])
+        })
+        .collect();
+    Json(rows)
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let addr = std::env::var("MOCK_CRYPTO_ADDR").unwrap_or_else(|_| "127.0.0.1:9876".to_string());
+    let app = Router::new()
+        .route("/stream", get(stream))
+        .route("/api/v3/klines", get(klines));
+    println!("mock crypto exchange on {}", addr);
+    axum::serve(tokio::net::TcpListener::bind(&addr).await?, app).await?;
+    Ok(())
+}
@@ -0,0 +1,16 @@
+-- Per-asset parameters for the deterministic seed price model
+-- (geometric Brownian motion + volatility regimes + jumps + shared factor).
+CREATE TABLE asset_price_models (
+    asset_id UUID PRIMARY KEY REFERENCES assets(id) ON DELETE CASCADE,
+    base_price DOUBLE PRECISION NOT NULL CHECK (base_price > 0),
+    drift DOUBLE PRECISION NOT NULL DEFAULT 0,
+    volatility DOUBLE PRECISION NOT NULL CHECK (volatility >= 0),
+    regime_multiplier DOUBLE PRECISION NOT NULL DEFAULT 1 CHECK (regime_multiplier >= 1),
+    regime_probability DOUBLE PRECISION NOT NULL DEFAULT 0 CHECK (regime_probability >= 0 AND regime_probability <= 1),
+    jumps_per_day DOUBLE PRECISION NOT NULL DEFAULT 0 CHECK (jumps_per_day >= 0),
+    jump_mean DOUBLE PRECISION NOT NULL DEFAULT 0,
+    jump_stddev DOUBLE PRECISION NOT NULL DEFAULT 0 CHECK (jump_stddev >= 0),
+    factor TEXT NOT NULL DEFAULT 'market',
+    factor_correlation DOUBLE PRECISION NOT NULL DEFAULT 0 CHECK (factor_correlation >= 0 AND factor_correlation <= 1),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,19 @@
+-- One row per gap-repair pass over an asset that found missing minute buckets.
+CREATE TABLE market_data_repairs (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    asset_id UUID NOT NULL REFERENCES assets(id) ON DELETE CASCADE,
+    trigger VARCHAR(20) NOT NULL CHECK (trigger IN ('bootstrap', 'scheduled')),
+    source VARCHAR(20) NOT NULL,
+    window_start TIMESTAMP NOT NULL,
+    window_end TIMESTAMP NOT NULL,
+    expected_minutes INTEGER NOT NULL,
+    missing_minutes INTEGER NOT NULL,
+    gap_count INTEGER NOT NULL,
+    filled_rows INTEGER NOT NULL DEFAULT 0,
+    remaining_minutes INTEGER NOT NULL,
+    gaps JSONB NOT NULL DEFAULT '[]',
+    error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_market_data_repairs_asset_created ON market_data_repairs(asset_id, created_at DESC);
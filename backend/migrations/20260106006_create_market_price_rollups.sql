@@ -0,0 +1,27 @@
+-- Downsampled candles. The retention job rolls 1-minute rows older than the
+-- configured horizon from market_prices into these tables and deletes them;
+-- `timestamp` is the UTC start of the bucket.
+CREATE TABLE market_prices_1h (
+    asset_id UUID NOT NULL REFERENCES assets(id) ON DELETE CASCADE,
+    timestamp TIMESTAMP NOT NULL,
+    open NUMERIC(12,4) NOT NULL,
+    high NUMERIC(12,4) NOT NULL,
+    low NUMERIC(12,4) NOT NULL,
+    close NUMERIC(12,4) NOT NULL,
+    volume NUMERIC(20,4),
+    PRIMARY KEY (asset_id, timestamp)
+);
+
+CREATE TABLE market_prices_1d (
+    asset_id UUID NOT NULL REFERENCES assets(id) ON DELETE CASCADE,
+    timestamp TIMESTAMP NOT NULL,
+    open NUMERIC(12,4) NOT NULL,
+    high NUMERIC(12,4) NOT NULL,
+    low NUMERIC(12,4) NOT NULL,
+    close NUMERIC(12,4) NOT NULL,
+    volume NUMERIC(20,4),
+    PRIMARY KEY (asset_id, timestamp)
+);
+
+CREATE INDEX idx_market_prices_timestamp ON market_prices(timestamp);
+CREATE INDEX idx_market_prices_1h_timestamp ON market_prices_1h(timestamp);
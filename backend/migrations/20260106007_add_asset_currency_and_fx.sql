@@ -0,0 +1,11 @@
+-- Quote currency of every asset (ISO 4217). Seed crypto is quoted in USD,
+-- everything on NSE in INR.
+ALTER TABLE assets ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'INR';
+UPDATE assets SET currency = 'USD' WHERE asset_type = 'crypto';
+
+-- FX pairs are ordinary assets (symbol = base || quote, e.g. USDINR, quoted in
+-- the quote currency) so the seed and live providers produce them like any
+-- other series.
+ALTER TABLE assets DROP CONSTRAINT assets_asset_type_check;
+ALTER TABLE assets ADD CONSTRAINT assets_asset_type_check
+    CHECK (asset_type IN ('crypto', 'equity', 'etf', 'index', 'fx'));
@@ -0,0 +1,19 @@
+-- Catalogue metadata, editable at runtime through the admin asset API.
+ALTER TABLE assets
+    ADD COLUMN lot_size NUMERIC(20,4) NOT NULL DEFAULT 1 CHECK (lot_size > 0),
+    ADD COLUMN tick_size NUMERIC(12,4) NOT NULL DEFAULT 0.01 CHECK (tick_size > 0),
+    ADD COLUMN sector TEXT,
+    ADD COLUMN instrument_token BIGINT UNIQUE CHECK (instrument_token > 0),
+    ADD COLUMN display_name TEXT,
+    ADD COLUMN display_decimals SMALLINT NOT NULL DEFAULT 2 CHECK (display_decimals BETWEEN 0 AND 8),
+    ADD COLUMN logo_url TEXT,
+    ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT NOW();
+
+-- Kite instrument tokens that used to be hardcoded in the ingester.
+UPDATE assets SET instrument_token = 256265 WHERE symbol = 'NIFTY50';
+UPDATE assets SET instrument_token = 408065 WHERE symbol = 'INFY';
+
+-- Activating an asset from the admin API backfills its history.
+ALTER TABLE market_data_repairs DROP CONSTRAINT market_data_repairs_trigger_check;
+ALTER TABLE market_data_repairs ADD CONSTRAINT market_data_repairs_trigger_check
+    CHECK (trigger IN ('bootstrap', 'scheduled', 'activation'));
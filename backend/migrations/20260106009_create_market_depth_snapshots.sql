@@ -0,0 +1,14 @@
+-- Periodic top-of-book snapshots from the live feed (Kite `Full` mode
+-- carries five bid and five offer levels). `bids`/`asks` are JSON arrays of
+-- {price, quantity, orders}, best level first.
+CREATE TABLE market_depth_snapshots (
+    asset_id UUID NOT NULL REFERENCES assets(id) ON DELETE CASCADE,
+    timestamp TIMESTAMP NOT NULL,
+    bids JSONB NOT NULL,
+    asks JSONB NOT NULL,
+    total_buy_quantity BIGINT,
+    total_sell_quantity BIGINT,
+    PRIMARY KEY (asset_id, timestamp)
+);
+
+CREATE INDEX idx_market_depth_snapshots_timestamp ON market_depth_snapshots(timestamp);
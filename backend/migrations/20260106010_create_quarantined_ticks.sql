@@ -0,0 +1,23 @@
+-- Live ticks that failed data-quality checks, held back from market_prices
+-- until an admin approves (merges) or rejects them.
+CREATE TABLE quarantined_ticks (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    asset_id UUID NOT NULL REFERENCES assets(id) ON DELETE CASCADE,
+    instrument_token BIGINT NOT NULL,
+    received_at TIMESTAMP NOT NULL,
+    exchange_time TIMESTAMP,
+    price NUMERIC(12,4),
+    volume NUMERIC(20,4),
+    -- Last accepted price the tick was compared against.
+    reference_price NUMERIC(12,4),
+    reasons TEXT[] NOT NULL,
+    raw JSONB NOT NULL,
+    status VARCHAR(10) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'approved', 'rejected')),
+    reviewed_by VARCHAR(255),
+    reviewed_at TIMESTAMP,
+    review_note TEXT
+);
+
+CREATE INDEX idx_quarantined_ticks_status_received ON quarantined_ticks(status, received_at DESC);
+CREATE INDEX idx_quarantined_ticks_asset_received ON quarantined_ticks(asset_id, received_at DESC);
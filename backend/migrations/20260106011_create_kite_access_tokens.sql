@@ -0,0 +1,13 @@
+-- Kite access tokens rotated in through the admin API. Kite expires tokens
+-- daily (06:00 IST); the newest unexpired row is preferred over
+-- KITE_ACCESS_TOKEN.
+CREATE TABLE kite_access_tokens (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    access_token TEXT NOT NULL,
+    kite_user_id VARCHAR(50),
+    source VARCHAR(20) NOT NULL CHECK (source IN ('access_token', 'request_token')),
+    created_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_kite_access_tokens_created ON kite_access_tokens(created_at DESC);
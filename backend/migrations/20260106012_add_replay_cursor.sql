@@ -0,0 +1,6 @@
+-- Bar the replay socket last sent, so paper trades fill at the price the
+-- student is looking at. NULL until the first bar goes out.
+ALTER TABLE replay_sessions
+    ADD COLUMN cursor_time TIMESTAMP,
+    ADD COLUMN cursor_price NUMERIC(12,4),
+    ADD COLUMN cursor_updated_at TIMESTAMP;
@@ -0,0 +1,15 @@
+-- Replay paper-trading accounts: each session starts with cash, and every
+-- fill moves cash and the position it trades. Trades placed before this
+-- migration stay in replay_trades but are not replayed into positions.
+ALTER TABLE replay_sessions
+    ADD COLUMN starting_cash NUMERIC(20,2) NOT NULL DEFAULT 100000 CHECK (starting_cash > 0),
+    ADD COLUMN cash NUMERIC(20,4) NOT NULL DEFAULT 100000 CHECK (cash >= 0);
+
+CREATE TABLE replay_positions (
+    replay_id UUID NOT NULL REFERENCES replay_sessions(id) ON DELETE CASCADE,
+    asset_id UUID NOT NULL REFERENCES assets(id) ON DELETE CASCADE,
+    quantity NUMERIC(12,4) NOT NULL CHECK (quantity >= 0),
+    avg_cost NUMERIC(20,8) NOT NULL,
+    realized_pnl NUMERIC(20,4) NOT NULL DEFAULT 0,
+    PRIMARY KEY (replay_id, asset_id)
+);
@@ -0,0 +1,33 @@
+-- Resting replay orders, filled by the replay socket against each bar it
+-- streams. Bracket legs (stop_loss / take_profit) point at their entry and
+-- stay 'pending' until it fills; the legs are one-cancels-the-other.
+CREATE TABLE replay_orders (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    replay_id UUID NOT NULL REFERENCES replay_sessions(id) ON DELETE CASCADE,
+    asset_id UUID NOT NULL REFERENCES assets(id) ON DELETE CASCADE,
+    parent_id UUID REFERENCES replay_orders(id) ON DELETE CASCADE,
+    role VARCHAR(12) NOT NULL DEFAULT 'entry'
+        CHECK (role IN ('entry', 'stop_loss', 'take_profit')),
+    side TEXT NOT NULL CHECK (side IN ('buy', 'sell')),
+    order_type VARCHAR(10) NOT NULL
+        CHECK (order_type IN ('market', 'limit', 'stop', 'stop_limit')),
+    quantity NUMERIC(12,4) NOT NULL CHECK (quantity > 0),
+    limit_price NUMERIC(12,4),
+    stop_price NUMERIC(12,4),
+    -- A stop-limit whose stop has been hit and now rests as a limit.
+    triggered BOOLEAN NOT NULL DEFAULT FALSE,
+    status VARCHAR(10) NOT NULL DEFAULT 'open'
+        CHECK (status IN ('pending', 'open', 'filled', 'cancelled', 'rejected')),
+    -- Replay time the order went live; only later bars can fill it.
+    active_from TIMESTAMP,
+    fill_price NUMERIC(12,4),
+    filled_at TIMESTAMP,
+    reason TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_replay_orders_replay ON replay_orders(replay_id, created_at);
+CREATE INDEX idx_replay_orders_parent ON replay_orders(parent_id);
+
+ALTER TABLE replay_trades
+    ADD COLUMN order_id UUID REFERENCES replay_orders(id) ON DELETE SET NULL;
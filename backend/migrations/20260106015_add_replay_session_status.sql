@@ -0,0 +1,5 @@
+-- Whether the replay socket has sent the session's last bar, for listing
+-- sessions by status.
+ALTER TABLE replay_sessions ADD COLUMN finished BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX idx_replay_sessions_user_created ON replay_sessions(user_id, created_at DESC);
@@ -0,0 +1,19 @@
+-- Replay sessions cover a set of assets on one clock. An asset's cursor
+-- price is its last close at or before the session's cursor time (NULL
+-- until it has a bar), and trades name the asset they fill.
+CREATE TABLE replay_session_assets (
+    replay_id UUID NOT NULL REFERENCES replay_sessions(id) ON DELETE CASCADE,
+    asset_id UUID NOT NULL REFERENCES assets(id) ON DELETE CASCADE,
+    position SMALLINT NOT NULL,
+    cursor_price NUMERIC(12,4),
+    PRIMARY KEY (replay_id, asset_id)
+);
+
+INSERT INTO replay_session_assets (replay_id, asset_id, position, cursor_price)
+SELECT id, asset_id, 0, cursor_price FROM replay_sessions;
+
+ALTER TABLE replay_trades ADD COLUMN asset_id UUID REFERENCES assets(id) ON DELETE CASCADE;
+UPDATE replay_trades t SET asset_id = rs.asset_id FROM replay_sessions rs WHERE rs.id = t.replay_id;
+ALTER TABLE replay_trades ALTER COLUMN asset_id SET NOT NULL;
+
+ALTER TABLE replay_sessions DROP COLUMN asset_id, DROP COLUMN cursor_price;
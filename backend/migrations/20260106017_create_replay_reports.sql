@@ -0,0 +1,12 @@
+-- Trade journal notes, and the order trades were placed in (replay time can
+-- go backwards when a student seeks).
+ALTER TABLE replay_trades
+    ADD COLUMN note TEXT,
+    ADD COLUMN recorded_at TIMESTAMP NOT NULL DEFAULT NOW();
+
+-- The latest performance report of each session.
+CREATE TABLE replay_reports (
+    replay_id UUID PRIMARY KEY REFERENCES replay_sessions(id) ON DELETE CASCADE,
+    report JSONB NOT NULL,
+    generated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,31 @@
+-- Admin-curated replay windows. Blind scenarios hide their dates and
+-- symbols from students until the replay ends.
+CREATE TABLE replay_scenarios (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    title VARCHAR(200) NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    learning_goals TEXT[] NOT NULL DEFAULT '{}',
+    start_time TIMESTAMP NOT NULL,
+    end_time TIMESTAMP NOT NULL,
+    starting_cash NUMERIC(20, 2) NOT NULL DEFAULT 100000,
+    blind BOOLEAN NOT NULL DEFAULT FALSE,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_by TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CHECK (start_time < end_time)
+);
+
+CREATE TABLE replay_scenario_assets (
+    scenario_id UUID NOT NULL REFERENCES replay_scenarios(id) ON DELETE CASCADE,
+    asset_id UUID NOT NULL REFERENCES assets(id) ON DELETE CASCADE,
+    position SMALLINT NOT NULL,
+    PRIMARY KEY (scenario_id, asset_id)
+);
+
+ALTER TABLE replay_sessions ADD COLUMN scenario_id UUID REFERENCES replay_scenarios(id);
+CREATE INDEX idx_replay_sessions_scenario ON replay_sessions(scenario_id, user_id, created_at)
+    WHERE scenario_id IS NOT NULL;
+
+-- Stand-in asset ids shown while a blind scenario runs.
+ALTER TABLE replay_session_assets ADD COLUMN alias_id UUID NOT NULL DEFAULT uuid_generate_v4();
@@ -0,0 +1,29 @@
+-- Bar-by-bar replays reveal a bar only when the student steps, and score
+-- next-bar predictions made before each reveal.
+ALTER TABLE replay_sessions ADD COLUMN mode VARCHAR(20) NOT NULL DEFAULT 'stream'
+    CHECK (mode IN ('stream', 'bar_by_bar'));
+
+CREATE TABLE replay_predictions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    replay_id UUID NOT NULL REFERENCES replay_sessions(id) ON DELETE CASCADE,
+    asset_id UUID NOT NULL REFERENCES assets(id) ON DELETE CASCADE,
+    direction VARCHAR(4) CHECK (direction IN ('up', 'down', 'flat')),
+    target_price NUMERIC(20, 8),
+    -- The bar on screen when the prediction was made
+    reference_time TIMESTAMP NOT NULL,
+    reference_price NUMERIC(20, 8) NOT NULL,
+    -- The asset's next bar, once revealed
+    resolved_time TIMESTAMP,
+    actual_price NUMERIC(20, 8),
+    actual_direction VARCHAR(4),
+    direction_correct BOOLEAN,
+    target_score DOUBLE PRECISION,
+    score DOUBLE PRECISION,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CHECK (direction IS NOT NULL OR target_price IS NOT NULL)
+);
+
+-- One open prediction per asset; predicting again replaces it
+CREATE UNIQUE INDEX idx_replay_predictions_open ON replay_predictions(replay_id, asset_id)
+    WHERE resolved_time IS NULL;
+CREATE INDEX idx_replay_predictions_replay ON replay_predictions(replay_id, reference_time);
@@ -0,0 +1,5 @@
+-- Playback state of the replay socket, so a reconnect picks up where the
+-- last connection left off.
+ALTER TABLE replay_sessions
+    ADD COLUMN paused BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN speed DOUBLE PRECISION NOT NULL DEFAULT 1 CHECK (speed BETWEEN 0.5 AND 60);
@@ -1,3 +1,5 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use chrono_tz::Tz;
 use std::env;
 
 /// Which data source powers replay, contests, and leaderboards.
@@ -6,10 +8,16 @@ use std::env;
 /// - `Live`  : attempts Zerodha Kite stream. If tokens are missing or handshake fails,
 ///   the runtime **falls back to Seed** so the app is never broken by a
 ///   foreign runtime dependency.
+/// - `File`  : imports OHLCV history from CSV/Parquet files (`MARKET_DATA_FILES`),
+///   optionally playing one historical day forward as if live. Fully offline.
+/// - `Demo`  : re-emits a historical window (from `market_prices` or the files)
+///   as current-time candles at a configurable speed, for classrooms and demos.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MarketDataMode {
     Seed,
     Live,
+    File,
+    Demo,
 }
 
 impl MarketDataMode {
@@ -17,6 +25,8 @@ impl MarketDataMode {
         match self {
             MarketDataMode::Seed => "seed",
             MarketDataMode::Live => "live",
+            MarketDataMode::File => "file",
+            MarketDataMode::Demo => "demo",
         }
     }
 }
@@ -30,8 +40,40 @@ pub struct AppConfig {
     pub session_secret: String,
     pub frontend_url: String,
     pub market_data_mode: MarketDataMode,
-    /// Optional — only used when `market_data_mode == Live` AND both values are set.
+    /// Optional — present when `KITE_API_KEY` is set. Live mode also needs an
+    /// access token, from the environment or rotated in through the admin API.
     pub kite: Option<KiteConfig>,
+    /// Used when `market_data_mode == File`, and by `Demo` with a file source.
+    pub files: FileDataConfig,
+    /// Only used when `market_data_mode == Demo`.
+    pub demo: DemoConfig,
+    /// Optional crypto exchange WebSocket feed (`CRYPTO_FEED=binance`), run
+    /// beside the seed or Kite provider for assets on its exchanges.
+    pub crypto: Option<CryptoFeedConfig>,
+    /// CSV of exchange holidays for the trading calendar.
+    pub holidays_file: String,
+    /// Days of 1-minute history the bootstrap seeds for every asset.
+    pub seed_history_days: i64,
+    /// How far back gap repair looks for missing minute candles.
+    pub gap_repair_lookback_hours: i64,
+    /// Minutes between background gap-repair passes.
+    pub gap_repair_interval_minutes: u64,
+    /// Days of 1-minute candles kept before rolling them into 1h/1d buckets.
+    pub retention_minute_days: i64,
+    /// Days of 1-hour rollups kept; older history is daily only.
+    pub retention_hourly_days: i64,
+    /// Minimum seconds between stored order-book snapshots per asset.
+    pub depth_snapshot_seconds: i64,
+    /// Days of order-book snapshots kept.
+    pub depth_retention_days: i64,
+    /// Live ticks moving more than this % from the last accepted price are quarantined.
+    pub tick_max_jump_pct: f64,
+    /// ...or more than this many standard deviations of recent returns.
+    pub tick_max_jump_sigma: f64,
+    /// Live ticks stamped further than this in the future are quarantined.
+    pub tick_max_future_seconds: i64,
+    /// Emails allowed to use the admin API (comma-separated `ADMIN_EMAILS`).
+    pub admin_emails: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -44,7 +86,68 @@ pub struct GoogleOAuthConfig {
 #[derive(Debug, Clone)]
 pub struct KiteConfig {
     pub api_key: String,
-    pub access_token: String,
+    /// Deploy-time token; a newer one stored through the admin API wins.
+    pub access_token: Option<String>,
+    /// Needed to exchange a login `request_token` for an access token.
+    pub api_secret: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileDataConfig {
+    /// CSV/Parquet files or directories of them (comma-separated `MARKET_DATA_FILES`).
+    pub paths: Vec<String>,
+    /// Zone of timestamps in the files that carry no offset.
+    pub timezone: Tz,
+    /// File symbol → asset symbol (`MARKET_DATA_SYMBOL_MAP=RELIANCE.NS=RELIANCE,...`).
+    /// Unmapped symbols are matched to assets as-is.
+    pub symbol_map: Vec<(String, String)>,
+    /// Day (in `timezone`) to play forward as live after the import.
+    pub playback_date: Option<NaiveDate>,
+    /// Playback speed; 1.0 is real time.
+    pub playback_speed: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DemoSource {
+    /// Candles already stored in the price tables.
+    Database,
+    /// The `MARKET_DATA_FILES` files (not imported).
+    Files,
+}
+
+#[derive(Debug, Clone)]
+pub struct DemoConfig {
+    pub source: DemoSource,
+    /// Historical window to replay (UTC). Demo mode needs both.
+    pub window_start: Option<NaiveDateTime>,
+    pub window_end: Option<NaiveDateTime>,
+    /// Replay speed; 6.25 plays an NSE session in an hour.
+    pub speed: f64,
+    /// Start the window over when it ends.
+    pub repeat: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CryptoStream {
+    /// 1-minute kline updates (`<symbol>@kline_1m`).
+    Kline,
+    /// Individual trades (`<symbol>@trade`), merged into minute candles.
+    Trade,
+}
+
+#[derive(Debug, Clone)]
+pub struct CryptoFeedConfig {
+    /// Binance-style WebSocket base URL; streams are joined onto `/stream?streams=`.
+    pub ws_url: String,
+    /// REST base URL serving `/api/v3/klines`, used for gap repair.
+    pub rest_url: String,
+    /// Asset exchanges routed to this feed (`CRYPTO_EXCHANGES`, upper case).
+    pub exchanges: Vec<String>,
+    /// Quote asset appended to asset symbols (`BTC` → `BTCUSDT`).
+    pub quote_asset: String,
+    /// Asset symbol → exchange symbol, for pairs that don't follow the rule above.
+    pub symbol_map: Vec<(String, String)>,
+    pub stream: CryptoStream,
 }
 
 impl AppConfig {
@@ -57,17 +160,169 @@ impl AppConfig {
             .as_str()
         {
             "live" => MarketDataMode::Live,
+            "file" => MarketDataMode::File,
+            "demo" => MarketDataMode::Demo,
             _ => MarketDataMode::Seed,
         };
 
-        let kite = match (env::var("KITE_API_KEY"), env::var("KITE_ACCESS_TOKEN")) {
-            (Ok(k), Ok(t)) if !k.trim().is_empty() && !t.trim().is_empty() => Some(KiteConfig {
-                api_key: k,
-                access_token: t,
+        let non_empty = |name: &str| env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let kite = non_empty("KITE_API_KEY").map(|api_key| KiteConfig {
+            api_key,
+            access_token: non_empty("KITE_ACCESS_TOKEN"),
+            api_secret: non_empty("KITE_API_SECRET"),
+        });
+
+        let files = FileDataConfig {
+            paths: env::var("MARKET_DATA_FILES")
+                .unwrap_or_default()
+                .split(',')
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect(),
+            timezone: env::var("MARKET_DATA_FILE_TIMEZONE")
+                .unwrap_or_else(|_| "UTC".to_string())
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid MARKET_DATA_FILE_TIMEZONE: {}", e))?,
+            symbol_map: env::var("MARKET_DATA_SYMBOL_MAP")
+                .unwrap_or_default()
+                .split(',')
+                .filter(|pair| !pair.trim().is_empty())
+                .map(|pair| match pair.split_once('=') {
+                    Some((from, to)) if !from.trim().is_empty() && !to.trim().is_empty() => {
+                        Ok((from.trim().to_string(), to.trim().to_ascii_uppercase()))
+                    }
+                    _ => Err(anyhow::anyhow!(
+                        "Invalid MARKET_DATA_SYMBOL_MAP entry '{}' (expected FILE_SYMBOL=ASSET)",
+                        pair
+                    )),
+                })
+                .collect::<anyhow::Result<_>>()?,
+            playback_date: env::var("MARKET_DATA_PLAYBACK_DATE")
+                .ok()
+                .filter(|d| !d.trim().is_empty())
+                .map(|d| d.trim().parse())
+                .transpose()
+                .map_err(|e| anyhow::anyhow!("Invalid MARKET_DATA_PLAYBACK_DATE: {}", e))?,
+            playback_speed: env::var("MARKET_DATA_PLAYBACK_SPEED")
+                .unwrap_or_else(|_| "1".to_string())
+                .parse()?,
+        };
+        anyhow::ensure!(
+            files.playback_speed > 0.0,
+            "MARKET_DATA_PLAYBACK_SPEED must be positive"
+        );
+
+        let demo_time = |name: &str| -> anyhow::Result<Option<NaiveDateTime>> {
+            env::var(name)
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+                .map(|v| {
+                    chrono::DateTime::parse_from_rfc3339(v.trim())
+                        .map(|d| d.naive_utc())
+                        .map_err(|e| anyhow::anyhow!("Invalid {} (expected RFC 3339): {}", name, e))
+                })
+                .transpose()
+        };
+        let demo = DemoConfig {
+            source: match env::var("DEMO_SOURCE")
+                .unwrap_or_else(|_| "db".to_string())
+                .to_ascii_lowercase()
+                .as_str()
+            {
+                "db" => DemoSource::Database,
+                "file" => DemoSource::Files,
+                other => anyhow::bail!("Invalid DEMO_SOURCE '{}' (expected db or file)", other),
+            },
+            window_start: demo_time("DEMO_WINDOW_START")?,
+            window_end: demo_time("DEMO_WINDOW_END")?,
+            speed: env::var("DEMO_SPEED")
+                .unwrap_or_else(|_| "6.25".to_string())
+                .parse()?,
+            repeat: env::var("DEMO_REPEAT")
+                .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+                .unwrap_or(true),
+        };
+        anyhow::ensure!(demo.speed > 0.0, "DEMO_SPEED must be positive");
+
+        let crypto = match env::var("CRYPTO_FEED")
+            .unwrap_or_else(|_| "off".to_string())
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "off" | "" => None,
+            "binance" => Some(CryptoFeedConfig {
+                ws_url: env::var("CRYPTO_WS_URL")
+                    .unwrap_or_else(|_| "wss://stream.binance.com:9443".to_string())
+                    .trim_end_matches('/')
+                    .to_string(),
+                rest_url: env::var("CRYPTO_REST_URL")
+                    .unwrap_or_else(|_| "https://api.binance.com".to_string())
+                    .trim_end_matches('/')
+                    .to_string(),
+                exchanges: env::var("CRYPTO_EXCHANGES")
+                    .unwrap_or_else(|_| "BINANCE".to_string())
+                    .split(',')
+                    .map(|e| e.trim().to_ascii_uppercase())
+                    .filter(|e| !e.is_empty())
+                    .collect(),
+                quote_asset: env::var("CRYPTO_QUOTE_ASSET")
+                    .unwrap_or_else(|_| "USDT".to_string())
+                    .trim()
+                    .to_ascii_uppercase(),
+                symbol_map: env::var("CRYPTO_SYMBOL_MAP")
+                    .unwrap_or_default()
+                    .split(',')
+                    .filter(|pair| !pair.trim().is_empty())
+                    .map(|pair| match pair.split_once('=') {
+                        Some((asset, pair)) if !asset.trim().is_empty() && !pair.trim().is_empty() => Ok((
+                            asset.trim().to_ascii_uppercase(),
+                            pair.trim().to_ascii_uppercase(),
+                        )),
+                        _ => Err(anyhow::anyhow!(
+                            "Invalid CRYPTO_SYMBOL_MAP entry '{}' (expected ASSET=PAIR)",
+                            pair
+                        )),
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+                stream: match env::var("CRYPTO_STREAM")
+                    .unwrap_or_else(|_| "kline".to_string())
+                    .to_ascii_lowercase()
+                    .as_str()
+                {
+                    "kline" => CryptoStream::Kline,
+                    "trade" => CryptoStream::Trade,
+                    other => anyhow::bail!("Invalid CRYPTO_STREAM '{}' (expected kline or trade)", other),
+                },
             }),
-            _ => None,
+            other => anyhow::bail!("Invalid CRYPTO_FEED '{}' (expected off or binance)", other),
         };
 
+        let seed_history_days: i64 = env::var("SEED_HISTORY_DAYS")
+            .unwrap_or_else(|_| "3".to_string())
+            .parse()?;
+        let gap_repair_lookback_hours: i64 = env::var("GAP_REPAIR_LOOKBACK_HOURS")
+            .unwrap_or_else(|_| "72".to_string())
+            .parse()?;
+        let retention_minute_days: i64 = env::var("RETENTION_MINUTE_DAYS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()?;
+        let retention_hourly_days: i64 = env::var("RETENTION_HOURLY_DAYS")
+            .unwrap_or_else(|_| "365".to_string())
+            .parse()?;
+        // Otherwise gap repair would keep re-creating minutes retention just pruned.
+        anyhow::ensure!(
+            gap_repair_lookback_hours < retention_minute_days * 24,
+            "GAP_REPAIR_LOOKBACK_HOURS must be shorter than RETENTION_MINUTE_DAYS"
+        );
+        anyhow::ensure!(
+            seed_history_days < retention_minute_days,
+            "SEED_HISTORY_DAYS must be shorter than RETENTION_MINUTE_DAYS"
+        );
+        anyhow::ensure!(
+            retention_hourly_days >= retention_minute_days,
+            "RETENTION_HOURLY_DAYS must be at least RETENTION_MINUTE_DAYS"
+        );
+
         Ok(Self {
             database_url: env::var("DATABASE_URL").expect("DATABASE_URL must be set"),
             port: env::var("PORT")
@@ -86,6 +341,39 @@ impl AppConfig {
                 .unwrap_or_else(|_| "http://localhost:5173".to_string()),
             market_data_mode,
             kite,
+            files,
+            demo,
+            crypto,
+            holidays_file: env::var("MARKET_HOLIDAYS_FILE")
+                .unwrap_or_else(|_| "config/market_holidays.csv".to_string()),
+            seed_history_days,
+            gap_repair_lookback_hours,
+            gap_repair_interval_minutes: env::var("GAP_REPAIR_INTERVAL_MINUTES")
+                .unwrap_or_else(|_| "15".to_string())
+                .parse()?,
+            retention_minute_days,
+            retention_hourly_days,
+            depth_snapshot_seconds: env::var("DEPTH_SNAPSHOT_SECONDS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()?,
+            depth_retention_days: env::var("DEPTH_RETENTION_DAYS")
+                .unwrap_or_else(|_| "7".to_string())
+                .parse()?,
+            tick_max_jump_pct: env::var("TICK_MAX_JUMP_PCT")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()?,
+            tick_max_jump_sigma: env::var("TICK_MAX_JUMP_SIGMA")
+                .unwrap_or_else(|_| "8".to_string())
+                .parse()?,
+            tick_max_future_seconds: env::var("TICK_MAX_FUTURE_SECONDS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()?,
+            admin_emails: env::var("ADMIN_EMAILS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|e| e.trim().to_string())
+                .filter(|e| !e.is_empty())
+                .collect(),
         })
     }
 }
@@ -1,5 +1,6 @@
 use axum::{routing::get, Router};
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tower_http::{compression::CompressionLayer, cors::CorsLayer, trace::TraceLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
@@ -12,7 +13,8 @@ mod services;
 
 use config::AppConfig;
 use db::Database;
-use services::{market_data, seeder, ContestExecutor};
+use services::market_data::MarketDataProvider;
+use services::{seeder, ContestExecutor, GapRepairer, MarketDataControl, RetentionJob, TradingCalendar};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -33,30 +35,54 @@ async fn main() -> anyhow::Result<()> {
         config.market_data_mode.as_str()
     );
 
+    let calendar = Arc::new(TradingCalendar::load(&config.holidays_file));
+
     // 2. Database + migrations
     let database = Database::new(&config.database_url).await?;
     tracing::info!("Database connected and migrations applied");
 
-    // 3. Auto-seed the DB (idempotent, self-healing)
-    seeder::bootstrap(&database.pool).await?;
-    tracing::info!("DB bootstrap complete (assets, history, contests present)");
-
-    // 4. Pick market-data provider (seed by default, live if Kite creds present)
-    let (provider, effective_mode) = market_data::build(database.pool.clone(), &config);
+    // 3. Pick market-data provider (seed by default, live if Kite creds present);
+    //    admins can switch it and rotate the Kite token at runtime.
+    let provider = Arc::new(
+        MarketDataControl::new(database.pool.clone(), config.clone(), calendar.clone()).await?,
+    );
     tracing::info!(
         "Market-data provider: {} (effective mode = {})",
         provider.label(),
-        effective_mode.as_str()
+        provider.mode().as_str()
     );
-    provider.start().await?;
+
+    // 4. Auto-seed the DB (idempotent, self-healing); history gaps are filled
+    //    from the provider picked above.
+    let repairer = Arc::new(GapRepairer::new(
+        database.pool.clone(),
+        calendar.clone(),
+        provider.clone(),
+        config.gap_repair_lookback_hours,
+        config.gap_repair_interval_minutes,
+    ));
+    seeder::bootstrap(&database.pool, &calendar, &repairer, config.seed_history_days).await?;
+    tracing::info!("DB bootstrap complete (assets, history, contests present)");
+
+    provider.clone().start().await?;
+    tokio::spawn(repairer.clone().run());
 
     // 5. Contest executor — drives state transitions, leaderboard, settlement
     let executor = ContestExecutor::new(database.pool.clone());
     tokio::spawn(executor.run());
     tracing::info!("Contest executor spawned");
 
-    // 6. HTTP + WebSocket server
-    let app = build_router(database, config.clone());
+    // 6. Retention — rolls old 1-minute candles into hourly/daily buckets
+    let retention = RetentionJob::new(
+        database.pool.clone(),
+        config.retention_minute_days,
+        config.retention_hourly_days,
+        config.depth_retention_days,
+    );
+    tokio::spawn(retention.run());
+
+    // 7. HTTP + WebSocket server
+    let app = build_router(database, config.clone(), calendar, provider, repairer);
     let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
     tracing::info!("Server listening on {}", addr);
 
@@ -66,8 +92,14 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-fn build_router(database: Database, config: AppConfig) -> Router {
-    let app_state = modules::AppState::new(database, config);
+fn build_router(
+    database: Database,
+    config: AppConfig,
+    calendar: Arc<TradingCalendar>,
+    provider: Arc<MarketDataControl>,
+    repairer: Arc<GapRepairer>,
+) -> Router {
+    let app_state = modules::AppState::new(database, config, calendar, provider, repairer);
 
     Router::new()
         .route("/health", get(health_check))
@@ -87,6 +119,7 @@ fn api_v1_routes(state: modules::AppState) -> Router {
         .nest("/market-data", modules::market_data::routes(state.clone()))
         .nest("/replay", modules::replay::routes(state.clone()))
         .nest("/contests", modules::contests::routes(state.clone()))
+        .nest("/admin", modules::admin::routes(state.clone()))
 }
 
 fn websocket_routes(state: modules::AppState) -> Router {
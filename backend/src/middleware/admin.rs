@@ -0,0 +1,50 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+use uuid::Uuid;
+use crate::{error::AppError, middleware::SessionUser, modules::AppState};
+
+/// Authenticated user whose email is listed in `ADMIN_EMAILS`.
+/// Use as a handler extractor to require admin rights.
+#[derive(Debug, Clone)]
+pub struct AdminUser {
+    #[allow(dead_code)]
+    pub user_id: Uuid,
+    pub email: String,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AdminUser {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let session = SessionUser::from_request_parts(parts, state).await?;
+
+        let email: String = sqlx::query_scalar("SELECT email FROM users WHERE id = $1")
+            .bind(session.user_id)
+            .fetch_optional(&state.db.pool)
+            .await
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("DB error: {}", e)).into_response())?
+            .ok_or_else(|| AppError::Unauthorized.into_response())?;
+
+        if !state
+            .config
+            .admin_emails
+            .iter()
+            .any(|admin| admin.eq_ignore_ascii_case(&email))
+        {
+            return Err(AppError::Forbidden("Admin access required".to_string()).into_response());
+        }
+
+        Ok(AdminUser {
+            user_id: session.user_id,
+            email,
+        })
+    }
+}
@@ -1,2 +1,4 @@
+pub mod admin;
 pub mod session;
+pub use admin::AdminUser;
 pub use session::SessionUser;
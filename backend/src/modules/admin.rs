@@ -0,0 +1,992 @@
+use axum::{
+    extract::{Path, Query, State},
+    routing::{get, patch, post},
+    Router, Json,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use uuid::Uuid;
+use rust_decimal::Decimal;
+use chrono::NaiveDateTime;
+use std::collections::BTreeMap;
+use crate::{
+    error::{AppError, Result},
+    middleware::AdminUser,
+    modules::AppState,
+    config::MarketDataMode,
+    services::{
+        fx,
+        gap_repair::Trigger,
+        market_data::{floor_minute, MarketDataProvider, SeedAsset},
+        market_data_ingester,
+        price_model::PriceModel,
+        provider_control::ProviderStatus,
+        replay_scenarios::{self, Scenario},
+    },
+    modules::replay::{check_assets, parse_starting_cash, parse_time},
+};
+
+/// Must match the `assets.asset_type` CHECK constraint.
+const ASSET_TYPES: &[&str] = &["crypto", "equity", "etf", "index", "fx"];
+
+const ASSET_COLUMNS: &str = "id, symbol, name, asset_type, exchange, currency, is_active, \
+     lot_size, tick_size, sector, instrument_token, display_name, display_decimals, logo_url, \
+     created_at, updated_at";
+
+pub fn routes(state: AppState) -> Router {
+    Router::new()
+        .route("/assets", get(list_assets).post(create_asset))
+        .route("/assets/:asset_id", patch(update_asset))
+        .route("/assets/:asset_id/activate", post(activate_asset))
+        .route("/assets/:asset_id/deactivate", post(deactivate_asset))
+        .route("/quarantine", get(list_quarantine))
+        .route("/quarantine/stats", get(quarantine_stats))
+        .route("/quarantine/:tick_id/approve", post(approve_tick))
+        .route("/quarantine/:tick_id/reject", post(reject_tick))
+        .route("/market-data", get(market_data_status))
+        .route("/market-data/mode", post(switch_market_data_mode))
+        .route("/market-data/kite-token", post(rotate_kite_token))
+        .route("/scenarios", get(list_scenarios).post(create_scenario))
+        .route("/scenarios/:scenario_id", patch(update_scenario))
+        .with_state(state)
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct AdminAsset {
+    id: Uuid,
+    symbol: String,
+    name: String,
+    asset_type: String,
+    exchange: Option<String>,
+    currency: String,
+    is_active: bool,
+    lot_size: Decimal,
+    tick_size: Decimal,
+    sector: Option<String>,
+    instrument_token: Option<i64>,
+    display_name: Option<String>,
+    display_decimals: i16,
+    logo_url: Option<String>,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateAssetRequest {
+    symbol: String,
+    name: String,
+    asset_type: String,
+    exchange: Option<String>,
+    currency: String,
+    lot_size: Option<Decimal>,
+    tick_size: Option<Decimal>,
+    sector: Option<String>,
+    instrument_token: Option<i64>,
+    display_name: Option<String>,
+    display_decimals: Option<i16>,
+    logo_url: Option<String>,
+    /// Defaults to true.
+    is_active: Option<bool>,
+    /// Starting level of the seed price model. Defaults to the fallback 1000.
+    base_price: Option<f64>,
+}
+
+/// Partial update — omitted fields are left unchanged, and `null` clears the
+/// optional ones. The symbol is the asset's identity (and seeds its price
+/// model), so it cannot be changed.
+#[derive(Debug, Deserialize)]
+struct UpdateAssetRequest {
+    name: Option<String>,
+    asset_type: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    exchange: Option<Option<String>>,
+    currency: Option<String>,
+    lot_size: Option<Decimal>,
+    tick_size: Option<Decimal>,
+    #[serde(default, deserialize_with = "nullable")]
+    sector: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    instrument_token: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "nullable")]
+    display_name: Option<Option<String>>,
+    display_decimals: Option<i16>,
+    #[serde(default, deserialize_with = "nullable")]
+    logo_url: Option<Option<String>>,
+}
+
+/// `Some(None)` for an explicit `null`, so it can be told apart from an
+/// omitted field (`None`, via `#[serde(default)]`).
+fn nullable<'de, D, T>(deserializer: D) -> std::result::Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateScenarioRequest {
+    title: String,
+    description: Option<String>,
+    learning_goals: Option<Vec<String>>,
+    asset_ids: Vec<Uuid>,
+    /// `2026-10-16T05:00:00Z`, like replay sessions.
+    from: String,
+    to: String,
+    /// Defaults to 100,000 in the assets' currency.
+    starting_cash: Option<f64>,
+    /// Hide dates and symbols until the replay ends. Defaults to false.
+    blind: Option<bool>,
+    /// Defaults to true.
+    is_active: Option<bool>,
+}
+
+/// Partial update — omitted fields are left unchanged. Once students have
+/// played a scenario, what they played (window, assets, cash, blind mode)
+/// is fixed so its leaderboard stays comparable.
+#[derive(Debug, Deserialize)]
+struct UpdateScenarioRequest {
+    title: Option<String>,
+    description: Option<String>,
+    learning_goals: Option<Vec<String>>,
+    asset_ids: Option<Vec<Uuid>>,
+    from: Option<String>,
+    to: Option<String>,
+    starting_cash: Option<f64>,
+    blind: Option<bool>,
+    is_active: Option<bool>,
+}
+
+const MAX_SCENARIO_TITLE: usize = 200;
+
+const QUARANTINE_COLUMNS: &str = "q.id, q.asset_id, a.symbol, q.instrument_token, q.received_at, \
+     q.exchange_time, q.price, q.volume, q.reference_price, q.reasons, q.raw, q.status, \
+     q.reviewed_by, q.reviewed_at, q.review_note";
+
+/// Default / maximum rows for `GET /quarantine`.
+const DEFAULT_QUARANTINE_LIMIT: i64 = 100;
+const MAX_QUARANTINE_LIMIT: i64 = 1_000;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct QuarantinedTick {
+    id: Uuid,
+    asset_id: Uuid,
+    symbol: String,
+    instrument_token: i64,
+    received_at: NaiveDateTime,
+    exchange_time: Option<NaiveDateTime>,
+    price: Option<Decimal>,
+    volume: Option<Decimal>,
+    reference_price: Option<Decimal>,
+    reasons: Vec<String>,
+    raw: serde_json::Value,
+    status: String,
+    reviewed_by: Option<String>,
+    reviewed_at: Option<NaiveDateTime>,
+    review_note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QuarantineQuery {
+    /// `pending` (default), `approved`, `rejected` or `all`.
+    status: Option<String>,
+    asset_id: Option<Uuid>,
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ReviewRequest {
+    note: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct QuarantineStats {
+    by_status: BTreeMap<String, i64>,
+    /// Each tick counts once per rule it broke.
+    by_reason: BTreeMap<String, i64>,
+    pending_by_asset: BTreeMap<String, i64>,
+    last_24h: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct SwitchModeRequest {
+    /// `seed` or `live`.
+    mode: String,
+}
+
+/// Exactly one of the two: a ready access token, or the `request_token`
+/// from a Kite login redirect (exchanged using KITE_API_SECRET).
+#[derive(Debug, Deserialize)]
+struct KiteTokenRequest {
+    access_token: Option<String>,
+    request_token: Option<String>,
+}
+
+/// Trimmed, upper-cased symbol of 1-20 characters of A-Z, 0-9, '-' or '_'.
+fn validate_symbol(symbol: &str) -> Result<String> {
+    let symbol = symbol.trim().to_ascii_uppercase();
+    if symbol.is_empty()
+        || symbol.len() > 20
+        || !symbol.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(AppError::Validation(
+            "symbol must be 1-20 characters of A-Z, 0-9, '-' or '_'".to_string(),
+        ));
+    }
+    Ok(symbol)
+}
+
+fn validate_asset_type(asset_type: &str) -> Result<()> {
+    if !ASSET_TYPES.contains(&asset_type) {
+        return Err(AppError::Validation(format!(
+            "asset_type must be one of: {}",
+            ASSET_TYPES.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+fn validate_currency(currency: &str) -> Result<String> {
+    fx::normalize_currency(currency)
+        .ok_or_else(|| AppError::Validation("currency must be a 3-letter code".to_string()))
+}
+
+fn validate_metadata(
+    lot_size: Option<Decimal>,
+    tick_size: Option<Decimal>,
+    instrument_token: Option<i64>,
+    display_decimals: Option<i16>,
+) -> Result<()> {
+    if lot_size.is_some_and(|l| l <= Decimal::ZERO) {
+        return Err(AppError::Validation("lot_size must be positive".to_string()));
+    }
+    if tick_size.is_some_and(|t| t <= Decimal::ZERO) {
+        return Err(AppError::Validation("tick_size must be positive".to_string()));
+    }
+    if instrument_token.is_some_and(|t| t <= 0 || t > u32::MAX as i64) {
+        return Err(AppError::Validation(
+            "instrument_token must be a positive 32-bit integer".to_string(),
+        ));
+    }
+    if display_decimals.is_some_and(|d| !(0..=8).contains(&d)) {
+        return Err(AppError::Validation(
+            "display_decimals must be between 0 and 8".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Unique-violation (duplicate symbol / instrument token) → 409.
+fn map_unique_violation(e: sqlx::Error) -> AppError {
+    match &e {
+        sqlx::Error::Database(db) if db.code().as_deref() == Some("23505") => {
+            AppError::Conflict("An asset with this symbol or instrument token already exists".to_string())
+        }
+        _ => AppError::Database(e),
+    }
+}
+
+/// Admin — every asset, including inactive ones, with full metadata.
+async fn list_assets(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+) -> Result<Json<Vec<AdminAsset>>> {
+    let assets = sqlx::query_as::<_, AdminAsset>(&format!(
+        "SELECT {} FROM assets ORDER BY symbol",
+        ASSET_COLUMNS
+    ))
+    .fetch_all(&state.db.pool)
+    .await?;
+
+    Ok(Json(assets))
+}
+
+async fn create_asset(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Json(payload): Json<CreateAssetRequest>,
+) -> Result<Json<AdminAsset>> {
+    let symbol = validate_symbol(&payload.symbol)?;
+    if payload.name.trim().is_empty() {
+        return Err(AppError::Validation("name is required".to_string()));
+    }
+    validate_asset_type(&payload.asset_type)?;
+    let currency = validate_currency(&payload.currency)?;
+    validate_metadata(
+        payload.lot_size,
+        payload.tick_size,
+        payload.instrument_token,
+        payload.display_decimals,
+    )?;
+    if payload.base_price.is_some_and(|p| !(p.is_finite() && p > 0.0)) {
+        return Err(AppError::Validation("base_price must be positive".to_string()));
+    }
+    let model = default_price_model(
+        &payload.asset_type,
+        payload.exchange.as_deref(),
+        payload.base_price,
+    );
+
+    let mut tx = state.db.pool.begin().await?;
+    let asset = sqlx::query_as::<_, AdminAsset>(&format!(
+        r#"
+        INSERT INTO assets
+            (symbol, name, asset_type, exchange, currency, is_active, lot_size, tick_size,
+             sector, instrument_token, display_name, display_decimals, logo_url)
+        VALUES ($1, $2, $3, $4, $5, $6, COALESCE($7, 1), COALESCE($8, 0.01),
+                $9, $10, $11, COALESCE($12, 2), $13)
+        RETURNING {}
+        "#,
+        ASSET_COLUMNS
+    ))
+    .bind(&symbol)
+    .bind(payload.name.trim())
+    .bind(&payload.asset_type)
+    .bind(payload.exchange.map(|e| e.trim().to_ascii_uppercase()))
+    .bind(&currency)
+    .bind(payload.is_active.unwrap_or(true))
+    .bind(payload.lot_size)
+    .bind(payload.tick_size)
+    .bind(payload.sector)
+    .bind(payload.instrument_token)
+    .bind(payload.display_name)
+    .bind(payload.display_decimals)
+    .bind(payload.logo_url)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_unique_violation)?;
+
+    // Without a model row the seed provider would price it at the fallback
+    sqlx::query(
+        r#"
+        INSERT INTO asset_price_models
+            (asset_id, base_price, drift, volatility, regime_multiplier, regime_probability,
+             jumps_per_day, jump_mean, jump_stddev, factor, factor_correlation)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        "#,
+    )
+    .bind(asset.id)
+    .bind(model.base_price)
+    .bind(model.drift)
+    .bind(model.volatility)
+    .bind(model.regime_multiplier)
+    .bind(model.regime_probability)
+    .bind(model.jumps_per_day)
+    .bind(model.jump_mean)
+    .bind(model.jump_stddev)
+    .bind(&model.factor)
+    .bind(model.factor_correlation)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    tracing::info!("Admin {} created asset {}", admin.email, asset.symbol);
+    if asset.is_active {
+        on_activated(&state, asset.id);
+    }
+
+    Ok(Json(asset))
+}
+
+/// Fallback parameters, moved with the market factor the asset belongs to.
+fn default_price_model(asset_type: &str, exchange: Option<&str>, base_price: Option<f64>) -> PriceModel {
+    let mut model = PriceModel::fallback();
+    model.factor = match (asset_type, exchange.map(|e| e.trim().to_ascii_uppercase())) {
+        ("crypto", _) => "crypto",
+        ("fx", _) => "fx",
+        (_, Some(e)) if e == "NSE" => "nse",
+        _ => "market",
+    }
+    .to_string();
+    if let Some(base) = base_price {
+        model.base_price = base;
+    }
+    model
+}
+
+async fn update_asset(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(asset_id): Path<Uuid>,
+    Json(payload): Json<UpdateAssetRequest>,
+) -> Result<Json<AdminAsset>> {
+    if let Some(asset_type) = &payload.asset_type {
+        validate_asset_type(asset_type)?;
+    }
+    let currency = payload.currency.as_deref().map(validate_currency).transpose()?;
+    validate_metadata(
+        payload.lot_size,
+        payload.tick_size,
+        payload.instrument_token.flatten(),
+        payload.display_decimals,
+    )?;
+    if payload.name.as_deref().is_some_and(|n| n.trim().is_empty()) {
+        return Err(AppError::Validation("name must not be empty".to_string()));
+    }
+
+    // Nullable columns take a "present" flag, so `null` clears them
+    // Providers route subscriptions by exchange, type and token
+    let routing_changed =
+        payload.instrument_token.is_some() || payload.exchange.is_some() || payload.asset_type.is_some();
+    let asset = sqlx::query_as::<_, AdminAsset>(&format!(
+        r#"
+        UPDATE assets SET
+            name             = COALESCE($2, name),
+            asset_type       = COALESCE($3, asset_type),
+            exchange         = CASE WHEN $13 THEN $4 ELSE exchange END,
+            currency         = COALESCE($5, currency),
+            lot_size         = COALESCE($6, lot_size),
+            tick_size        = COALESCE($7, tick_size),
+            sector           = CASE WHEN $14 THEN $8 ELSE sector END,
+            instrument_token = CASE WHEN $15 THEN $9 ELSE instrument_token END,
+            display_name     = CASE WHEN $16 THEN $10 ELSE display_name END,
+            display_decimals = COALESCE($11, display_decimals),
+            logo_url         = CASE WHEN $17 THEN $12 ELSE logo_url END,
+            updated_at       = NOW()
+        WHERE id = $1
+        RETURNING {}
+        "#,
+        ASSET_COLUMNS
+    ))
+    .bind(asset_id)
+    .bind(payload.name.map(|n| n.trim().to_string()))
+    .bind(payload.asset_type)
+    .bind(payload.exchange.clone().flatten().map(|e| e.trim().to_ascii_uppercase()))
+    .bind(currency)
+    .bind(payload.lot_size)
+    .bind(payload.tick_size)
+    .bind(payload.sector.clone().flatten())
+    .bind(payload.instrument_token.flatten())
+    .bind(payload.display_name.clone().flatten())
+    .bind(payload.display_decimals)
+    .bind(payload.logo_url.clone().flatten())
+    .bind(payload.exchange.is_some())
+    .bind(payload.sector.is_some())
+    .bind(payload.instrument_token.is_some())
+    .bind(payload.display_name.is_some())
+    .bind(payload.logo_url.is_some())
+    .fetch_optional(&state.db.pool)
+    .await
+    .map_err(map_unique_violation)?
+    .ok_or(AppError::NotFound)?;
+
+    tracing::info!("Admin {} updated asset {}", admin.email, asset.symbol);
+    if routing_changed && asset.is_active {
+        state.provider.refresh_subscriptions();
+    }
+
+    Ok(Json(asset))
+}
+
+async fn activate_asset(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(asset_id): Path<Uuid>,
+) -> Result<Json<AdminAsset>> {
+    let asset = set_active(&state, asset_id, true).await?;
+    tracing::info!("Admin {} activated asset {}", admin.email, asset.symbol);
+    on_activated(&state, asset.id);
+    Ok(Json(asset))
+}
+
+async fn deactivate_asset(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(asset_id): Path<Uuid>,
+) -> Result<Json<AdminAsset>> {
+    let asset = set_active(&state, asset_id, false).await?;
+    tracing::info!("Admin {} deactivated asset {}", admin.email, asset.symbol);
+    // Drops it from the live stream; the seed ticker skips inactive assets.
+    state.provider.refresh_subscriptions();
+    Ok(Json(asset))
+}
+
+async fn set_active(state: &AppState, asset_id: Uuid, active: bool) -> Result<AdminAsset> {
+    sqlx::query_as::<_, AdminAsset>(&format!(
+        "UPDATE assets SET is_active = $2, updated_at = NOW() WHERE id = $1 RETURNING {}",
+        ASSET_COLUMNS
+    ))
+    .bind(asset_id)
+    .bind(active)
+    .fetch_optional(&state.db.pool)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+/// Subscribe a newly active asset to the live stream and backfill its
+/// history over the gap-repair lookback in the background.
+fn on_activated(state: &AppState, asset_id: Uuid) {
+    state.provider.refresh_subscriptions();
+
+    let pool = state.db.pool.clone();
+    let repairer = state.repairer.clone();
+    tokio::spawn(async move {
+        let asset = match SeedAsset::load_active(&pool).await {
+            Ok(assets) => assets.into_iter().find(|a| a.id == asset_id),
+            Err(e) => {
+                tracing::warn!("Activation backfill: could not load assets: {:?}", e);
+                return;
+            }
+        };
+        if let Some(asset) = asset {
+            if let Err(e) = repairer.repair_one(&asset, Trigger::Activation).await {
+                tracing::warn!("Activation backfill for {} failed: {:?}", asset.symbol, e);
+            }
+        }
+    });
+}
+
+/// Admin — quarantined ticks, newest first.
+async fn list_quarantine(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    Query(params): Query<QuarantineQuery>,
+) -> Result<Json<Vec<QuarantinedTick>>> {
+    let status = match params.status.as_deref().unwrap_or("pending") {
+        "all" => None,
+        s @ ("pending" | "approved" | "rejected") => Some(s.to_string()),
+        _ => {
+            return Err(AppError::Validation(
+                "status must be pending, approved, rejected or all".to_string(),
+            ))
+        }
+    };
+    let limit = params.limit.unwrap_or(DEFAULT_QUARANTINE_LIMIT);
+    if !(1..=MAX_QUARANTINE_LIMIT).contains(&limit) {
+        return Err(AppError::Validation(format!(
+            "limit must be between 1 and {}",
+            MAX_QUARANTINE_LIMIT
+        )));
+    }
+
+    let ticks = sqlx::query_as::<_, QuarantinedTick>(&format!(
+        "SELECT {} FROM quarantined_ticks q JOIN assets a ON a.id = q.asset_id
+         WHERE ($1::text IS NULL OR q.status = $1)
+           AND ($2::uuid IS NULL OR q.asset_id = $2)
+         ORDER BY q.received_at DESC
+         LIMIT $3",
+        QUARANTINE_COLUMNS
+    ))
+    .bind(status)
+    .bind(params.asset_id)
+    .bind(limit)
+    .fetch_all(&state.db.pool)
+    .await?;
+
+    Ok(Json(ticks))
+}
+
+/// Admin — quarantine counters by status, rule and asset.
+async fn quarantine_stats(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+) -> Result<Json<QuarantineStats>> {
+    let pool = &state.db.pool;
+    let by_status: Vec<(String, i64)> =
+        sqlx::query_as("SELECT status, COUNT(*) FROM quarantined_ticks GROUP BY status")
+            .fetch_all(pool)
+            .await?;
+    let by_reason: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT reason, COUNT(*) FROM quarantined_ticks, unnest(reasons) AS reason GROUP BY reason",
+    )
+    .fetch_all(pool)
+    .await?;
+    let pending_by_asset: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT a.symbol, COUNT(*) FROM quarantined_ticks q JOIN assets a ON a.id = q.asset_id
+         WHERE q.status = 'pending' GROUP BY a.symbol",
+    )
+    .fetch_all(pool)
+    .await?;
+    let last_24h: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM quarantined_ticks WHERE received_at >= NOW() AT TIME ZONE 'UTC' - INTERVAL '1 day'",
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(Json(QuarantineStats {
+        by_status: by_status.into_iter().collect(),
+        by_reason: by_reason.into_iter().collect(),
+        pending_by_asset: pending_by_asset.into_iter().collect(),
+        last_24h,
+    }))
+}
+
+/// Admin — accept a quarantined tick: its price is merged into the candle
+/// it would have been stored in.
+async fn approve_tick(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(tick_id): Path<Uuid>,
+    payload: Option<Json<ReviewRequest>>,
+) -> Result<Json<QuarantinedTick>> {
+    let note = payload.unwrap_or_default().0.note;
+    let mut tx = state.db.pool.begin().await?;
+    let tick = review(&mut tx, tick_id, "approved", &admin, note).await?;
+    let price = tick
+        .price
+        .filter(|p| *p > Decimal::ZERO)
+        .ok_or_else(|| AppError::Validation("Tick has no valid price to approve".to_string()))?;
+    let minute = floor_minute(tick.received_at);
+    market_data_ingester::store_price(&mut *tx, tick.asset_id, minute, price, tick.volume).await?;
+    tx.commit().await?;
+
+    tracing::info!("Admin {} approved quarantined tick {}", admin.email, tick_id);
+    Ok(Json(tick))
+}
+
+/// Admin — discard a quarantined tick.
+async fn reject_tick(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(tick_id): Path<Uuid>,
+    payload: Option<Json<ReviewRequest>>,
+) -> Result<Json<QuarantinedTick>> {
+    let note = payload.unwrap_or_default().0.note;
+    let mut tx = state.db.pool.begin().await?;
+    let tick = review(&mut tx, tick_id, "rejected", &admin, note).await?;
+    tx.commit().await?;
+
+    tracing::info!("Admin {} rejected quarantined tick {}", admin.email, tick_id);
+    Ok(Json(tick))
+}
+
+/// Move a pending tick to `status`; 404 if unknown, 409 if already reviewed.
+async fn review(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    tick_id: Uuid,
+    status: &str,
+    admin: &AdminUser,
+    note: Option<String>,
+) -> Result<QuarantinedTick> {
+    let updated = sqlx::query_as::<_, QuarantinedTick>(&format!(
+        "WITH q AS (
+             UPDATE quarantined_ticks
+             SET status = $2, reviewed_by = $3, reviewed_at = NOW() AT TIME ZONE 'UTC', review_note = $4
+             WHERE id = $1 AND status = 'pending'
+             RETURNING *
+         )
+         SELECT {} FROM q JOIN assets a ON a.id = q.asset_id",
+        QUARANTINE_COLUMNS
+    ))
+    .bind(tick_id)
+    .bind(status)
+    .bind(&admin.email)
+    .bind(note)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    if let Some(tick) = updated {
+        return Ok(tick);
+    }
+    let current: Option<String> =
+        sqlx::query_scalar("SELECT status FROM quarantined_ticks WHERE id = $1")
+            .bind(tick_id)
+            .fetch_optional(&mut **tx)
+            .await?;
+    match current {
+        Some(current) => Err(AppError::Conflict(format!("Tick is already {}", current))),
+        None => Err(AppError::NotFound),
+    }
+}
+
+/// Admin — running market-data provider, Kite token and stream health.
+async fn market_data_status(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+) -> Result<Json<ProviderStatus>> {
+    Ok(Json(state.provider.status()))
+}
+
+/// Admin — switch between seed and live without a restart.
+async fn switch_market_data_mode(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Json(payload): Json<SwitchModeRequest>,
+) -> Result<Json<ProviderStatus>> {
+    let mode = match payload.mode.trim().to_ascii_lowercase().as_str() {
+        "seed" => MarketDataMode::Seed,
+        "live" => MarketDataMode::Live,
+        _ => return Err(AppError::Validation("mode must be seed or live".to_string())),
+    };
+    if mode == MarketDataMode::Live {
+        if !state.provider.kite_configured() {
+            return Err(AppError::Validation(
+                "Live mode needs KITE_API_KEY in the server environment".to_string(),
+            ));
+        }
+        if !state.provider.has_kite_token() {
+            return Err(AppError::Validation(
+                "No valid Kite access token; submit one to /admin/market-data/kite-token first".to_string(),
+            ));
+        }
+    }
+
+    state.provider.switch(mode).await?;
+    tracing::info!("Admin {} switched market data to {}", admin.email, mode.as_str());
+    Ok(Json(state.provider.status()))
+}
+
+/// Admin — store a new Kite access token; a running live stream restarts
+/// with it.
+async fn rotate_kite_token(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Json(payload): Json<KiteTokenRequest>,
+) -> Result<Json<ProviderStatus>> {
+    let Some(kite) = state.config.kite.as_ref() else {
+        return Err(AppError::Validation(
+            "Kite is not configured (KITE_API_KEY)".to_string(),
+        ));
+    };
+    let non_empty = |v: Option<String>| v.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+    let (access_token, kite_user_id, source) =
+        match (non_empty(payload.access_token), non_empty(payload.request_token)) {
+            (Some(token), None) => (token, None, "access_token"),
+            (None, Some(request_token)) => {
+                let secret = kite.api_secret.as_deref().ok_or_else(|| {
+                    AppError::Validation(
+                        "Exchanging a request_token needs KITE_API_SECRET in the server environment"
+                            .to_string(),
+                    )
+                })?;
+                let session =
+                    market_data_ingester::exchange_request_token(&kite.api_key, secret, &request_token)
+                        .await
+                        .map_err(|e| AppError::Validation(e.to_string()))?;
+                (session.access_token, session.user_id, "request_token")
+            }
+            _ => {
+                return Err(AppError::Validation(
+                    "Provide exactly one of access_token or request_token".to_string(),
+                ))
+            }
+        };
+
+    state
+        .provider
+        .rotate_kite_token(access_token, kite_user_id, source, &admin.email)
+        .await?;
+    tracing::info!("Admin {} rotated the Kite access token ({})", admin.email, source);
+    Ok(Json(state.provider.status()))
+}
+
+fn validate_scenario_text(title: Option<&str>, goals: Option<&[String]>) -> Result<()> {
+    if title.is_some_and(|t| t.trim().is_empty() || t.trim().chars().count() > MAX_SCENARIO_TITLE) {
+        return Err(AppError::Validation(format!(
+            "title must be 1-{} characters",
+            MAX_SCENARIO_TITLE
+        )));
+    }
+    if goals.is_some_and(|g| g.iter().any(|goal| goal.trim().is_empty())) {
+        return Err(AppError::Validation("learning_goals must not be empty".to_string()));
+    }
+    Ok(())
+}
+
+fn trimmed(goals: Vec<String>) -> Vec<String> {
+    goals.into_iter().map(|g| g.trim().to_string()).collect()
+}
+
+/// Admin — every scenario, including inactive ones, with dates and symbols.
+async fn list_scenarios(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+) -> Result<Json<Vec<Scenario>>> {
+    Ok(Json(replay_scenarios::list(&state.db.pool, false).await?))
+}
+
+async fn create_scenario(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Json(payload): Json<CreateScenarioRequest>,
+) -> Result<Json<Scenario>> {
+    validate_scenario_text(Some(&payload.title), payload.learning_goals.as_deref())?;
+    let from = parse_time("from", &payload.from)?;
+    let to = parse_time("to", &payload.to)?;
+    if from >= to {
+        return Err(AppError::Validation("from must be before to".to_string()));
+    }
+    let starting_cash = parse_starting_cash(payload.starting_cash)?;
+    let asset_ids = check_assets(&state.db.pool, payload.asset_ids).await?;
+
+    let mut tx = state.db.pool.begin().await?;
+    let scenario_id: Uuid = sqlx::query_scalar(
+        "INSERT INTO replay_scenarios
+             (title, description, learning_goals, start_time, end_time, starting_cash, blind,
+              is_active, created_by)
+         VALUES ($1, COALESCE($2, ''), COALESCE($3, '{}'), $4, $5, $6, $7, $8, $9)
+         RETURNING id",
+    )
+    .bind(payload.title.trim())
+    .bind(payload.description.map(|d| d.trim().to_string()))
+    .bind(payload.learning_goals.map(trimmed))
+    .bind(from)
+    .bind(to)
+    .bind(starting_cash)
+    .bind(payload.blind.unwrap_or(false))
+    .bind(payload.is_active.unwrap_or(true))
+    .bind(&admin.email)
+    .fetch_one(&mut *tx)
+    .await?;
+    set_scenario_assets(&mut tx, scenario_id, &asset_ids).await?;
+    tx.commit().await?;
+
+    let scenario = replay_scenarios::load(&state.db.pool, scenario_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    tracing::info!("Admin {} created replay scenario {}", admin.email, scenario.title);
+    Ok(Json(scenario))
+}
+
+async fn update_scenario(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(scenario_id): Path<Uuid>,
+    Json(payload): Json<UpdateScenarioRequest>,
+) -> Result<Json<Scenario>> {
+    validate_scenario_text(payload.title.as_deref(), payload.learning_goals.as_deref())?;
+    let current = replay_scenarios::load(&state.db.pool, scenario_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let from = payload.from.as_deref().map(|f| parse_time("from", f)).transpose()?;
+    let to = payload.to.as_deref().map(|t| parse_time("to", t)).transpose()?;
+    if from.unwrap_or(current.start_time) >= to.unwrap_or(current.end_time) {
+        return Err(AppError::Validation("from must be before to".to_string()));
+    }
+    let starting_cash = payload.starting_cash.map(|c| parse_starting_cash(Some(c))).transpose()?;
+    let asset_ids = match payload.asset_ids {
+        Some(ids) => Some(check_assets(&state.db.pool, ids).await?),
+        None => None,
+    };
+
+    let mut tx = state.db.pool.begin().await?;
+    // Lock the scenario so a student can't start it mid-change
+    sqlx::query("SELECT id FROM replay_scenarios WHERE id = $1 FOR UPDATE")
+        .bind(scenario_id)
+        .execute(&mut *tx)
+        .await?;
+    let replay_changed = from.is_some()
+        || to.is_some()
+        || starting_cash.is_some()
+        || asset_ids.is_some()
+        || payload.blind.is_some_and(|b| b != current.blind);
+    if replay_changed {
+        let played: bool =
+            sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM replay_sessions WHERE scenario_id = $1)")
+                .bind(scenario_id)
+                .fetch_one(&mut *tx)
+                .await?;
+        if played {
+            return Err(AppError::Conflict(
+                "Students have played this scenario; create a new one to change its window, \
+                 assets, cash or blind mode"
+                    .to_string(),
+            ));
+        }
+    }
+
+    sqlx::query(
+        "UPDATE replay_scenarios SET
+             title          = COALESCE($2, title),
+             description    = COALESCE($3, description),
+             learning_goals = COALESCE($4, learning_goals),
+             start_time     = COALESCE($5, start_time),
+             end_time       = COALESCE($6, end_time),
+             starting_cash  = COALESCE($7, starting_cash),
+             blind          = COALESCE($8, blind),
+             is_active      = COALESCE($9, is_active),
+             updated_at     = NOW()
+         WHERE id = $1",
+    )
+    .bind(scenario_id)
+    .bind(payload.title.map(|t| t.trim().to_string()))
+    .bind(payload.description.map(|d| d.trim().to_string()))
+    .bind(payload.learning_goals.map(trimmed))
+    .bind(from)
+    .bind(to)
+    .bind(starting_cash)
+    .bind(payload.blind)
+    .bind(payload.is_active)
+    .execute(&mut *tx)
+    .await?;
+    if let Some(asset_ids) = &asset_ids {
+        sqlx::query("DELETE FROM replay_scenario_assets WHERE scenario_id = $1")
+            .bind(scenario_id)
+            .execute(&mut *tx)
+            .await?;
+        set_scenario_assets(&mut tx, scenario_id, asset_ids).await?;
+    }
+    tx.commit().await?;
+
+    let scenario = replay_scenarios::load(&state.db.pool, scenario_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    tracing::info!("Admin {} updated replay scenario {}", admin.email, scenario.title);
+    Ok(Json(scenario))
+}
+
+async fn set_scenario_assets(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    scenario_id: Uuid,
+    asset_ids: &[Uuid],
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO replay_scenario_assets (scenario_id, asset_id, position)
+         SELECT $1, asset_id, (position - 1)::smallint
+         FROM UNNEST($2::uuid[]) WITH ORDINALITY AS t(asset_id, position)",
+    )
+    .bind(scenario_id)
+    .bind(asset_ids)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbols_are_normalized_and_restricted() {
+        assert_eq!(validate_symbol(" infy ").unwrap(), "INFY");
+        assert_eq!(validate_symbol("bank-nifty_1").unwrap(), "BANK-NIFTY_1");
+        assert!(validate_symbol("").is_err());
+        assert!(validate_symbol("   ").is_err());
+        assert!(validate_symbol("BRK.B").is_err());
+        assert!(validate_symbol("A B").is_err());
+        assert!(validate_symbol(&"X".repeat(20)).is_ok());
+        assert!(validate_symbol(&"X".repeat(21)).is_err());
+    }
+
+    #[test]
+    fn asset_type_must_be_known() {
+        for t in ASSET_TYPES {
+            assert!(validate_asset_type(t).is_ok());
+        }
+        assert!(validate_asset_type("bond").is_err());
+        assert!(validate_asset_type("Equity").is_err());
+    }
+
+    #[test]
+    fn metadata_bounds() {
+        assert!(validate_metadata(None, None, None, None).is_ok());
+        assert!(validate_metadata(Some(Decimal::ONE), Some(Decimal::new(5, 2)), Some(408065), Some(8)).is_ok());
+        assert!(validate_metadata(Some(Decimal::ZERO), None, None, None).is_err());
+        assert!(validate_metadata(None, Some(Decimal::new(-1, 2)), None, None).is_err());
+        assert!(validate_metadata(None, None, Some(0), None).is_err());
+        assert!(validate_metadata(None, None, Some(u32::MAX as i64), None).is_ok());
+        assert!(validate_metadata(None, None, Some(u32::MAX as i64 + 1), None).is_err());
+        assert!(validate_metadata(None, None, None, Some(-1)).is_err());
+        assert!(validate_metadata(None, None, None, Some(9)).is_err());
+    }
+
+    #[test]
+    fn new_assets_get_the_factor_of_their_market() {
+        assert_eq!(default_price_model("crypto", None, None).factor, "crypto");
+        assert_eq!(default_price_model("fx", Some("NSE"), None).factor, "fx");
+        assert_eq!(default_price_model("equity", Some("nse"), None).factor, "nse");
+        assert_eq!(default_price_model("equity", Some("NASDAQ"), None).factor, "market");
+        assert_eq!(default_price_model("equity", None, Some(42.0)).base_price, 42.0);
+        assert_eq!(default_price_model("etf", None, None).base_price, PriceModel::fallback().base_price);
+    }
+}
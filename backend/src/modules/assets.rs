@@ -1,15 +1,25 @@
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     routing::get,
     Router, Json,
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use crate::{error::Result, modules::AppState};
+use rust_decimal::Decimal;
+use crate::{
+    error::{AppError, Result},
+    modules::AppState,
+    services::asset_stats::{self, AssetStats},
+};
+
+/// Default / maximum range (days) for `/assets/:id` statistics.
+const DEFAULT_STATS_DAYS: i64 = 30;
+const MAX_STATS_DAYS: i64 = 365;
 
 pub fn routes(state: AppState) -> Router {
     Router::new()
         .route("/", get(list_assets))
+        .route("/:asset_id", get(get_asset))
         .with_state(state)
 }
 
@@ -19,37 +29,141 @@ struct AssetResponse {
     symbol: String,
     name: String,
     r#type: String,
+    currency: String,
+    last_price: Option<Decimal>,
+    /// % change of the last price against the close one day earlier.
+    change_1d: Option<Decimal>,
 }
 
 #[derive(Debug, Deserialize)]
 struct AssetsQuery {
     r#type: Option<String>,
+    /// Case-insensitive substring match on symbol or name.
+    q: Option<String>,
+    /// `symbol` (default), `name`, `last_price` or `change_1d`.
+    sort: Option<String>,
+    /// `asc` (default) or `desc`.
+    order: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AssetDetailQuery {
+    days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct AssetInfo {
+    id: Uuid,
+    symbol: String,
+    name: String,
+    r#type: String,
+    exchange: Option<String>,
+    currency: String,
+    sector: Option<String>,
+    lot_size: Decimal,
+    tick_size: Decimal,
+    display_name: Option<String>,
+    display_decimals: i16,
+    logo_url: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+struct AssetDetailResponse {
+    #[serde(flatten)]
+    asset: AssetInfo,
+    stats: AssetStats,
+}
+
+/// Public — tradable assets; FX series are conversion data and left out.
 async fn list_assets(
     State(state): State<AppState>,
     Query(params): Query<AssetsQuery>,
 ) -> Result<Json<Vec<AssetResponse>>> {
-    let assets = if let Some(asset_type) = params.r#type {
-        sqlx::query_as::<_, AssetResponse>(
-            r#"SELECT id, symbol, name, asset_type as "type" 
-               FROM assets 
-               WHERE is_active = true AND asset_type = $1
-               ORDER BY symbol"#
-        )
-        .bind(asset_type)
-        .fetch_all(&state.db.pool)
-        .await?
-    } else {
-        sqlx::query_as::<_, AssetResponse>(
-            r#"SELECT id, symbol, name, asset_type as "type" 
-               FROM assets 
-               WHERE is_active = true
-               ORDER BY symbol"#
-        )
-        .fetch_all(&state.db.pool)
-        .await?
+    let sort_column = match params.sort.as_deref().unwrap_or("symbol") {
+        "symbol" => "a.symbol",
+        "name" => "a.name",
+        "last_price" => "last_price",
+        "change_1d" => "change_1d",
+        other => {
+            return Err(AppError::Validation(format!("Unknown sort field '{}'", other)));
+        }
+    };
+    let direction = match params.order.as_deref().unwrap_or("asc") {
+        "asc" => "ASC",
+        "desc" => "DESC",
+        _ => return Err(AppError::Validation("order must be 'asc' or 'desc'".to_string())),
     };
-    
+    let pattern = params
+        .q
+        .map(|q| q.trim().to_string())
+        .filter(|q| !q.is_empty())
+        .map(|q| format!("%{}%", q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")));
+
+    let assets = sqlx::query_as::<_, AssetResponse>(&format!(
+        r#"SELECT a.id, a.symbol, a.name, a.asset_type as "type", a.currency,
+                  last.close AS last_price,
+                  CASE WHEN prev.close > 0
+                       THEN ROUND((last.close - prev.close) / prev.close * 100, 4)
+                  END AS change_1d
+           FROM assets a
+           LEFT JOIN LATERAL (
+               SELECT close, timestamp FROM market_prices
+               WHERE asset_id = a.id ORDER BY timestamp DESC LIMIT 1
+           ) last ON true
+           LEFT JOIN LATERAL (
+               SELECT close FROM market_prices
+               WHERE asset_id = a.id AND timestamp <= last.timestamp - INTERVAL '1 day'
+               ORDER BY timestamp DESC LIMIT 1
+           ) prev ON true
+           WHERE a.is_active = true
+             AND a.asset_type <> 'fx'
+             AND ($1::text IS NULL OR a.asset_type = $1)
+             AND ($2::text IS NULL OR a.symbol ILIKE $2 OR a.name ILIKE $2)
+           ORDER BY {} {} NULLS LAST, a.symbol"#,
+        sort_column, direction
+    ))
+    .bind(params.r#type)
+    .bind(pattern)
+    .fetch_all(&state.db.pool)
+    .await?;
+
     Ok(Json(assets))
 }
+
+/// Public — asset metadata plus statistics over the last `days` (default 30).
+async fn get_asset(
+    State(state): State<AppState>,
+    Path(asset_id): Path<Uuid>,
+    Query(params): Query<AssetDetailQuery>,
+) -> Result<Json<AssetDetailResponse>> {
+    let days = params.days.unwrap_or(DEFAULT_STATS_DAYS);
+    if !(1..=MAX_STATS_DAYS).contains(&days) {
+        return Err(AppError::Validation(format!(
+            "days must be between 1 and {}",
+            MAX_STATS_DAYS
+        )));
+    }
+
+    let asset = sqlx::query_as::<_, AssetInfo>(
+        r#"SELECT id, symbol, name, asset_type as "type", exchange, currency, sector,
+                  lot_size, tick_size, display_name, display_decimals, logo_url
+           FROM assets
+           WHERE id = $1 AND is_active = true"#,
+    )
+    .bind(asset_id)
+    .fetch_optional(&state.db.pool)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    let stats = asset_stats::compute(
+        &state.db.pool,
+        &state.calendar,
+        asset.id,
+        asset.exchange.as_deref().unwrap_or(""),
+        days,
+        state.config.retention_minute_days,
+    )
+    .await?;
+
+    Ok(Json(AssetDetailResponse { asset, stats }))
+}
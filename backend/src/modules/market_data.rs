@@ -5,62 +5,274 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use rust_decimal::Decimal;
-use chrono::NaiveDateTime;
-use crate::{error::{AppError, Result}, modules::AppState};
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use std::collections::BTreeMap;
+use crate::{
+    error::{AppError, Result},
+    modules::AppState,
+    services::{
+        fx,
+        indicators::{self, IndicatorSpec, Interval},
+        market_depth::{self, DepthFrame},
+        price_history::{self, Candle},
+        trading_calendar::{Holiday, Session},
+        TradingCalendar,
+    },
+};
+
+/// Longest range `/calendar` will enumerate sessions for.
+const MAX_CALENDAR_DAYS: i64 = 366;
 
 pub fn routes(state: AppState) -> Router {
     Router::new()
+        .route("/calendar", get(get_trading_calendar))
         .route("/:asset_id", get(get_historical_prices))
+        .route("/:asset_id/indicators", get(get_indicators))
+        .route("/:asset_id/depth", get(get_depth))
         .with_state(state)
 }
 
-#[derive(Debug, Serialize, sqlx::FromRow)]
-struct PriceCandle {
-    timestamp: NaiveDateTime,
-    open: Decimal,
-    high: Decimal,
-    low: Decimal,
-    close: Decimal,
+#[derive(Debug, Deserialize)]
+struct PriceQuery {
+    from: String,
+    to: String,
+    /// Display currency; defaults to the asset's own quote currency.
+    currency: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
-struct PriceQuery {
+struct IndicatorQuery {
     from: String,
     to: String,
+    /// `1m` (default), `5m`, `15m`, `30m`, `1h`, `4h` or `1d`.
+    interval: Option<String>,
+    /// e.g. `sma:20,ema:50,rsi:14,macd:12:26:9,bb:20:2,vwap,atr:14`
+    indicators: String,
+}
+
+#[derive(Debug, Serialize)]
+struct IndicatorResponse {
+    asset_id: Uuid,
+    interval: &'static str,
+    candles: Vec<Candle>,
+    /// One value per candle; `null` where an indicator has too little history.
+    indicators: BTreeMap<String, Vec<Option<f64>>>,
+}
+
+/// Either a single snapshot (latest at or before `at`, default now) or every
+/// snapshot in `from`..`to`.
+#[derive(Debug, Deserialize)]
+struct DepthQuery {
+    at: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    /// Cap on snapshots returned for a range (default and max 2000).
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct DepthResponse {
+    asset_id: Uuid,
+    snapshots: Vec<DepthFrame>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CalendarQuery {
+    exchange: Option<String>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize)]
+struct CalendarResponse {
+    exchange: String,
+    timezone: &'static str,
+    always_open: bool,
+    is_open: bool,
+    next_session: Option<Session>,
+    sessions: Vec<Session>,
+    holidays: Vec<Holiday>,
+}
+
+/// Public — trading sessions and holidays of an exchange (default NSE).
+/// `from`/`to` are exchange-local dates; defaults to the next 7 days.
+async fn get_trading_calendar(
+    State(state): State<AppState>,
+    Query(params): Query<CalendarQuery>,
+) -> Result<Json<CalendarResponse>> {
+    let exchange = params
+        .exchange
+        .unwrap_or_else(|| "NSE".to_string())
+        .to_ascii_uppercase();
+    let now = Utc::now().naive_utc();
+    let from = params.from.unwrap_or_else(|| now.date());
+    let to = params.to.unwrap_or(from + Duration::days(7));
+
+    if to < from {
+        return Err(AppError::Validation("'to' must not be before 'from'".to_string()));
+    }
+    if (to - from).num_days() > MAX_CALENDAR_DAYS {
+        return Err(AppError::Validation(format!(
+            "Calendar range is limited to {} days",
+            MAX_CALENDAR_DAYS
+        )));
+    }
+
+    let calendar = &state.calendar;
+    let always_open = TradingCalendar::is_24x7(&exchange);
+
+    Ok(Json(CalendarResponse {
+        timezone: TradingCalendar::timezone(&exchange),
+        always_open,
+        is_open: calendar.is_open(&exchange, now),
+        next_session: if always_open {
+            None
+        } else {
+            calendar.current_or_next_session(&exchange, now)
+        },
+        sessions: if always_open {
+            Vec::new()
+        } else {
+            calendar.sessions(&exchange, from, to)
+        },
+        holidays: calendar.holidays(&exchange, from, to),
+        exchange,
+    }))
 }
 
 async fn get_historical_prices(
     State(state): State<AppState>,
     Path(asset_id): Path<Uuid>,
     Query(params): Query<PriceQuery>,
-) -> Result<Json<Vec<PriceCandle>>> {
+) -> Result<Json<Vec<Candle>>> {
     // Parse timestamps
-    let from = chrono::NaiveDateTime::parse_from_str(&params.from, "%Y-%m-%dT%H:%M:%S%.fZ")
-        .or_else(|_| chrono::NaiveDateTime::parse_from_str(&params.from, "%Y-%m-%dT%H:%M:%SZ"))
-        .map_err(|_| AppError::Validation("Invalid 'from' timestamp format".to_string()))?;
-    
-    let to = chrono::NaiveDateTime::parse_from_str(&params.to, "%Y-%m-%dT%H:%M:%S%.fZ")
-        .or_else(|_| chrono::NaiveDateTime::parse_from_str(&params.to, "%Y-%m-%dT%H:%M:%SZ"))
-        .map_err(|_| AppError::Validation("Invalid 'to' timestamp format".to_string()))?;
+    let from = parse_timestamp(&params.from, "from")?;
+    let to = parse_timestamp(&params.to, "to")?;
     
-    let prices = sqlx::query_as::<_, PriceCandle>(
-        r#"
-        SELECT timestamp, open, high, low, close
-        FROM market_prices
-        WHERE asset_id = $1 AND timestamp BETWEEN $2 AND $3
-        ORDER BY timestamp ASC
-        "#
-    )
-    .bind(asset_id)
-    .bind(from)
-    .bind(to)
-    .fetch_all(&state.db.pool)
-    .await?;
+    // 1-minute candles where still retained, hourly/daily rollups before that.
+    let mut prices = price_history::candles(&state.db.pool, asset_id, from, to).await?;
     
     if prices.is_empty() {
         return Err(AppError::NotFound);
     }
+
+    if let Some(currency) = params.currency {
+        let target = fx::normalize_currency(&currency)
+            .ok_or_else(|| AppError::Validation("Invalid 'currency'".to_string()))?;
+        let quote = fx::asset_currency(&state.db.pool, asset_id)
+            .await?
+            .ok_or(AppError::NotFound)?;
+        prices = fx::convert_candles(&state.db.pool, prices, &quote, &target)
+            .await?
+            .ok_or_else(|| {
+                AppError::Validation(format!("No FX rate from {} to {}", quote, target))
+            })?;
+    }
     
     Ok(Json(prices))
 }
+
+/// Parse an ISO-8601 UTC timestamp (`2026-01-06T09:15:00Z`, fractional
+/// seconds optional).
+fn parse_timestamp(value: &str, field: &str) -> Result<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.fZ")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%SZ"))
+        .map_err(|_| AppError::Validation(format!("Invalid '{}' timestamp format", field)))
+}
+
+/// Public — candles resampled to `interval` with indicator lines aligned to
+/// them. Same query path as `get_historical_prices`; indicators are warmed
+/// up on bars before `from`, so their first values are already valid.
+async fn get_indicators(
+    State(state): State<AppState>,
+    Path(asset_id): Path<Uuid>,
+    Query(params): Query<IndicatorQuery>,
+) -> Result<Json<IndicatorResponse>> {
+    let from = parse_timestamp(&params.from, "from")?;
+    let to = parse_timestamp(&params.to, "to")?;
+    if to < from {
+        return Err(AppError::Validation("'to' must not be before 'from'".to_string()));
+    }
+    let interval = Interval::parse(params.interval.as_deref().unwrap_or("1m")).ok_or_else(|| {
+        AppError::Validation("interval must be one of 1m, 5m, 15m, 30m, 1h, 4h, 1d".to_string())
+    })?;
+    if (to - from).num_minutes() / interval.minutes() > indicators::MAX_BARS {
+        return Err(AppError::Validation(format!(
+            "Range covers more than {} bars at {}; use a larger interval",
+            indicators::MAX_BARS,
+            interval.label()
+        )));
+    }
+    let specs = IndicatorSpec::parse_list(&params.indicators).map_err(AppError::Validation)?;
+    if specs.is_empty() {
+        return Err(AppError::Validation("'indicators' must not be empty".to_string()));
+    }
+
+    let series =
+        indicators::compute_for_range(&state.db.pool, asset_id, from, to, interval, &specs).await?;
+    if series.candles.is_empty() {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(Json(IndicatorResponse {
+        asset_id,
+        interval: interval.label(),
+        candles: series.candles,
+        indicators: series.lines,
+    }))
+}
+
+/// Public — stored order-book snapshots (top five levels per side) with
+/// spread, mid and imbalance. Only assets on the live feed have depth.
+async fn get_depth(
+    State(state): State<AppState>,
+    Path(asset_id): Path<Uuid>,
+    Query(params): Query<DepthQuery>,
+) -> Result<Json<DepthResponse>> {
+    let snapshots = match (&params.from, &params.to) {
+        (Some(from), Some(to)) => {
+            if params.at.is_some() {
+                return Err(AppError::Validation(
+                    "Use either 'at' or 'from'/'to', not both".to_string(),
+                ));
+            }
+            let from = parse_timestamp(from, "from")?;
+            let to = parse_timestamp(to, "to")?;
+            if to < from {
+                return Err(AppError::Validation("'to' must not be before 'from'".to_string()));
+            }
+            let limit = params.limit.unwrap_or(market_depth::MAX_SNAPSHOTS);
+            if !(1..=market_depth::MAX_SNAPSHOTS).contains(&limit) {
+                return Err(AppError::Validation(format!(
+                    "limit must be between 1 and {}",
+                    market_depth::MAX_SNAPSHOTS
+                )));
+            }
+            market_depth::range(&state.db.pool, asset_id, from, to, limit).await?
+        }
+        (None, None) => {
+            let at = match &params.at {
+                Some(at) => parse_timestamp(at, "at")?,
+                None => Utc::now().naive_utc(),
+            };
+            market_depth::at_or_before(&state.db.pool, asset_id, at)
+                .await?
+                .into_iter()
+                .collect()
+        }
+        _ => {
+            return Err(AppError::Validation(
+                "'from' and 'to' must be given together".to_string(),
+            ))
+        }
+    };
+
+    if snapshots.is_empty() {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(Json(DepthResponse {
+        asset_id,
+        snapshots: snapshots.into_iter().map(DepthFrame::from).collect(),
+    }))
+}
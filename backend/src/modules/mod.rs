@@ -1,3 +1,4 @@
+pub mod admin;
 pub mod auth;
 pub mod users;
 pub mod wallet;
@@ -7,20 +8,36 @@ pub mod replay;
 pub mod contests;
 pub mod websocket;
 
-use crate::{config::AppConfig, db::Database};
+use crate::{
+    config::AppConfig,
+    db::Database,
+    services::{GapRepairer, MarketDataControl, TradingCalendar},
+};
 use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: Database,
     pub config: Arc<AppConfig>,
+    pub calendar: Arc<TradingCalendar>,
+    pub provider: Arc<MarketDataControl>,
+    pub repairer: Arc<GapRepairer>,
 }
 
 impl AppState {
-    pub fn new(db: Database, config: AppConfig) -> Self {
+    pub fn new(
+        db: Database,
+        config: AppConfig,
+        calendar: Arc<TradingCalendar>,
+        provider: Arc<MarketDataControl>,
+        repairer: Arc<GapRepairer>,
+    ) -> Self {
         Self {
             db,
             config: Arc::new(config),
+            calendar,
+            provider,
+            repairer,
         }
     }
 }
@@ -1,36 +1,258 @@
 use axum::{
-    extract::{Path, State},
-    routing::post,
+    extract::{Path, Query, State},
+    routing::{delete, get, post, put},
     Router, Json,
 };
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use crate::{error::Result, middleware::SessionUser, modules::AppState};
+use crate::{
+    error::Result,
+    middleware::SessionUser,
+    modules::AppState,
+    services::{
+        fx,
+        replay::{self, Fill, ReplayMode, ReplayPortfolio, TradeSide},
+        replay_orders::{self, OrderRequest, OrderType, ReplayOrder},
+        replay_predictions::{self, Direction, Prediction, PredictionSummary},
+        replay_report::{self, ReplayReport},
+        replay_scenarios::{self, BlindMask, LeaderboardEntry, Scenario},
+    },
+};
 
 pub fn routes(state: AppState) -> Router {
     Router::new()
-        .route("/", post(create_replay_session))
+        .route("/", post(create_replay_session).get(list_sessions))
+        .route("/scenarios", get(list_scenarios))
+        .route("/scenarios/:scenario_id", get(get_scenario))
+        .route("/scenarios/:scenario_id/start", post(start_scenario))
+        .route("/scenarios/:scenario_id/leaderboard", get(scenario_leaderboard))
+        .route("/:replay_id", get(get_session).delete(delete_session))
         .route("/:replay_id/trade", post(place_demo_trade))
+        .route("/:replay_id/portfolio", get(get_portfolio))
+        .route("/:replay_id/orders", post(place_order).get(list_orders))
+        .route("/:replay_id/orders/:order_id", delete(cancel_order))
+        .route("/:replay_id/predictions", post(submit_prediction).get(list_predictions))
+        .route("/:replay_id/report", get(get_report).post(regenerate_report))
+        .route("/:replay_id/trades/:trade_id/note", put(set_trade_note))
         .with_state(state)
 }
 
 #[derive(Debug, Deserialize)]
 struct CreateReplayRequest {
-    asset_id: Uuid,
+    /// One asset, or several in `asset_ids` (played on one clock).
+    asset_id: Option<Uuid>,
+    asset_ids: Option<Vec<Uuid>>,
     from: String,
     to: String,
+    /// Paper-trading cash, in the assets' currency (default 100,000).
+    starting_cash: Option<f64>,
+    /// `stream` (default) or `bar_by_bar`.
+    mode: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StartScenarioQuery {
+    /// `stream` (default) or `bar_by_bar`.
+    mode: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
 struct CreateReplayResponse {
     replay_id: Uuid,
+    asset_ids: Vec<Uuid>,
+    mode: ReplayMode,
     ws_url: String,
 }
 
 #[derive(Debug, Deserialize)]
 struct PlaceTradeRequest {
+    /// Required when the replay covers several assets.
+    asset_id: Option<Uuid>,
     side: String,
     quantity: f64,
+    /// Optional display currency for the returned `display` block.
+    currency: Option<String>,
+}
+
+/// Default / maximum sessions per page of `GET /replay`.
+const DEFAULT_SESSION_LIMIT: i64 = 20;
+const MAX_SESSION_LIMIT: i64 = 100;
+
+#[derive(Debug, Deserialize)]
+struct SessionListQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+const SESSION_COLUMNS: &str = "rs.id,
+     ARRAY(SELECT rsa.asset_id FROM replay_session_assets rsa
+           WHERE rsa.replay_id = rs.id ORDER BY rsa.position) AS asset_ids,
+     ARRAY(SELECT a.symbol FROM replay_session_assets rsa JOIN assets a ON a.id = rsa.asset_id
+           WHERE rsa.replay_id = rs.id ORDER BY rsa.position) AS symbols,
+     rs.start_time, rs.end_time,
+     CASE WHEN rs.cursor_time IS NULL THEN 'not_started'
+          WHEN rs.finished THEN 'finished'
+          ELSE 'in_progress' END AS status,
+     rs.mode, rs.scenario_id,
+     COALESCE((SELECT s.blind FROM replay_scenarios s WHERE s.id = rs.scenario_id), FALSE)
+         AND NOT rs.finished AS blind,
+     rs.cursor_time, rs.starting_cash, rs.cash, rs.created_at";
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct SessionSummary {
+    id: Uuid,
+    asset_ids: Vec<Uuid>,
+    symbols: Vec<String>,
+    start_time: chrono::NaiveDateTime,
+    end_time: chrono::NaiveDateTime,
+    /// `not_started`, `in_progress` or `finished` (the last bar was sent).
+    status: String,
+    /// `stream` or `bar_by_bar`.
+    mode: String,
+    scenario_id: Option<Uuid>,
+    /// Dates and symbols are hidden until the replay ends.
+    blind: bool,
+    cursor_time: Option<chrono::NaiveDateTime>,
+    starting_cash: Decimal,
+    cash: Decimal,
+    created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+struct SessionPage {
+    sessions: Vec<SessionSummary>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct TradeRecord {
+    id: Uuid,
+    asset_id: Uuid,
+    order_id: Option<Uuid>,
+    side: String,
+    price: Decimal,
+    quantity: Decimal,
+    /// Replay time of the bar it filled on.
+    timestamp: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+struct SessionDetail {
+    #[serde(flatten)]
+    session: SessionSummary,
+    trades: Vec<TradeRecord>,
+    portfolio: ReplayPortfolio,
+    /// Bar-by-bar sessions only.
+    predictions: Option<PredictionSummary>,
+}
+
+/// A trade with its realized P&L and the student's note.
+#[derive(Debug, Serialize)]
+struct JournalEntry {
+    id: Uuid,
+    asset_id: Uuid,
+    symbol: String,
+    order_id: Option<Uuid>,
+    side: String,
+    price: Decimal,
+    quantity: Decimal,
+    timestamp: chrono::NaiveDateTime,
+    /// Sells only.
+    realized_pnl: Option<f64>,
+    note: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ReportResponse {
+    generated_at: chrono::NaiveDateTime,
+    report: ReplayReport,
+    journal: Vec<JournalEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TradeNoteRequest {
+    /// `null` clears the note.
+    note: Option<String>,
+}
+
+const MAX_NOTE_LENGTH: usize = 2000;
+
+#[derive(Debug, Deserialize)]
+struct PredictionRequest {
+    /// Required when the replay covers several assets.
+    asset_id: Option<Uuid>,
+    /// `up`, `down` or `flat`.
+    direction: Option<String>,
+    target_price: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct PredictionPage {
+    summary: PredictionSummary,
+    predictions: Vec<Prediction>,
+}
+
+/// A scenario as students see it; blind scenarios keep their window and
+/// assets hidden.
+#[derive(Debug, Serialize)]
+struct ScenarioCard {
+    id: Uuid,
+    title: String,
+    description: String,
+    learning_goals: Vec<String>,
+    blind: bool,
+    starting_cash: Decimal,
+    asset_count: usize,
+    duration_minutes: i64,
+    start_time: Option<chrono::NaiveDateTime>,
+    end_time: Option<chrono::NaiveDateTime>,
+    asset_ids: Option<Vec<Uuid>>,
+    symbols: Option<Vec<String>>,
+}
+
+impl From<Scenario> for ScenarioCard {
+    fn from(s: Scenario) -> Self {
+        let shown = !s.blind;
+        Self {
+            id: s.id,
+            title: s.title,
+            description: s.description,
+            learning_goals: s.learning_goals,
+            blind: s.blind,
+            starting_cash: s.starting_cash,
+            asset_count: s.asset_ids.len(),
+            duration_minutes: (s.end_time - s.start_time).num_minutes(),
+            start_time: shown.then_some(s.start_time),
+            end_time: shown.then_some(s.end_time),
+            asset_ids: shown.then_some(s.asset_ids),
+            symbols: shown.then_some(s.symbols),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaceOrderRequest {
+    /// Required when the replay covers several assets.
+    asset_id: Option<Uuid>,
+    side: String,
+    quantity: f64,
+    /// `market`, `limit`, `stop` or `stop_limit`.
+    #[serde(rename = "type")]
+    order_type: String,
+    limit_price: Option<f64>,
+    stop_price: Option<f64>,
+    /// Bracket legs for a buy entry.
+    stop_loss: Option<f64>,
+    take_profit: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrderListQuery {
+    /// `open` (default), `filled`, `cancelled`, `rejected` or `all`.
+    status: Option<String>,
 }
 
 async fn create_replay_session(
@@ -38,83 +260,956 @@ async fn create_replay_session(
     session: SessionUser,
     Json(payload): Json<CreateReplayRequest>,
 ) -> Result<Json<CreateReplayResponse>> {
-    let from = chrono::NaiveDateTime::parse_from_str(&payload.from, "%Y-%m-%dT%H:%M:%SZ")
-        .map_err(|_| crate::error::AppError::Validation("Invalid from timestamp".to_string()))?;
+    let from = parse_time("from", &payload.from)?;
+    let to = parse_time("to", &payload.to)?;
+    let starting_cash = parse_starting_cash(payload.starting_cash)?;
+    let mode = parse_mode(payload.mode.as_deref())?;
 
-    let to = chrono::NaiveDateTime::parse_from_str(&payload.to, "%Y-%m-%dT%H:%M:%SZ")
-        .map_err(|_| crate::error::AppError::Validation("Invalid to timestamp".to_string()))?;
+    let asset_ids = match (payload.asset_id, payload.asset_ids) {
+        (Some(id), None) => vec![id],
+        (None, Some(ids)) => ids,
+        _ => {
+            return Err(crate::error::AppError::Validation(
+                "Give either asset_id or asset_ids".to_string(),
+            ))
+        }
+    };
+    let asset_ids = check_assets(&state.db.pool, asset_ids).await?;
 
+    let replay_id =
+        insert_session(&state, session.user_id, from, to, starting_cash, &asset_ids, mode, None).await?;
+    Ok(Json(CreateReplayResponse {
+        replay_id,
+        asset_ids,
+        mode,
+        ws_url: format!("/ws/replay/{}", replay_id),
+    }))
+}
+
+/// Parse a `2026-10-16T05:00:00Z` request time.
+pub(crate) fn parse_time(name: &str, value: &str) -> Result<chrono::NaiveDateTime> {
+    chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%SZ")
+        .map_err(|_| crate::error::AppError::Validation(format!("Invalid {} timestamp", name)))
+}
+
+fn parse_mode(mode: Option<&str>) -> Result<ReplayMode> {
+    match mode {
+        Some(mode) => ReplayMode::parse(mode).ok_or_else(|| {
+            crate::error::AppError::Validation("mode must be stream or bar_by_bar".to_string())
+        }),
+        None => Ok(ReplayMode::Stream),
+    }
+}
+
+/// Paper-trading cash, rounded to cents (default 100,000).
+pub(crate) fn parse_starting_cash(cash: Option<f64>) -> Result<Decimal> {
+    match cash {
+        Some(cash) => Decimal::from_f64_retain(cash)
+            .filter(|c| *c > Decimal::ZERO)
+            .map(|c| c.round_dp(2))
+            .ok_or_else(|| {
+                crate::error::AppError::Validation("starting_cash must be positive".to_string())
+            }),
+        None => Ok(Decimal::from(replay::DEFAULT_STARTING_CASH)),
+    }
+}
+
+/// Deduplicate a replay's assets and check they exist and share a quote
+/// currency (cash and P&L are in one currency).
+pub(crate) async fn check_assets(pool: &sqlx::PgPool, mut asset_ids: Vec<Uuid>) -> Result<Vec<Uuid>> {
+    let mut seen = std::collections::HashSet::new();
+    asset_ids.retain(|id| seen.insert(*id));
+    if asset_ids.is_empty() || asset_ids.len() > replay::MAX_ASSETS {
+        return Err(crate::error::AppError::Validation(format!(
+            "A replay covers 1 to {} assets",
+            replay::MAX_ASSETS
+        )));
+    }
+
+    let currencies: Vec<(Uuid, String)> =
+        sqlx::query_as("SELECT id, currency FROM assets WHERE id = ANY($1)")
+            .bind(&asset_ids)
+            .fetch_all(pool)
+            .await?;
+    if currencies.len() != asset_ids.len() {
+        return Err(crate::error::AppError::Validation("Unknown asset".to_string()));
+    }
+    if currencies.iter().any(|(_, c)| *c != currencies[0].1) {
+        return Err(crate::error::AppError::Validation(
+            "Replay assets must share a quote currency".to_string(),
+        ));
+    }
+    Ok(asset_ids)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn insert_session(
+    state: &AppState,
+    user_id: Uuid,
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+    starting_cash: Decimal,
+    asset_ids: &[Uuid],
+    mode: ReplayMode,
+    scenario_id: Option<Uuid>,
+) -> Result<Uuid> {
     let replay_id = Uuid::new_v4();
+    let mut tx = state.db.pool.begin().await?;
 
     sqlx::query(
-        "INSERT INTO replay_sessions (id, user_id, asset_id, start_time, end_time, created_at)
-         VALUES ($1, $2, $3, $4, $5, NOW())",
+        "INSERT INTO replay_sessions
+             (id, user_id, start_time, end_time, starting_cash, cash, mode, scenario_id, created_at)
+         VALUES ($1, $2, $3, $4, $5, $5, $6, $7, NOW())",
     )
     .bind(replay_id)
-    .bind(session.user_id)
-    .bind(payload.asset_id)
+    .bind(user_id)
     .bind(from)
     .bind(to)
-    .execute(&state.db.pool)
+    .bind(starting_cash)
+    .bind(mode.as_str())
+    .bind(scenario_id)
+    .execute(&mut *tx)
     .await?;
 
-    Ok(Json(CreateReplayResponse {
+    sqlx::query(
+        "INSERT INTO replay_session_assets (replay_id, asset_id, position)
+         SELECT $1, asset_id, (position - 1)::smallint
+         FROM UNNEST($2::uuid[]) WITH ORDINALITY AS t(asset_id, position)",
+    )
+    .bind(replay_id)
+    .bind(asset_ids)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(replay_id)
+}
+
+/// Serialize a response, hiding dates and symbols while a blind scenario runs.
+fn masked<T: Serialize>(mask: Option<&BlindMask>, body: &T) -> Json<serde_json::Value> {
+    let mut value = serde_json::to_value(body).unwrap_or_default();
+    if let Some(mask) = mask {
+        mask.apply(&mut value);
+    }
+    Json(value)
+}
+
+/// A session row locked for trading, with the bar on screen.
+struct TradingSession {
+    asset_id: Uuid,
+    currency: String,
+    cash: Decimal,
+    cursor_time: chrono::NaiveDateTime,
+    cursor_price: Decimal,
+}
+
+/// Lock the session for a trade or order in `asset_id` (optional when the
+/// replay has one asset). Fills happen at the bar the replay socket last
+/// sent, so the replay must have started and the asset must have a bar.
+async fn lock_session(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    replay_id: Uuid,
+    user_id: Uuid,
+    asset_id: Option<Uuid>,
+) -> Result<TradingSession> {
+    #[derive(sqlx::FromRow)]
+    struct ReplaySession {
+        cash: Decimal,
+        start_time: chrono::NaiveDateTime,
+        end_time: chrono::NaiveDateTime,
+        cursor_time: Option<chrono::NaiveDateTime>,
+        finished: bool,
+        from_scenario: bool,
+    }
+
+    #[derive(sqlx::FromRow)]
+    struct SessionAsset {
+        asset_id: Uuid,
+        currency: String,
+        cursor_price: Option<Decimal>,
+    }
+
+    let rs = sqlx::query_as::<_, ReplaySession>(
+        "SELECT cash, start_time, end_time, cursor_time, finished,
+                scenario_id IS NOT NULL AS from_scenario
+         FROM replay_sessions
+         WHERE id = $1 AND user_id = $2
+         FOR UPDATE",
+    )
+    .bind(replay_id)
+    .bind(user_id)
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or(crate::error::AppError::NotFound)?;
+    // Scenario returns are ranked, so trading closes with the last bar
+    if rs.from_scenario && rs.finished {
+        return Err(crate::error::AppError::Conflict(
+            "This scenario has ended; trading is closed".to_string(),
+        ));
+    }
+
+    let cursor_time = rs.cursor_time.ok_or_else(|| {
+        crate::error::AppError::Validation(
+            "Replay has not started; trades fill at the bar on screen".to_string(),
+        )
+    })?;
+    if cursor_time < rs.start_time || cursor_time > rs.end_time {
+        return Err(crate::error::AppError::Validation(
+            "Replay cursor is outside the session window".to_string(),
+        ));
+    }
+
+    let mut assets = sqlx::query_as::<_, SessionAsset>(
+        "SELECT rsa.asset_id, a.currency, rsa.cursor_price
+         FROM replay_session_assets rsa JOIN assets a ON a.id = rsa.asset_id
+         WHERE rsa.replay_id = $1 AND ($2::uuid IS NULL OR rsa.asset_id = $2)",
+    )
+    .bind(replay_id)
+    .bind(asset_id)
+    .fetch_all(&mut **tx)
+    .await?;
+    let asset = match (asset_id, assets.len()) {
+        (_, 1) => assets.remove(0),
+        (Some(_), _) => {
+            return Err(crate::error::AppError::Validation(
+                "Asset is not part of this replay".to_string(),
+            ))
+        }
+        (None, _) => {
+            return Err(crate::error::AppError::Validation(
+                "asset_id is required: this replay covers several assets".to_string(),
+            ))
+        }
+    };
+    let cursor_price = asset.cursor_price.ok_or_else(|| {
+        crate::error::AppError::Validation("No bar for this asset yet".to_string())
+    })?;
+
+    Ok(TradingSession {
+        asset_id: asset.asset_id,
+        currency: asset.currency,
+        cash: rs.cash,
+        cursor_time,
+        cursor_price,
+    })
+}
+
+fn parse_side(side: &str) -> Result<TradeSide> {
+    TradeSide::parse(side)
+        .ok_or_else(|| crate::error::AppError::Validation("Side must be 'buy' or 'sell'".to_string()))
+}
+
+fn parse_quantity(quantity: f64) -> Result<Decimal> {
+    Decimal::from_f64_retain(quantity)
+        .filter(|q| *q > Decimal::ZERO)
+        .ok_or_else(|| crate::error::AppError::Validation("Quantity must be positive".to_string()))
+}
+
+fn parse_price(name: &str, price: Option<f64>) -> Result<Option<Decimal>> {
+    price
+        .map(|p| {
+            Decimal::from_f64_retain(p)
+                .map(|d| d.round_dp(4))
+                .ok_or_else(|| crate::error::AppError::Validation(format!("Invalid {}", name)))
+        })
+        .transpose()
+}
+
+/// A quantity the student sent for `asset_id`, in real shares.
+fn real_quantity(mask: Option<&BlindMask>, asset_id: Uuid, quantity: Decimal) -> Result<Decimal> {
+    let Some(mask) = mask else {
+        return Ok(quantity);
+    };
+    Some(mask.real_quantity(asset_id, quantity))
+        .filter(|q| *q > Decimal::ZERO)
+        .ok_or_else(|| crate::error::AppError::Validation("Quantity is too small".to_string()))
+}
+
+/// Fill `quantity` at the cursor price, booking it to the account.
+async fn fill_at_cursor(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    replay_id: Uuid,
+    session: &TradingSession,
+    order_id: Option<Uuid>,
+    side: TradeSide,
+    quantity: Decimal,
+) -> Result<()> {
+    let mut holding = replay::holding(tx, replay_id, session.asset_id).await?;
+    let mut cash = session.cash;
+    holding
+        .fill(side, quantity, session.cursor_price, &mut cash)
+        .map_err(crate::error::AppError::Validation)?;
+    let fill = Fill {
         replay_id,
-        ws_url: format!("/ws/replay/{}", replay_id),
-    }))
+        asset_id: session.asset_id,
+        order_id,
+        side,
+        quantity,
+        price: session.cursor_price,
+        timestamp: session.cursor_time,
+    };
+    replay::record_fill(tx, &fill, cash, &holding).await?;
+    Ok(())
 }
 
 async fn place_demo_trade(
     State(state): State<AppState>,
-    _session: SessionUser,
+    session: SessionUser,
     Path(replay_id): Path<Uuid>,
     Json(payload): Json<PlaceTradeRequest>,
 ) -> Result<Json<serde_json::Value>> {
-    if payload.side != "buy" && payload.side != "sell" {
-        return Err(crate::error::AppError::Validation(
-            "Side must be 'buy' or 'sell'".to_string(),
-        ));
+    let side = parse_side(&payload.side)?;
+    let quantity = parse_quantity(payload.quantity)?;
+    let mask = replay_scenarios::blind_mask(&state.db.pool, replay_id).await?;
+    let asset_id = payload.asset_id.map(|id| mask.as_ref().map_or(id, |m| m.real_asset(id)));
+
+    let mut tx = state.db.pool.begin().await?;
+    let rs = lock_session(&mut tx, replay_id, session.user_id, asset_id).await?;
+    let (cursor_time, current_price) = (rs.cursor_time, rs.cursor_price);
+    let quantity = real_quantity(mask.as_ref(), rs.asset_id, quantity)?;
+
+    let display = match payload.currency.as_deref() {
+        Some(code) => {
+            let target = fx::normalize_currency(code).ok_or_else(|| {
+                crate::error::AppError::Validation("Invalid currency".to_string())
+            })?;
+            let price = fx::convert(&state.db.pool, current_price, &rs.currency, &target, cursor_time)
+                .await?
+                .ok_or_else(|| {
+                    crate::error::AppError::Validation(format!(
+                        "No FX rate from {} to {}",
+                        rs.currency, target
+                    ))
+                })?;
+            Some(serde_json::json!({ "currency": target, "price": price }))
+        }
+        None => None,
+    };
+
+    fill_at_cursor(&mut tx, replay_id, &rs, None, side, quantity).await?;
+    tx.commit().await?;
+
+    let portfolio = replay::load_portfolio(&state.db.pool, replay_id).await?;
+
+    Ok(masked(
+        mask.as_ref(),
+        &serde_json::json!({
+            "success": true,
+            "asset_id": rs.asset_id,
+            "price": current_price,
+            "timestamp": cursor_time,
+            "currency": rs.currency,
+            "display": display,
+            "side": side,
+            "quantity": quantity,
+            "portfolio": portfolio,
+        }),
+    ))
+}
+
+/// Place an order. Market orders fill at the cursor; the rest rest until a
+/// streamed bar fills them. `stop_loss` / `take_profit` make a buy a bracket.
+async fn place_order(
+    State(state): State<AppState>,
+    session: SessionUser,
+    Path(replay_id): Path<Uuid>,
+    Json(payload): Json<PlaceOrderRequest>,
+) -> Result<Json<serde_json::Value>> {
+    let request = OrderRequest {
+        side: parse_side(&payload.side)?,
+        order_type: OrderType::parse(&payload.order_type).ok_or_else(|| {
+            crate::error::AppError::Validation(
+                "type must be market, limit, stop or stop_limit".to_string(),
+            )
+        })?,
+        quantity: parse_quantity(payload.quantity)?,
+        limit_price: parse_price("limit_price", payload.limit_price)?,
+        stop_price: parse_price("stop_price", payload.stop_price)?,
+        stop_loss: parse_price("stop_loss", payload.stop_loss)?,
+        take_profit: parse_price("take_profit", payload.take_profit)?,
+    };
+    let mask = replay_scenarios::blind_mask(&state.db.pool, replay_id).await?;
+    let asset_id = payload.asset_id.map(|id| mask.as_ref().map_or(id, |m| m.real_asset(id)));
+
+    let mut tx = state.db.pool.begin().await?;
+    let rs = lock_session(&mut tx, replay_id, session.user_id, asset_id).await?;
+    // A blind student's prices are checked against the price they see
+    let shown_price = mask.as_ref().map_or(rs.cursor_price, |m| m.price(rs.asset_id, rs.cursor_price));
+    request
+        .validate(shown_price)
+        .map_err(crate::error::AppError::Validation)?;
+    let request = match mask.as_ref() {
+        Some(m) => {
+            let real_price = |p: Option<Decimal>| p.map(|p| m.real_price(rs.asset_id, p));
+            OrderRequest {
+                quantity: real_quantity(Some(m), rs.asset_id, request.quantity)?,
+                limit_price: real_price(request.limit_price),
+                stop_price: real_price(request.stop_price),
+                stop_loss: real_price(request.stop_loss),
+                take_profit: real_price(request.take_profit),
+                ..request
+            }
+        }
+        None => request,
+    };
+    // Resting sells already claim part of the position
+    if request.side == TradeSide::Sell {
+        let held = replay::holding(&mut tx, replay_id, rs.asset_id).await?.quantity;
+        let committed = replay_orders::committed_sells(&mut tx, replay_id, rs.asset_id).await?;
+        if request.quantity > held - committed {
+            let shown = |q: Decimal| mask.as_ref().map_or(q, |m| m.quantity(rs.asset_id, q)).normalize();
+            return Err(crate::error::AppError::Validation(format!(
+                "Cannot sell {}; position is {} with {} in open sell orders",
+                shown(request.quantity),
+                shown(held),
+                shown(committed)
+            )));
+        }
     }
 
-    // Fetch the latest price tick for this replay session
-    #[derive(sqlx::FromRow)]
-    struct ReplaySession {
-        asset_id: Uuid,
+    let market = request.order_type == OrderType::Market;
+    let mut entry = replay_orders::insert(
+        &mut tx,
+        replay_id,
+        rs.asset_id,
+        None,
+        "entry",
+        request.side,
+        request.order_type,
+        request.quantity,
+        request.limit_price,
+        request.stop_price,
+        Some(rs.cursor_time),
+    )
+    .await?;
+    if market {
+        fill_at_cursor(&mut tx, replay_id, &rs, Some(entry.id), request.side, request.quantity).await?;
+        entry = replay_orders::mark_filled(&mut tx, entry.id, rs.cursor_price, rs.cursor_time).await?;
     }
 
-    let rs = sqlx::query_as::<_, ReplaySession>(
-        "SELECT asset_id FROM replay_sessions WHERE id = $1",
+    // Bracket legs wait for the entry, or go live now if it already filled
+    let legs_from = market.then_some(rs.cursor_time);
+    let mut orders = vec![entry.clone()];
+    for (role, order_type, limit_price, stop_price) in [
+        ("stop_loss", OrderType::Stop, None, request.stop_loss),
+        ("take_profit", OrderType::Limit, request.take_profit, None),
+    ] {
+        if limit_price.or(stop_price).is_none() {
+            continue;
+        }
+        let leg = replay_orders::insert(
+            &mut tx,
+            replay_id,
+            rs.asset_id,
+            Some(entry.id),
+            role,
+            TradeSide::Sell,
+            order_type,
+            request.quantity,
+            limit_price,
+            stop_price,
+            legs_from,
+        )
+        .await?;
+        orders.push(leg);
+    }
+    tx.commit().await?;
+
+    let portfolio = replay::load_portfolio(&state.db.pool, replay_id).await?;
+    Ok(masked(
+        mask.as_ref(),
+        &serde_json::json!({
+            "success": true,
+            "orders": orders,
+            "portfolio": portfolio,
+        }),
+    ))
+}
+
+/// Orders in the session, oldest first; `?status=` filters (default `open`,
+/// which includes bracket legs still waiting on their entry).
+async fn list_orders(
+    State(state): State<AppState>,
+    session: SessionUser,
+    Path(replay_id): Path<Uuid>,
+    Query(params): Query<OrderListQuery>,
+) -> Result<Json<serde_json::Value>> {
+    let statuses: Vec<String> = match params.status.as_deref().unwrap_or("open") {
+        "all" => Vec::new(),
+        "open" => vec!["open".to_string(), "pending".to_string()],
+        s @ ("filled" | "cancelled" | "rejected") => vec![s.to_string()],
+        _ => {
+            return Err(crate::error::AppError::Validation(
+                "status must be open, filled, cancelled, rejected or all".to_string(),
+            ))
+        }
+    };
+    ensure_owner(&state, replay_id, &session).await?;
+
+    let orders = sqlx::query_as::<_, ReplayOrder>(&format!(
+        "SELECT {} FROM replay_orders
+         WHERE replay_id = $1 AND (cardinality($2::text[]) = 0 OR status = ANY($2))
+         ORDER BY created_at, parent_id IS NOT NULL, id",
+        replay_orders::ORDER_COLUMNS
+    ))
+    .bind(replay_id)
+    .bind(statuses)
+    .fetch_all(&state.db.pool)
+    .await?;
+    let mask = replay_scenarios::blind_mask(&state.db.pool, replay_id).await?;
+    Ok(masked(mask.as_ref(), &orders))
+}
+
+/// Cancel an open order; cancelling a bracket entry cancels its legs.
+async fn cancel_order(
+    State(state): State<AppState>,
+    session: SessionUser,
+    Path((replay_id, order_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>> {
+    let mut tx = state.db.pool.begin().await?;
+    let status: String = sqlx::query_scalar(
+        "SELECT o.status FROM replay_orders o
+         JOIN replay_sessions rs ON rs.id = o.replay_id
+         WHERE o.id = $1 AND o.replay_id = $2 AND rs.user_id = $3
+         FOR UPDATE OF rs",
     )
+    .bind(order_id)
     .bind(replay_id)
+    .bind(session.user_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(crate::error::AppError::NotFound)?;
+    if status != "open" && status != "pending" {
+        return Err(crate::error::AppError::Conflict(format!("Order is already {}", status)));
+    }
+
+    let cancelled = replay_orders::cancel(&mut tx, order_id, "Cancelled by user").await?;
+    tx.commit().await?;
+    let mask = replay_scenarios::blind_mask(&state.db.pool, replay_id).await?;
+    Ok(masked(mask.as_ref(), &cancelled))
+}
+
+/// Cash, positions and P&L, marked at the replay cursor.
+async fn get_portfolio(
+    State(state): State<AppState>,
+    session: SessionUser,
+    Path(replay_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    ensure_owner(&state, replay_id, &session).await?;
+    let portfolio = replay::load_portfolio(&state.db.pool, replay_id)
+        .await?
+        .ok_or(crate::error::AppError::NotFound)?;
+    let mask = replay_scenarios::blind_mask(&state.db.pool, replay_id).await?;
+    Ok(masked(mask.as_ref(), &portfolio))
+}
+
+/// 404 unless the session exists and belongs to the caller.
+async fn ensure_owner(state: &AppState, replay_id: Uuid, session: &SessionUser) -> Result<()> {
+    if replay::is_owner(&state.db.pool, replay_id, session.user_id).await? {
+        Ok(())
+    } else {
+        Err(crate::error::AppError::NotFound)
+    }
+}
+
+/// The caller's sessions, newest first.
+async fn list_sessions(
+    State(state): State<AppState>,
+    session: SessionUser,
+    Query(params): Query<SessionListQuery>,
+) -> Result<Json<serde_json::Value>> {
+    let limit = params.limit.unwrap_or(DEFAULT_SESSION_LIMIT);
+    if !(1..=MAX_SESSION_LIMIT).contains(&limit) {
+        return Err(crate::error::AppError::Validation(format!(
+            "limit must be between 1 and {}",
+            MAX_SESSION_LIMIT
+        )));
+    }
+    let offset = params.offset.unwrap_or(0);
+    if offset < 0 {
+        return Err(crate::error::AppError::Validation("offset must not be negative".to_string()));
+    }
+
+    let sessions = sqlx::query_as::<_, SessionSummary>(&format!(
+        "SELECT {} FROM replay_sessions rs
+         WHERE rs.user_id = $1
+         ORDER BY rs.created_at DESC, rs.id
+         LIMIT $2 OFFSET $3",
+        SESSION_COLUMNS
+    ))
+    .bind(session.user_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&state.db.pool)
+    .await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM replay_sessions WHERE user_id = $1")
+        .bind(session.user_id)
+        .fetch_one(&state.db.pool)
+        .await?;
+
+    let mut page = serde_json::to_value(SessionPage { sessions, total, limit, offset }).unwrap_or_default();
+    if let Some(sessions) = page["sessions"].as_array_mut() {
+        for summary in sessions.iter_mut().filter(|s| s["blind"] == true) {
+            let replay_id = summary["id"].as_str().and_then(|id| id.parse().ok()).unwrap_or_default();
+            if let Some(mask) = replay_scenarios::blind_mask(&state.db.pool, replay_id).await? {
+                mask.apply(summary);
+            }
+        }
+    }
+    Ok(Json(page))
+}
+
+/// One session with its trades and portfolio.
+async fn get_session(
+    State(state): State<AppState>,
+    session: SessionUser,
+    Path(replay_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    let summary = sqlx::query_as::<_, SessionSummary>(&format!(
+        "SELECT {} FROM replay_sessions rs
+         WHERE rs.id = $1 AND rs.user_id = $2",
+        SESSION_COLUMNS
+    ))
+    .bind(replay_id)
+    .bind(session.user_id)
     .fetch_optional(&state.db.pool)
     .await?
     .ok_or(crate::error::AppError::NotFound)?;
 
-    // Get latest price from market_prices for this asset
-    let price_row: Option<rust_decimal::Decimal> = sqlx::query_scalar(
-        "SELECT close FROM market_prices WHERE asset_id = $1 ORDER BY timestamp DESC LIMIT 1",
+    let trades = sqlx::query_as::<_, TradeRecord>(
+        "SELECT id, asset_id, order_id, side, price, quantity, timestamp
+         FROM replay_trades WHERE replay_id = $1
+         ORDER BY timestamp, id",
     )
-    .bind(rs.asset_id)
-    .fetch_optional(&state.db.pool)
+    .bind(replay_id)
+    .fetch_all(&state.db.pool)
     .await?;
+    let portfolio = replay::load_portfolio(&state.db.pool, replay_id)
+        .await?
+        .ok_or(crate::error::AppError::NotFound)?;
 
-    let current_price = price_row.unwrap_or_else(|| rust_decimal::Decimal::new(42000, 2));
+    let predictions = if summary.mode == ReplayMode::BarByBar.as_str() {
+        Some(replay_predictions::summary(&state.db.pool, replay_id).await?)
+    } else {
+        None
+    };
 
-    sqlx::query(
-        "INSERT INTO replay_trades (replay_id, side, price, quantity, timestamp)
-         VALUES ($1, $2, $3, $4, NOW())",
+    let mask = replay_scenarios::blind_mask(&state.db.pool, replay_id).await?;
+    Ok(masked(mask.as_ref(), &SessionDetail { session: summary, trades, portfolio, predictions }))
+}
+
+/// Delete a session with its orders, trades and positions. Scenario
+/// sessions are kept: the first one is the student's ranked attempt.
+async fn delete_session(
+    State(state): State<AppState>,
+    session: SessionUser,
+    Path(replay_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    let scenario_id: Option<Uuid> =
+        sqlx::query_scalar("SELECT scenario_id FROM replay_sessions WHERE id = $1 AND user_id = $2")
+            .bind(replay_id)
+            .bind(session.user_id)
+            .fetch_optional(&state.db.pool)
+            .await?
+            .ok_or(crate::error::AppError::NotFound)?;
+    if scenario_id.is_some() {
+        return Err(crate::error::AppError::Conflict(
+            "Scenario sessions count toward the leaderboard and can't be deleted".to_string(),
+        ));
+    }
+    let deleted = sqlx::query(
+        "DELETE FROM replay_sessions WHERE id = $1 AND user_id = $2 AND scenario_id IS NULL",
     )
     .bind(replay_id)
-    .bind(&payload.side)
-    .bind(current_price)
-    .bind(rust_decimal::Decimal::from_f64_retain(payload.quantity).unwrap())
+    .bind(session.user_id)
     .execute(&state.db.pool)
+    .await?
+    .rows_affected();
+    if deleted == 0 {
+        return Err(crate::error::AppError::NotFound);
+    }
+    Ok(Json(serde_json::json!({ "success": true, "replay_id": replay_id })))
+}
+
+/// The stored report (generated on first request) with the trade journal.
+async fn get_report(
+    State(state): State<AppState>,
+    session: SessionUser,
+    Path(replay_id): Path<Uuid>,
+) -> Result<Json<ReportResponse>> {
+    ensure_owner(&state, replay_id, &session).await?;
+    ensure_finished(&state, replay_id).await?;
+    let report = match replay_report::stored(&state.db.pool, replay_id).await? {
+        Some(report) => Some(report),
+        None => replay_report::generate(&state.db.pool, replay_id).await?,
+    };
+    let (report, generated_at) = report.ok_or(crate::error::AppError::NotFound)?;
+    let journal = journal(&state, replay_id).await?;
+    Ok(Json(ReportResponse { generated_at, report, journal }))
+}
+
+/// Rebuild the report from the current trade log.
+async fn regenerate_report(
+    State(state): State<AppState>,
+    session: SessionUser,
+    Path(replay_id): Path<Uuid>,
+) -> Result<Json<ReportResponse>> {
+    ensure_owner(&state, replay_id, &session).await?;
+    ensure_finished(&state, replay_id).await?;
+    let (report, generated_at) = replay_report::generate(&state.db.pool, replay_id)
+        .await?
+        .ok_or(crate::error::AppError::NotFound)?;
+    let journal = journal(&state, replay_id).await?;
+    Ok(Json(ReportResponse { generated_at, report, journal }))
+}
+
+/// The report's benchmark and closes cover the whole window (and name the
+/// assets of blind scenarios), so it is only built once the replay ends.
+async fn ensure_finished(state: &AppState, replay_id: Uuid) -> Result<()> {
+    let finished: bool = sqlx::query_scalar("SELECT finished FROM replay_sessions WHERE id = $1")
+        .bind(replay_id)
+        .fetch_optional(&state.db.pool)
+        .await?
+        .ok_or(crate::error::AppError::NotFound)?;
+    if !finished {
+        return Err(crate::error::AppError::Conflict(
+            "The report is available when the replay ends".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Trades in replay-time order with the P&L each sell realized.
+async fn journal(state: &AppState, replay_id: Uuid) -> Result<Vec<JournalEntry>> {
+    let (_, realized) = replay_report::build(&state.db.pool, replay_id)
+        .await?
+        .ok_or(crate::error::AppError::NotFound)?;
+    let symbols: std::collections::HashMap<Uuid, String> = sqlx::query_as(
+        "SELECT a.id, a.symbol FROM replay_session_assets rsa
+         JOIN assets a ON a.id = rsa.asset_id WHERE rsa.replay_id = $1",
+    )
+    .bind(replay_id)
+    .fetch_all(&state.db.pool)
+    .await?
+    .into_iter()
+    .collect();
+
+    let mut trades = replay_report::trades(&state.db.pool, replay_id).await?;
+    trades.sort_by_key(|t| t.timestamp);
+    Ok(trades
+        .into_iter()
+        .map(|t| JournalEntry {
+            id: t.id,
+            asset_id: t.asset_id,
+            symbol: symbols.get(&t.asset_id).cloned().unwrap_or_default(),
+            order_id: t.order_id,
+            side: t.side,
+            price: t.price,
+            quantity: t.quantity,
+            timestamp: t.timestamp,
+            realized_pnl: realized.get(&t.id).copied(),
+            note: t.note,
+        })
+        .collect())
+}
+
+/// Attach a journal note to a trade.
+async fn set_trade_note(
+    State(state): State<AppState>,
+    session: SessionUser,
+    Path((replay_id, trade_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<TradeNoteRequest>,
+) -> Result<Json<serde_json::Value>> {
+    let note = payload.note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    if note.as_ref().is_some_and(|n| n.chars().count() > MAX_NOTE_LENGTH) {
+        return Err(crate::error::AppError::Validation(format!(
+            "note must be at most {} characters",
+            MAX_NOTE_LENGTH
+        )));
+    }
+
+    let updated = sqlx::query(
+        "UPDATE replay_trades t SET note = $1
+         FROM replay_sessions rs
+         WHERE t.id = $2 AND t.replay_id = $3 AND rs.id = t.replay_id AND rs.user_id = $4",
+    )
+    .bind(&note)
+    .bind(trade_id)
+    .bind(replay_id)
+    .bind(session.user_id)
+    .execute(&state.db.pool)
+    .await?
+    .rows_affected();
+    if updated == 0 {
+        return Err(crate::error::AppError::NotFound);
+    }
+    Ok(Json(serde_json::json!({ "id": trade_id, "note": note })))
+}
+
+/// Public — active scenarios, newest first.
+async fn list_scenarios(State(state): State<AppState>) -> Result<Json<Vec<ScenarioCard>>> {
+    let scenarios = replay_scenarios::list(&state.db.pool, true).await?;
+    Ok(Json(scenarios.into_iter().map(ScenarioCard::from).collect()))
+}
+
+/// Public — one active scenario.
+async fn get_scenario(
+    State(state): State<AppState>,
+    Path(scenario_id): Path<Uuid>,
+) -> Result<Json<ScenarioCard>> {
+    replay_scenarios::load(&state.db.pool, scenario_id)
+        .await?
+        .filter(|s| s.is_active)
+        .map(|s| Json(ScenarioCard::from(s)))
+        .ok_or(crate::error::AppError::NotFound)
+}
+
+/// Start a replay session of a scenario. Only a student's first session
+/// counts for the leaderboard; later ones are practice. Until that first
+/// session finishes, starting again resumes it, so walking away can't lose
+/// the ranked attempt.
+async fn start_scenario(
+    State(state): State<AppState>,
+    session: SessionUser,
+    Path(scenario_id): Path<Uuid>,
+    Query(params): Query<StartScenarioQuery>,
+) -> Result<Json<serde_json::Value>> {
+    let mode = parse_mode(params.mode.as_deref())?;
+    let scenario = replay_scenarios::load(&state.db.pool, scenario_id)
+        .await?
+        .filter(|s| s.is_active)
+        .ok_or(crate::error::AppError::NotFound)?;
+
+    let first: Option<(Uuid, String, bool)> = sqlx::query_as(
+        "SELECT id, mode, finished FROM replay_sessions
+         WHERE scenario_id = $1 AND user_id = $2
+         ORDER BY created_at, id LIMIT 1",
+    )
+    .bind(scenario.id)
+    .bind(session.user_id)
+    .fetch_optional(&state.db.pool)
     .await?;
+    let (replay_id, mode) = match first {
+        Some((replay_id, first_mode, false)) => (replay_id, parse_mode(Some(&first_mode))?),
+        _ => {
+            let replay_id = insert_session(
+                &state,
+                session.user_id,
+                scenario.start_time,
+                scenario.end_time,
+                scenario.starting_cash,
+                &scenario.asset_ids,
+                mode,
+                Some(scenario.id),
+            )
+            .await?;
+            (replay_id, mode)
+        }
+    };
+    let mask = replay_scenarios::blind_mask(&state.db.pool, replay_id).await?;
+    Ok(masked(
+        mask.as_ref(),
+        &CreateReplayResponse {
+            replay_id,
+            asset_ids: scenario.asset_ids,
+            mode,
+            ws_url: format!("/ws/replay/{}", replay_id),
+        },
+    ))
+}
+
+/// Public — students ranked by the return of their first finished session.
+async fn scenario_leaderboard(
+    State(state): State<AppState>,
+    Path(scenario_id): Path<Uuid>,
+) -> Result<Json<Vec<LeaderboardEntry>>> {
+    replay_scenarios::load(&state.db.pool, scenario_id)
+        .await?
+        .ok_or(crate::error::AppError::NotFound)?;
+    Ok(Json(replay_scenarios::leaderboard(&state.db.pool, scenario_id, 100).await?))
+}
 
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "price": current_price,
-        "side": payload.side,
-        "quantity": payload.quantity,
-    })))
+/// Predict the close of an asset's next bar in a bar-by-bar replay. The
+/// replay socket scores it when that bar is revealed; predicting again
+/// before then replaces it.
+async fn submit_prediction(
+    State(state): State<AppState>,
+    session: SessionUser,
+    Path(replay_id): Path<Uuid>,
+    Json(payload): Json<PredictionRequest>,
+) -> Result<Json<serde_json::Value>> {
+    let direction = payload
+        .direction
+        .as_deref()
+        .map(|d| {
+            Direction::parse(d).ok_or_else(|| {
+                crate::error::AppError::Validation("direction must be up, down or flat".to_string())
+            })
+        })
+        .transpose()?;
+    let target_price = parse_price("target_price", payload.target_price)?;
+    if target_price.is_some_and(|t| t <= Decimal::ZERO) {
+        return Err(crate::error::AppError::Validation("target_price must be positive".to_string()));
+    }
+    if direction.is_none() && target_price.is_none() {
+        return Err(crate::error::AppError::Validation(
+            "Give a direction, a target_price or both".to_string(),
+        ));
+    }
+    let mask = replay_scenarios::blind_mask(&state.db.pool, replay_id).await?;
+    let asset_id = payload.asset_id.map(|id| mask.as_ref().map_or(id, |m| m.real_asset(id)));
+
+    let mut tx = state.db.pool.begin().await?;
+    let (mode, started, finished): (String, bool, bool) = sqlx::query_as(
+        "SELECT mode, cursor_time IS NOT NULL, finished FROM replay_sessions
+         WHERE id = $1 AND user_id = $2",
+    )
+    .bind(replay_id)
+    .bind(session.user_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(crate::error::AppError::NotFound)?;
+    if mode != ReplayMode::BarByBar.as_str() {
+        return Err(crate::error::AppError::Validation(
+            "Predictions are for bar-by-bar replays".to_string(),
+        ));
+    }
+    if !started {
+        return Err(crate::error::AppError::Validation(
+            "Step to the first bar before predicting".to_string(),
+        ));
+    }
+    if finished {
+        return Err(crate::error::AppError::Validation("No bars left to predict".to_string()));
+    }
+    let rs = lock_session(&mut tx, replay_id, session.user_id, asset_id).await?;
+    let target_price = target_price.map(|t| mask.as_ref().map_or(t, |m| m.real_price(rs.asset_id, t)));
+
+    let prediction = replay_predictions::submit(
+        &mut tx,
+        replay_id,
+        rs.asset_id,
+        direction,
+        target_price,
+        rs.cursor_time,
+        rs.cursor_price,
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(masked(mask.as_ref(), &prediction))
+}
+
+/// The session's predictions with its accuracy so far.
+async fn list_predictions(
+    State(state): State<AppState>,
+    session: SessionUser,
+    Path(replay_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    ensure_owner(&state, replay_id, &session).await?;
+    let page = PredictionPage {
+        summary: replay_predictions::summary(&state.db.pool, replay_id).await?,
+        predictions: replay_predictions::list(&state.db.pool, replay_id).await?,
+    };
+    let mask = replay_scenarios::blind_mask(&state.db.pool, replay_id).await?;
+    Ok(masked(mask.as_ref(), &page))
 }
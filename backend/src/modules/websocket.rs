@@ -1,18 +1,121 @@
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        Path, State,
+        Path, Query, State,
     },
     response::IntoResponse,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use tokio::time::Instant;
 use uuid::Uuid;
-use crate::modules::AppState;
+use crate::{
+    error::{AppError, Result},
+    middleware::SessionUser,
+    modules::AppState,
+    services::{
+        indicators::{self, IndicatorSeries, IndicatorSpec, Interval},
+        market_depth::{self, DepthFrame},
+        replay::{self, ReplayCommand, ReplayCursor, ReplayMode, ReplayPlayer, ReplayPortfolio},
+        replay_orders::{self, OrderEvent},
+        replay_predictions::{self, Prediction},
+        replay_report::{self, ReplayReport},
+        replay_scenarios::{self, BlindMask},
+    },
+};
 
 #[derive(Debug, Serialize)]
 struct ReplayTick {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    asset_id: Uuid,
+    symbol: String,
     timestamp: String,
     price: f64,
+    /// Indicator values at this tick, when requested with `?indicators=`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    indicators: BTreeMap<String, Option<f64>>,
+    /// Order book at the close of this bar, when requested with `?depth=true`
+    /// and a snapshot was stored during it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    depth: Option<DepthFrame>,
+}
+
+/// One asset's bars in a replay, looked up by timestamp.
+struct AssetStream {
+    asset_id: Uuid,
+    symbol: String,
+    series: IndicatorSeries,
+    by_time: HashMap<chrono::NaiveDateTime, usize>,
+    depth: HashMap<chrono::NaiveDateTime, DepthFrame>,
+}
+
+impl AssetStream {
+    /// Close of the last bar at or before `at`.
+    fn close_at(&self, at: chrono::NaiveDateTime) -> Option<rust_decimal::Decimal> {
+        let n = self.series.candles.partition_point(|c| c.timestamp <= at);
+        n.checked_sub(1).map(|i| self.series.candles[i].close)
+    }
+}
+
+/// Optional overlay for the replay stream: bar size, indicator specs
+/// (same syntax as `/market-data/:asset_id/indicators`) and order-book depth,
+/// plus the last bar time a reconnecting client received (RFC 3339).
+#[derive(Debug, Deserialize)]
+pub struct ReplayStreamQuery {
+    interval: Option<String>,
+    indicators: Option<String>,
+    #[serde(default)]
+    depth: bool,
+    since: Option<String>,
+}
+
+/// Server → client playback status: `ready` on connect, `ack` for each
+/// command, `ended` after the last bar.
+#[derive(Debug, Serialize)]
+struct ReplayStatus {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    action: Option<&'static str>,
+    cursor: ReplayCursor,
+}
+
+/// Paper-trading account re-marked at each new bar.
+#[derive(Debug, Serialize)]
+struct PortfolioFrame {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(flatten)]
+    portfolio: ReplayPortfolio,
+}
+
+/// The session's performance report, sent when the last bar has been sent.
+#[derive(Debug, Serialize)]
+struct ReportFrame {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    generated_at: chrono::NaiveDateTime,
+    #[serde(flatten)]
+    report: ReplayReport,
+}
+
+/// An order filled, triggered, rejected or cancelled on a streamed bar.
+#[derive(Debug, Serialize)]
+struct OrderFrame {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(flatten)]
+    event: OrderEvent,
+}
+
+/// A next-bar prediction scored on a revealed bar.
+#[derive(Debug, Serialize)]
+struct PredictionFrame {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(flatten)]
+    prediction: Prediction,
 }
 
 #[derive(Debug, Serialize)]
@@ -22,12 +125,18 @@ struct LeaderboardUpdate {
     value: f64,
 }
 
+/// Only the session's owner can open its stream; anyone else gets a 404.
 pub async fn replay_handler(
     ws: WebSocketUpgrade,
+    session: SessionUser,
     Path(replay_id): Path<Uuid>,
+    Query(overlay): Query<ReplayStreamQuery>,
     State(state): State<AppState>,
-) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_replay_socket(socket, replay_id, state))
+) -> Result<impl IntoResponse> {
+    if !replay::is_owner(&state.db.pool, replay_id, session.user_id).await? {
+        return Err(AppError::NotFound);
+    }
+    Ok(ws.on_upgrade(move |socket| handle_replay_socket(socket, replay_id, overlay, state)))
 }
 
 pub async fn contest_handler(
@@ -38,19 +147,56 @@ pub async fn contest_handler(
     ws.on_upgrade(move |socket| handle_contest_socket(socket, contest_id, state))
 }
 
-async fn handle_replay_socket(mut socket: WebSocket, replay_id: Uuid, state: AppState) {
+async fn handle_replay_socket(
+    mut socket: WebSocket,
+    replay_id: Uuid,
+    overlay: ReplayStreamQuery,
+    state: AppState,
+) {
     tracing::info!("New WebSocket connection for replay: {}", replay_id);
+
+    let interval = match Interval::parse(overlay.interval.as_deref().unwrap_or("1m")) {
+        Some(i) => i,
+        None => {
+            let _ = socket.send(Message::Text(
+                serde_json::json!({"error": "Invalid interval"}).to_string()
+            )).await;
+            return;
+        }
+    };
+    let specs = match IndicatorSpec::parse_list(overlay.indicators.as_deref().unwrap_or("")) {
+        Ok(specs) => specs,
+        Err(e) => {
+            let _ = socket.send(Message::Text(serde_json::json!({"error": e}).to_string())).await;
+            return;
+        }
+    };
+    let since = match overlay.since.as_deref().map(chrono::DateTime::parse_from_rfc3339).transpose() {
+        Ok(since) => since.map(|t| t.naive_utc()),
+        Err(_) => {
+            let _ = socket.send(Message::Text(
+                serde_json::json!({"error": "since must be RFC 3339"}).to_string()
+            )).await;
+            return;
+        }
+    };
     
     // Fetch replay session details
     #[derive(sqlx::FromRow)]
     struct ReplayData {
-        asset_id: Uuid,
         start_time: chrono::NaiveDateTime,
         end_time: chrono::NaiveDateTime,
+        cursor_time: Option<chrono::NaiveDateTime>,
+        paused: bool,
+        speed: f64,
+        mode: String,
+        from_scenario: bool,
     }
     
     let replay = match sqlx::query_as::<_, ReplayData>(
-        "SELECT asset_id, start_time, end_time FROM replay_sessions WHERE id = $1"
+        "SELECT start_time, end_time, cursor_time, paused, speed, mode,
+                scenario_id IS NOT NULL AS from_scenario
+         FROM replay_sessions WHERE id = $1"
     )
     .bind(replay_id)
     .fetch_optional(&state.db.pool)
@@ -68,54 +214,310 @@ async fn handle_replay_socket(mut socket: WebSocket, replay_id: Uuid, state: App
             return;
         }
     };
-    
-    // Fetch price data
-    #[derive(sqlx::FromRow)]
-    struct PriceData {
-        timestamp: chrono::NaiveDateTime,
-        close: rust_decimal::Decimal,
-    }
-    
-    let prices = match sqlx::query_as::<_, PriceData>(
-        r#"
-        SELECT timestamp, close
-        FROM market_prices
-        WHERE asset_id = $1 AND timestamp BETWEEN $2 AND $3
-        ORDER BY timestamp ASC
-        "#
+    let assets: Vec<(Uuid, String)> = match sqlx::query_as(
+        "SELECT rsa.asset_id, a.symbol FROM replay_session_assets rsa
+         JOIN assets a ON a.id = rsa.asset_id
+         WHERE rsa.replay_id = $1 ORDER BY rsa.position",
     )
-    .bind(replay.asset_id)
-    .bind(replay.start_time)
-    .bind(replay.end_time)
+    .bind(replay_id)
     .fetch_all(&state.db.pool)
     .await
     {
-        Ok(p) => p,
+        Ok(a) => a,
         Err(e) => {
-            tracing::error!("Failed to fetch prices: {}", e);
+            tracing::error!("Database error: {}", e);
             return;
         }
     };
-    
-    // Stream price data
-    for price in prices {
+    // Blind scenarios hide dates and symbols until the last bar
+    let mut mask = match replay_scenarios::blind_mask(&state.db.pool, replay_id).await {
+        Ok(mask) => mask,
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return;
+        }
+    };
+
+    // Fetch each asset's price data (rolled-up candles for ranges past minute
+    // retention), resampled to the requested bar size, with any indicator
+    // overlay and depth
+    let mut streams = Vec::with_capacity(assets.len());
+    for (asset_id, symbol) in assets {
+        let series = match indicators::compute_for_range(
+            &state.db.pool,
+            asset_id,
+            replay.start_time,
+            replay.end_time,
+            interval,
+            &specs,
+        )
+        .await
+        {
+            Ok(p) => p,
+            Err(e) => {
+                tracing::error!("Failed to fetch prices: {}", e);
+                return;
+            }
+        };
+
+        let depth = if overlay.depth {
+            match market_depth::last_per_bar(
+                &state.db.pool,
+                asset_id,
+                replay.start_time,
+                replay.end_time + chrono::Duration::minutes(interval.minutes()),
+                interval.minutes(),
+            )
+            .await
+            {
+                Ok(d) => d,
+                Err(e) => {
+                    tracing::error!("Failed to fetch depth snapshots: {}", e);
+                    return;
+                }
+            }
+        } else {
+            Vec::new()
+        };
+        streams.push(AssetStream {
+            asset_id,
+            symbol,
+            by_time: series.candles.iter().enumerate().map(|(i, c)| (c.timestamp, i)).collect(),
+            depth: depth
+                .into_iter()
+                .map(|(bar, snapshot)| (bar, DepthFrame::from(snapshot)))
+                .collect(),
+            series,
+        });
+    }
+
+    // One clock over every asset's bars; each step sends the bars at that time
+    let clock: Vec<chrono::NaiveDateTime> = streams
+        .iter()
+        .flat_map(|s| s.series.candles.iter().map(|c| c.timestamp))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let tick = |stream: &AssetStream, i: usize| {
+        let price = &stream.series.candles[i];
         let tick = ReplayTick {
+            kind: "tick",
+            asset_id: stream.asset_id,
+            symbol: stream.symbol.clone(),
             timestamp: price.timestamp.to_string(),
             price: price.close.to_string().parse().unwrap_or(0.0),
+            indicators: stream
+                .series
+                .lines
+                .iter()
+                .map(|(name, values)| (name.clone(), values[i]))
+                .collect(),
+            depth: stream.depth.get(&price.timestamp).cloned(),
         };
-        
-        let msg = serde_json::to_string(&tick).unwrap();
-        
-        if socket.send(Message::Text(msg)).await.is_err() {
-            tracing::info!("Client disconnected");
-            break;
+        Message::Text(serde_json::to_string(&tick).unwrap())
+    };
+    let status = |kind: &'static str, action: Option<&'static str>, player: &ReplayPlayer| {
+        let status = ReplayStatus { kind, action, cursor: player.cursor() };
+        Message::Text(serde_json::to_string(&status).unwrap())
+    };
+
+    // Stream bars at the player's pace (1x = one bar per second) while
+    // taking play/pause/step/seek/speed commands from the client. Scenario
+    // sessions can't go back; bar-by-bar sessions also wait for a step
+    // before each bar
+    let bar_by_bar = replay.mode == ReplayMode::BarByBar.as_str();
+    let mut player = ReplayPlayer::new(clock.clone());
+    if bar_by_bar {
+        player = player.turn_based();
+    } else if replay.from_scenario {
+        player = player.forward_only();
+    }
+    player = player.restore(replay.paused, replay.speed);
+
+    // Pick up at the saved cursor, first resending the bars the client
+    // missed (blind clients send a shifted `since`)
+    let since = since.map(|t| mask.as_ref().map_or(t, |m| m.real_time(t)));
+    let mut missed = player.resume(replay.cursor_time, since);
+    if socket.send(masked(status("ready", None, &player), mask.as_ref())).await.is_err() {
+        return;
+    }
+    let mut next_due = Instant::now();
+    if !missed.is_empty() {
+        next_due += player.delay();
+    }
+    let mut playback = (player.is_playing(), player.cursor().speed);
+
+    loop {
+        let cursor = player.cursor().index;
+
+        // Bars due now, bars a forward seek jumped over, and status/error
+        // messages to send after them
+        let resending = !missed.is_empty();
+        let mut skipped = Vec::new();
+        let (bars, statuses) = if resending {
+            (std::mem::take(&mut missed), Vec::new())
+        } else {
+            let timer = async {
+                if player.is_playing() {
+                    tokio::time::sleep_until(next_due).await
+                } else {
+                    std::future::pending().await
+                }
+            };
+            tokio::select! {
+                msg = socket.recv() => match msg {
+                    Some(Ok(Message::Text(text))) => match serde_json::from_str::<ReplayCommand>(&text) {
+                        Ok(command) => match player.apply(&unmask_command(command.clone(), mask.as_ref())) {
+                            Ok(bars) => {
+                                if matches!(command, ReplayCommand::Play | ReplayCommand::Speed { .. }) {
+                                    next_due = Instant::now() + player.delay();
+                                }
+                                if let (ReplayCommand::Seek { .. }, Some(&to)) = (&command, bars.first()) {
+                                    skipped = (cursor.map_or(0, |c| c + 1)..to).collect();
+                                }
+                                (bars, vec![status("ack", Some(command.name()), &player)])
+                            }
+                            Err(e) => (Vec::new(), vec![replay_error(&e)]),
+                        },
+                        Err(e) => (Vec::new(), vec![replay_error(&format!("Invalid command: {}", e))]),
+                    },
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                        tracing::info!("Client disconnected");
+                        break;
+                    }
+                    Some(Ok(_)) => (Vec::new(), Vec::new()),
+                },
+                _ = timer => {
+                    next_due += player.delay();
+                    let bars: Vec<usize> = player.advance().into_iter().collect();
+                    let ended = if player.is_playing() { Vec::new() } else { vec![status("ended", None, &player)] };
+                    (bars, ended)
+                }
+            }
+        };
+        // Resting orders still trade through the bars a seek skips
+        if !skipped.is_empty() {
+            match replay_orders::has_open(&state.db.pool, replay_id).await {
+                Ok(true) => {}
+                Ok(false) => skipped.clear(),
+                Err(e) => tracing::error!("Failed to check replay orders: {}", e),
+            }
+        }
+        // Each new bar may fill resting orders and score a prediction;
+        // resent bars have already done so
+        let mut outgoing = Vec::new();
+        let steps = skipped.into_iter().map(|step| (step, false)).chain(bars.into_iter().map(|step| (step, true)));
+        for (step, shown) in steps {
+            for stream in &streams {
+                let Some(&i) = stream.by_time.get(&clock[step]) else { continue };
+                if shown {
+                    outgoing.push(tick(stream, i));
+                }
+                if resending {
+                    continue;
+                }
+                let bar = &stream.series.candles[i];
+                match replay_orders::process_bar(&state.db.pool, replay_id, stream.asset_id, bar).await {
+                    Ok(events) => outgoing.extend(events.into_iter().map(|event| {
+                        Message::Text(serde_json::to_string(&OrderFrame { kind: "order", event }).unwrap())
+                    })),
+                    Err(e) => tracing::error!("Failed to match replay orders: {}", e),
+                }
+                if bar_by_bar && shown {
+                    let resolved =
+                        replay_predictions::resolve(&state.db.pool, replay_id, stream.asset_id, bar.timestamp, bar.close)
+                            .await;
+                    match resolved {
+                        Ok(Some(prediction)) => outgoing.push(Message::Text(
+                            serde_json::to_string(&PredictionFrame { kind: "prediction", prediction }).unwrap(),
+                        )),
+                        Ok(None) => {}
+                        Err(e) => tracing::error!("Failed to score replay prediction: {}", e),
+                    }
+                }
+            }
+        }
+
+        // Save the new cursor before the student sees the bar, so a trade
+        // placed on it fills at its close, and re-mark the portfolio there
+        let mut report = None;
+        let moved = player.cursor().index.filter(|i| Some(*i) != cursor);
+        if let Some(step) = moved {
+            let prices: Vec<(Uuid, Option<rust_decimal::Decimal>)> =
+                streams.iter().map(|s| (s.asset_id, s.close_at(clock[step]))).collect();
+            if let Err(e) =
+                replay::save_cursor(&state.db.pool, replay_id, clock[step], &prices, player.cursor().finished).await
+            {
+                tracing::error!("Failed to save replay cursor: {}", e);
+            }
+        }
+        if moved.is_some() || resending {
+            match replay::load_portfolio(&state.db.pool, replay_id).await {
+                Ok(Some(portfolio)) => outgoing.push(Message::Text(
+                    serde_json::to_string(&PortfolioFrame { kind: "portfolio", portfolio }).unwrap(),
+                )),
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to load replay portfolio: {}", e),
+            }
+        }
+        if moved.is_some() && player.cursor().finished {
+            match replay_report::generate(&state.db.pool, replay_id).await {
+                Ok(Some((report_body, generated_at))) => {
+                    report = Some(ReportFrame { kind: "report", generated_at, report: report_body })
+                }
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to generate replay report: {}", e),
+            }
+            mask = None;
+        }
+        // Keep pause and speed changes for the next connection
+        let now = (player.is_playing(), player.cursor().speed);
+        if now != playback {
+            if let Err(e) = replay::save_playback(&state.db.pool, replay_id, !now.0, now.1).await {
+                tracing::error!("Failed to save replay playback: {}", e);
+            }
+            playback = now;
+        }
+        outgoing.extend(statuses);
+        outgoing.extend(report.map(|r| Message::Text(serde_json::to_string(&r).unwrap())));
+
+        for msg in outgoing {
+            if socket.send(masked(msg, mask.as_ref())).await.is_err() {
+                tracing::info!("Client disconnected");
+                return;
+            }
         }
-        
-        // Simulate real-time delay (1 tick per second)
-        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
     }
-    
-    let _ = socket.send(Message::Close(None)).await;
+}
+
+/// Hide a blind session's dates and symbols in an outgoing frame.
+fn masked(msg: Message, mask: Option<&BlindMask>) -> Message {
+    let (Some(mask), Message::Text(text)) = (mask, &msg) else {
+        return msg;
+    };
+    match serde_json::from_str::<serde_json::Value>(text) {
+        Ok(mut value) => {
+            mask.apply(&mut value);
+            Message::Text(value.to_string())
+        }
+        Err(_) => msg,
+    }
+}
+
+/// Seek targets arrive in the blind session's shifted time.
+fn unmask_command(command: ReplayCommand, mask: Option<&BlindMask>) -> ReplayCommand {
+    match (command, mask) {
+        (ReplayCommand::Seek { timestamp }, Some(mask)) => {
+            let real = chrono::DateTime::parse_from_rfc3339(&timestamp)
+                .map(|t| mask.real_time(t.naive_utc()).format("%Y-%m-%dT%H:%M:%SZ").to_string());
+            ReplayCommand::Seek { timestamp: real.unwrap_or(timestamp) }
+        }
+        (command, _) => command,
+    }
+}
+
+fn replay_error(error: &str) -> Message {
+    Message::Text(serde_json::json!({"type": "error", "error": error}).to_string())
 }
 
 async fn handle_contest_socket(mut socket: WebSocket, contest_id: Uuid, state: AppState) {
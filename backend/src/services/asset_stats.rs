@@ -0,0 +1,192 @@
+//! Per-asset statistics for the asset picker: last price, trailing returns,
+//! realized volatility, range high/low, average volume and data coverage.
+//!
+//! Prices come through `price_history`, so ranges past minute retention are
+//! served from the hourly/daily rollups.
+
+use anyhow::Result;
+use chrono::{Duration, NaiveDateTime, SubsecRound, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::services::price_history::{self, Candle};
+use crate::services::TradingCalendar;
+
+/// Trailing windows reported under `returns`, in days.
+const RETURN_WINDOWS: &[(&str, i64)] = &[("1d", 1), ("7d", 7), ("30d", 30)];
+
+#[derive(Debug, Serialize)]
+pub struct AssetStats {
+    pub range_days: i64,
+    pub last_price: Option<Decimal>,
+    pub last_price_at: Option<NaiveDateTime>,
+    /// Percentage change of the last price over each trailing window.
+    pub returns: BTreeMap<&'static str, Option<f64>>,
+    /// Annualized standard deviation of daily log returns over the range, in %.
+    pub realized_volatility: Option<f64>,
+    pub high: Option<Decimal>,
+    pub low: Option<Decimal>,
+    pub average_daily_volume: Option<Decimal>,
+    pub coverage: Coverage,
+}
+
+/// How much of the 1-minute history the exchange calendar expects is
+/// actually stored, over the part of the range still kept at 1-minute
+/// resolution.
+#[derive(Debug, Serialize)]
+pub struct Coverage {
+    pub window_start: NaiveDateTime,
+    pub window_end: NaiveDateTime,
+    pub expected_minutes: i64,
+    pub stored_minutes: i64,
+    pub ratio: Option<f64>,
+}
+
+/// Percentage change from `from` to `to`.
+pub fn pct_change(from: Decimal, to: Decimal) -> Option<f64> {
+    if from.is_zero() {
+        return None;
+    }
+    ((to - from) / from * Decimal::ONE_HUNDRED).round_dp(4).to_f64()
+}
+
+/// Annualized realized volatility (in %) of a close series sampled once per
+/// period. Needs at least two returns.
+pub fn realized_volatility(closes: &[f64], periods_per_year: f64) -> Option<f64> {
+    let returns: Vec<f64> = closes
+        .windows(2)
+        .filter(|w| w[0] > 0.0 && w[1] > 0.0)
+        .map(|w| (w[1] / w[0]).ln())
+        .collect();
+    if returns.len() < 2 {
+        return None;
+    }
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    Some((variance.sqrt() * periods_per_year.sqrt() * 100.0 * 10_000.0).round() / 10_000.0)
+}
+
+/// Range high, low and average volume per day from daily candles.
+pub fn range_summary(daily: &[Candle]) -> (Option<Decimal>, Option<Decimal>, Option<Decimal>) {
+    let high = daily.iter().map(|c| c.high).max();
+    let low = daily.iter().map(|c| c.low).min();
+    let volumes: Vec<Decimal> = daily.iter().filter_map(|c| c.volume).collect();
+    let average = (!volumes.is_empty())
+        .then(|| (volumes.iter().sum::<Decimal>() / Decimal::from(volumes.len())).round_dp(4));
+    (high, low, average)
+}
+
+pub async fn compute(
+    pool: &PgPool,
+    calendar: &TradingCalendar,
+    asset_id: Uuid,
+    exchange: &str,
+    range_days: i64,
+    minute_retention_days: i64,
+) -> Result<AssetStats> {
+    let now = Utc::now().naive_utc().trunc_subsecs(0);
+    let range_start = now - Duration::days(range_days);
+
+    #[derive(sqlx::FromRow)]
+    struct Last {
+        close: Decimal,
+        timestamp: NaiveDateTime,
+    }
+
+    let last = sqlx::query_as::<_, Last>(
+        "SELECT close, timestamp FROM market_prices WHERE asset_id = $1
+         ORDER BY timestamp DESC LIMIT 1",
+    )
+    .bind(asset_id)
+    .fetch_optional(pool)
+    .await?;
+
+    // Returns are measured from the last stored price, not from "now", so a
+    // closed market doesn't show a stale 0%.
+    let mut returns = BTreeMap::new();
+    for (label, days) in RETURN_WINDOWS {
+        let change = match &last {
+            Some(last) => price_history::close_at_or_before(
+                pool,
+                asset_id,
+                last.timestamp - Duration::days(*days),
+            )
+            .await?
+            .and_then(|base| pct_change(base, last.close)),
+            None => None,
+        };
+        returns.insert(*label, change);
+    }
+
+    let daily = price_history::daily_candles(pool, asset_id, range_start, now).await?;
+    let closes: Vec<f64> = daily.iter().filter_map(|c| c.close.to_f64()).collect();
+    let periods_per_year = if TradingCalendar::is_24x7(exchange) { 365.0 } else { 252.0 };
+    let (high, low, average_daily_volume) = range_summary(&daily);
+
+    let window_start = range_start.max(now - Duration::days(minute_retention_days));
+    let stored_minutes: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM market_prices
+         WHERE asset_id = $1 AND timestamp >= $2 AND timestamp < $3",
+    )
+    .bind(asset_id)
+    .bind(window_start)
+    .bind(now)
+    .fetch_one(pool)
+    .await?;
+    let expected_minutes = calendar.open_minutes(exchange, window_start, now);
+
+    Ok(AssetStats {
+        range_days,
+        last_price: last.as_ref().map(|l| l.close),
+        last_price_at: last.as_ref().map(|l| l.timestamp),
+        returns,
+        realized_volatility: realized_volatility(&closes, periods_per_year),
+        high,
+        low,
+        average_daily_volume,
+        coverage: Coverage {
+            window_start,
+            window_end: now,
+            expected_minutes,
+            stored_minutes,
+            ratio: (expected_minutes > 0).then(|| {
+                ((stored_minutes as f64 / expected_minutes as f64).min(1.0) * 10_000.0).round()
+                    / 10_000.0
+            }),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pct_change_handles_zero_base() {
+        assert_eq!(pct_change(Decimal::new(100, 0), Decimal::new(110, 0)), Some(10.0));
+        assert_eq!(pct_change(Decimal::new(200, 0), Decimal::new(150, 0)), Some(-25.0));
+        assert_eq!(pct_change(Decimal::ZERO, Decimal::ONE), None);
+    }
+
+    #[test]
+    fn realized_volatility_of_constant_growth_is_zero() {
+        let closes: Vec<f64> = (0..10).map(|i| 100.0 * 1.01f64.powi(i)).collect();
+        assert!(realized_volatility(&closes, 252.0).unwrap() < 1e-6);
+        assert_eq!(realized_volatility(&[100.0, 101.0], 252.0), None);
+    }
+
+    #[test]
+    fn realized_volatility_is_annualized() {
+        // Alternating ±1% daily log returns → daily stdev ≈ 1.03%.
+        let closes: Vec<f64> = (0..21)
+            .map(|i| 100.0 * if i % 2 == 0 { 1.0 } else { 0.01f64.exp() })
+            .collect();
+        let vol = realized_volatility(&closes, 365.0).unwrap();
+        assert!((vol - 1.026 * 365f64.sqrt()).abs() < 0.5, "vol = {}", vol);
+    }
+}
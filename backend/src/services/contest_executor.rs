@@ -10,6 +10,8 @@ use tracing::{error, info};
 use std::time::Duration;
 use chrono::NaiveDateTime;
 
+use crate::services::price_history;
+
 pub struct ContestExecutor {
     pool: PgPool,
 }
@@ -141,7 +143,7 @@ impl ContestExecutor {
         }
 
         // Sort descending by value, assign ranks
-        portfolio_values.sort_by(|a, b| b.1.cmp(&a.1));
+        portfolio_values.sort_by_key(|p| std::cmp::Reverse(p.1));
 
         for (rank, (user_id, value)) in portfolio_values.iter().enumerate() {
             let rank_i32 = (rank + 1) as i32;
@@ -167,6 +169,8 @@ impl ContestExecutor {
     /// Weighted portfolio value:
     ///   value = sum( (alloc_pct/100) * virtual_capital * (latest_price / entry_price) )
     /// where `entry_price` = most recent close at-or-before contest start_time.
+    /// Each asset's return is taken in its own quote currency (see `fx` for
+    /// conversions), so mixed USD/INR pools are scored on performance alone.
     async fn compute_portfolio_value(
         &self,
         participant_id: Uuid,
@@ -202,15 +206,9 @@ impl ContestExecutor {
             .await?;
 
             // Entry price: most recent close at-or-before contest start
-            let entry: Option<Decimal> = sqlx::query_scalar(
-                "SELECT close FROM market_prices
-                 WHERE asset_id = $1 AND timestamp <= $2
-                 ORDER BY timestamp DESC LIMIT 1",
-            )
-            .bind(alloc.asset_id)
-            .bind(contest_start)
-            .fetch_optional(&self.pool)
-            .await?;
+            let entry =
+                price_history::close_at_or_before(&self.pool, alloc.asset_id, contest_start)
+                    .await?;
 
             // Fall back to earliest price if nothing before start
             let entry = match entry {
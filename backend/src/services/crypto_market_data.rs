@@ -0,0 +1,526 @@
+//! Live crypto prices from a Binance-style exchange feed (`CRYPTO_FEED=binance`).
+//!
+//! Assets whose exchange is in `CRYPTO_EXCHANGES` are streamed from the
+//! combined-stream WebSocket (`/stream?streams=btcusdt@kline_1m/...`) and
+//! backfilled from the REST `/api/v3/klines` endpoint. Both base URLs are
+//! configurable, so the feed can be pointed at the local mock in
+//! `examples/mock_crypto_feed.rs`.
+//!
+//! While the stream is connected the seed generator leaves the streamed
+//! assets alone; when it drops, seed prices take over again until the
+//! reconnect succeeds.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime};
+use futures_util::StreamExt;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::config::{CryptoFeedConfig, CryptoStream};
+use crate::services::market_data::{
+    floor_minute, CandleBatch, MarketDataProvider, ProviderTasks, RangeFill, SeedAsset,
+    SeedMarketDataProvider, StreamMonitor, StreamReport,
+};
+
+/// Binance sends kline updates every ~2s; a silent socket is treated as dead.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Rows per `/api/v3/klines` request (Binance's maximum).
+const KLINES_LIMIT: usize = 1000;
+
+/// A price update decoded from the stream.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamUpdate {
+    /// Running OHLCV of the minute starting at `minute`.
+    Candle {
+        symbol: String,
+        minute: NaiveDateTime,
+        ohlcv: (Decimal, Decimal, Decimal, Decimal, Decimal),
+    },
+    Trade {
+        symbol: String,
+        time: NaiveDateTime,
+        price: Decimal,
+        quantity: Decimal,
+    },
+}
+
+impl StreamUpdate {
+    fn symbol(&self) -> &str {
+        match self {
+            StreamUpdate::Candle { symbol, .. } | StreamUpdate::Trade { symbol, .. } => symbol,
+        }
+    }
+}
+
+/// Decode one combined-stream (or raw-stream) message. Subscription acks
+/// and unknown events yield `None`.
+pub fn parse_message(text: &str) -> Option<StreamUpdate> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Envelope {
+        Combined { data: Event },
+        Raw(Event),
+    }
+
+    #[derive(Deserialize)]
+    #[serde(tag = "e")]
+    enum Event {
+        #[serde(rename = "kline")]
+        Kline {
+            #[serde(rename = "k")]
+            kline: Kline,
+        },
+        #[serde(rename = "trade")]
+        Trade {
+            #[serde(rename = "s")]
+            symbol: String,
+            #[serde(rename = "p")]
+            price: String,
+            #[serde(rename = "q")]
+            quantity: String,
+            #[serde(rename = "T")]
+            time: i64,
+        },
+    }
+
+    #[derive(Deserialize)]
+    struct Kline {
+        #[serde(rename = "t")]
+        start: i64,
+        #[serde(rename = "s")]
+        symbol: String,
+        o: String,
+        h: String,
+        l: String,
+        c: String,
+        v: String,
+    }
+
+    let event = match serde_json::from_str::<Envelope>(text).ok()? {
+        Envelope::Combined { data } | Envelope::Raw(data) => data,
+    };
+    let millis = |ms: i64| DateTime::from_timestamp_millis(ms).map(|t| t.naive_utc());
+    let num = |s: &str| Decimal::from_str(s).ok();
+    match event {
+        Event::Kline { kline: k } => Some(StreamUpdate::Candle {
+            minute: millis(k.start)?,
+            ohlcv: (num(&k.o)?, num(&k.h)?, num(&k.l)?, num(&k.c)?, num(&k.v)?),
+            symbol: k.symbol.to_ascii_uppercase(),
+        }),
+        Event::Trade { symbol, price, quantity, time } => Some(StreamUpdate::Trade {
+            symbol: symbol.to_ascii_uppercase(),
+            time: millis(time)?,
+            price: num(&price)?,
+            quantity: num(&quantity)?,
+        }),
+    }
+}
+
+/// Exchange pair for an asset symbol: the configured mapping, else the
+/// symbol followed by the quote asset.
+pub fn exchange_symbol(config: &CryptoFeedConfig, asset_symbol: &str) -> String {
+    let asset_symbol = asset_symbol.to_ascii_uppercase();
+    config
+        .symbol_map
+        .iter()
+        .find(|(asset, _)| *asset == asset_symbol)
+        .map(|(_, pair)| pair.clone())
+        .unwrap_or_else(|| format!("{}{}", asset_symbol, config.quote_asset))
+}
+
+/// Combined-stream URL for `pairs`.
+fn stream_url(config: &CryptoFeedConfig, pairs: &[String]) -> String {
+    let suffix = match config.stream {
+        CryptoStream::Kline => "kline_1m",
+        CryptoStream::Trade => "trade",
+    };
+    let streams: Vec<String> = pairs
+        .iter()
+        .map(|p| format!("{}@{}", p.to_ascii_lowercase(), suffix))
+        .collect();
+    format!("{}/stream?streams={}", config.ws_url, streams.join("/"))
+}
+
+/// Whether the asset trades on one of the feed's exchanges.
+pub fn routes_to(config: &CryptoFeedConfig, asset: &SeedAsset) -> bool {
+    asset
+        .exchange
+        .as_deref()
+        .is_some_and(|e| config.exchanges.iter().any(|x| x.eq_ignore_ascii_case(e)))
+}
+
+pub struct CryptoMarketDataProvider {
+    pool: PgPool,
+    config: CryptoFeedConfig,
+    seed: Arc<SeedMarketDataProvider>,
+    client: reqwest::Client,
+    /// Tells the stream to reload its pair list.
+    resubscribe: Arc<Notify>,
+    monitor: StreamMonitor,
+    tasks: ProviderTasks,
+}
+
+impl CryptoMarketDataProvider {
+    /// `seed` is the generator running for the other assets; streamed assets
+    /// are handed over to it whenever the stream is down.
+    pub fn new(pool: PgPool, seed: Arc<SeedMarketDataProvider>, config: CryptoFeedConfig) -> Self {
+        Self {
+            pool,
+            config,
+            seed,
+            client: reqwest::Client::new(),
+            resubscribe: Arc::new(Notify::new()),
+            monitor: StreamMonitor::default(),
+            tasks: ProviderTasks::default(),
+        }
+    }
+
+    pub fn routes_to(&self, asset: &SeedAsset) -> bool {
+        routes_to(&self.config, asset)
+    }
+
+    /// Exchange pair → asset id for every active asset on the feed's exchanges.
+    async fn subscriptions(&self) -> Result<HashMap<String, Uuid>> {
+        Ok(SeedAsset::load_active(&self.pool)
+            .await?
+            .into_iter()
+            .filter(|a| self.routes_to(a))
+            .map(|a| (exchange_symbol(&self.config, &a.symbol), a.id))
+            .collect())
+    }
+
+    /// Reconnect forever, backing off while the exchange is unreachable.
+    async fn run(self: Arc<Self>) {
+        let mut backoff = Duration::from_secs(1);
+        loop {
+            let pairs = match self.subscriptions().await {
+                Ok(pairs) => pairs,
+                Err(e) => {
+                    warn!("Crypto feed: could not load assets: {:?}", e);
+                    tokio::time::sleep(backoff).await;
+                    continue;
+                }
+            };
+            if pairs.is_empty() {
+                info!("Crypto feed: no active assets on {:?}; waiting for subscriptions", self.config.exchanges);
+                self.resubscribe.notified().await;
+                continue;
+            }
+
+            match self.stream(&pairs).await {
+                Ok(true) => {
+                    backoff = Duration::from_secs(1);
+                    continue;
+                }
+                Ok(false) => {
+                    backoff = Duration::from_secs(1);
+                    warn!("Crypto stream ended; seed prices cover until it reconnects");
+                }
+                Err(e) => {
+                    warn!("Crypto stream unavailable ({}); retrying in {:?}", e, backoff);
+                    self.monitor.disconnected(Some(e.to_string()));
+                }
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(backoff) => {}
+                _ = self.resubscribe.notified() => {}
+            }
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    /// Stream `pairs` until the connection drops (`Ok(false)`) or the asset
+    /// list changes (`Ok(true)`).
+    async fn stream(&self, pairs: &HashMap<String, Uuid>) -> Result<bool> {
+        let mut symbols: Vec<String> = pairs.keys().cloned().collect();
+        symbols.sort();
+        let url = stream_url(&self.config, &symbols);
+        let (mut ws, _) = tokio_tungstenite::connect_async(url.as_str())
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to connect to {}: {}", self.config.ws_url, e))?;
+        info!("Connected to crypto stream for {}", symbols.join(", "));
+        self.monitor.connected();
+        self.seed.set_streamed(pairs.values().copied().collect());
+
+        let outcome = loop {
+            tokio::select! {
+                msg = tokio::time::timeout(IDLE_TIMEOUT, ws.next()) => match msg {
+                    Err(_) => {
+                        warn!("Crypto stream silent for {:?}, reconnecting", IDLE_TIMEOUT);
+                        break Ok(false);
+                    }
+                    Ok(None) => break Ok(false),
+                    Ok(Some(Err(e))) => break Err(anyhow::anyhow!("Crypto stream error: {}", e)),
+                    Ok(Some(Ok(Message::Text(text)))) => {
+                        self.monitor.message();
+                        if let Some(update) = parse_message(&text) {
+                            match pairs.get(update.symbol()) {
+                                Some(asset_id) => {
+                                    if let Err(e) = self.store(*asset_id, &update).await {
+                                        tracing::error!("Failed to store crypto update: {}", e);
+                                    }
+                                }
+                                None => debug!("Update for unsubscribed pair {}", update.symbol()),
+                            }
+                        }
+                    }
+                    Ok(Some(Ok(Message::Close(_)))) => break Ok(false),
+                    // Pings are answered by tungstenite itself.
+                    Ok(Some(Ok(_))) => {}
+                },
+                _ = self.resubscribe.notified() => {
+                    info!("Crypto asset list changed, reconnecting stream");
+                    break Ok(true);
+                }
+            }
+        };
+
+        self.seed.set_streamed(HashSet::new());
+        self.monitor.disconnected(None);
+        outcome
+    }
+
+    /// Write an update into the asset's minute candle.
+    async fn store(&self, asset_id: Uuid, update: &StreamUpdate) -> Result<()> {
+        match update {
+            // A kline carries the minute so far, so it replaces the bucket.
+            StreamUpdate::Candle { minute, ohlcv: (o, h, l, c, v), .. } => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO market_prices (asset_id, timestamp, open, high, low, close, volume)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    ON CONFLICT (asset_id, timestamp) DO UPDATE SET
+                        open   = EXCLUDED.open,
+                        high   = EXCLUDED.high,
+                        low    = EXCLUDED.low,
+                        close  = EXCLUDED.close,
+                        volume = EXCLUDED.volume
+                    "#,
+                )
+                .bind(asset_id)
+                .bind(*minute)
+                .bind(*o)
+                .bind(*h)
+                .bind(*l)
+                .bind(*c)
+                .bind(*v)
+                .execute(&self.pool)
+                .await?;
+            }
+            StreamUpdate::Trade { time, price, quantity, .. } => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO market_prices (asset_id, timestamp, open, high, low, close, volume)
+                    VALUES ($1, $2, $3, $3, $3, $3, $4)
+                    ON CONFLICT (asset_id, timestamp) DO UPDATE SET
+                        high   = GREATEST(market_prices.high, EXCLUDED.high),
+                        low    = LEAST(market_prices.low, EXCLUDED.low),
+                        close  = EXCLUDED.close,
+                        volume = COALESCE(market_prices.volume, 0) + EXCLUDED.volume
+                    "#,
+                )
+                .bind(asset_id)
+                .bind(floor_minute(*time))
+                .bind(price.round_dp(4))
+                .bind(*quantity)
+                .execute(&self.pool)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// One-minute klines for `pair` over `[start, end]` from the REST API.
+    async fn fetch_klines(
+        &self,
+        pair: &str,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<Vec<(NaiveDateTime, (f64, f64, f64, f64, f64))>> {
+        let num = |v: &serde_json::Value| -> Result<f64> {
+            match v {
+                serde_json::Value::String(s) => Ok(s.parse()?),
+                other => other.as_f64().ok_or_else(|| anyhow::anyhow!("Invalid kline field {}", other)),
+            }
+        };
+
+        let end_ms = end.and_utc().timestamp_millis();
+        let mut from_ms = start.and_utc().timestamp_millis();
+        let mut out = Vec::new();
+        while from_ms <= end_ms {
+            let rows: Vec<Vec<serde_json::Value>> = self
+                .client
+                .get(format!("{}/api/v3/klines", self.config.rest_url))
+                .query(&[
+                    ("symbol", pair.to_string()),
+                    ("interval", "1m".to_string()),
+                    ("startTime", from_ms.to_string()),
+                    ("endTime", end_ms.to_string()),
+                    ("limit", KLINES_LIMIT.to_string()),
+                ])
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            let mut last_ms = None;
+            for row in &rows {
+                let open_ms = row
+                    .first()
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| anyhow::anyhow!("Kline row without open time"))?;
+                let [o, h, l, c, v] = [1, 2, 3, 4, 5].map(|i| row.get(i).map(num));
+                let (Some(o), Some(h), Some(l), Some(c), Some(v)) = (o, h, l, c, v) else {
+                    anyhow::bail!("Kline row with fewer than 6 fields");
+                };
+                let ts = DateTime::from_timestamp_millis(open_ms)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid kline open time {}", open_ms))?
+                    .naive_utc();
+                out.push((ts, (o?, h?, l?, c?, v?)));
+                last_ms = Some(open_ms);
+            }
+            match last_ms {
+                Some(last) if rows.len() == KLINES_LIMIT => from_ms = last + 60_000,
+                _ => break,
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[async_trait]
+impl MarketDataProvider for CryptoMarketDataProvider {
+    fn label(&self) -> &'static str {
+        "crypto exchange websocket + seed fallback"
+    }
+
+    async fn start(self: Arc<Self>) -> Result<()> {
+        info!("Attempting crypto exchange stream at {}...", self.config.ws_url);
+        self.tasks.spawn(self.clone().run());
+        Ok(())
+    }
+
+    /// The shared seed generator is stopped by the main provider.
+    fn stop(&self) {
+        self.tasks.abort_all();
+        self.seed.set_streamed(HashSet::new());
+        self.monitor.disconnected(None);
+    }
+
+    fn streams(&self) -> Vec<StreamReport> {
+        vec![self.monitor.report("crypto")]
+    }
+
+    fn refresh_subscriptions(&self) {
+        self.resubscribe.notify_one();
+    }
+
+    /// Exchange klines; seed math when the REST API is unavailable.
+    async fn fill_range(
+        &self,
+        asset: &SeedAsset,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<RangeFill> {
+        let pair = exchange_symbol(&self.config, &asset.symbol);
+        match self.fetch_klines(&pair, start, end).await {
+            Ok(candles) => {
+                let mut batch = CandleBatch::default();
+                for (ts, ohlcv) in candles.into_iter().filter(|(ts, _)| start <= *ts && *ts <= end) {
+                    batch.push(ts, ohlcv);
+                }
+                let rows = batch.insert(&self.pool, asset.id).await?;
+                Ok(RangeFill { rows, source: "crypto" })
+            }
+            Err(e) => {
+                warn!(
+                    "Kline fetch for {} ({}) failed ({}). Filling from seed.",
+                    asset.symbol, pair, e
+                );
+                self.seed.fill_range(asset, start, end).await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> CryptoFeedConfig {
+        CryptoFeedConfig {
+            ws_url: "ws://localhost:9876".to_string(),
+            rest_url: "http://localhost:9876".to_string(),
+            exchanges: vec!["BINANCE".to_string()],
+            quote_asset: "USDT".to_string(),
+            symbol_map: vec![("MATIC".to_string(), "POLUSDT".to_string())],
+            stream: CryptoStream::Kline,
+        }
+    }
+
+    fn utc(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn parses_combined_kline_and_raw_trade() {
+        let kline = r#"{"stream":"btcusdt@kline_1m","data":{"e":"kline","E":1700000012345,"s":"BTCUSDT",
+            "k":{"t":1699999980000,"T":1700000039999,"s":"BTCUSDT","i":"1m","o":"37000.10","c":"37010.5",
+                 "h":"37020","l":"36990.01","v":"12.5","x":false}}}"#;
+        assert_eq!(
+            parse_message(kline),
+            Some(StreamUpdate::Candle {
+                symbol: "BTCUSDT".to_string(),
+                minute: utc("2023-11-14 22:13:00"),
+                ohlcv: (
+                    Decimal::from_str("37000.10").unwrap(),
+                    Decimal::from(37020),
+                    Decimal::from_str("36990.01").unwrap(),
+                    Decimal::from_str("37010.5").unwrap(),
+                    Decimal::from_str("12.5").unwrap(),
+                ),
+            })
+        );
+
+        let trade = r#"{"e":"trade","E":1700000012345,"s":"ethusdt","t":1,"p":"2050.25","q":"0.4","T":1700000012000,"m":true}"#;
+        assert_eq!(
+            parse_message(trade),
+            Some(StreamUpdate::Trade {
+                symbol: "ETHUSDT".to_string(),
+                time: utc("2023-11-14 22:13:32"),
+                price: Decimal::from_str("2050.25").unwrap(),
+                quantity: Decimal::from_str("0.4").unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn ignores_acks_and_unknown_events() {
+        assert_eq!(parse_message(r#"{"result":null,"id":1}"#), None);
+        assert_eq!(parse_message(r#"{"stream":"x","data":{"e":"24hrTicker","s":"BTCUSDT"}}"#), None);
+        assert_eq!(parse_message("not json"), None);
+    }
+
+    #[test]
+    fn maps_assets_to_pairs_and_streams() {
+        let config = config();
+        assert_eq!(exchange_symbol(&config, "btc"), "BTCUSDT");
+        assert_eq!(exchange_symbol(&config, "MATIC"), "POLUSDT");
+        assert_eq!(
+            stream_url(&config, &["BTCUSDT".to_string(), "ETHUSDT".to_string()]),
+            "ws://localhost:9876/stream?streams=btcusdt@kline_1m/ethusdt@kline_1m"
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! Accelerated "demo day" provider (`MARKET_DATA_MODE=demo`).
+//!
+//! A historical window — candles already in the price tables, or the
+//! `MARKET_DATA_FILES` files — is re-emitted as current-time candles at
+//! `DEMO_SPEED`, so a whole trading day of real price action plays out in a
+//! class period. Contests, leaderboards and replays read those candles
+//! exactly as they read live ones. Assets without data in the window keep
+//! seed prices; with `DEMO_REPEAT` the window starts over when it ends.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDateTime};
+use rust_decimal::prelude::ToPrimitive;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::config::{DemoConfig, DemoSource, FileDataConfig};
+use crate::services::file_market_data;
+use crate::services::market_data::{
+    floor_minute, MarketDataProvider, ProviderTasks, RangeFill, SeedAsset, SeedMarketDataProvider,
+};
+use crate::services::playback::{self, TimelineBar};
+use crate::services::price_history;
+use crate::services::TradingCalendar;
+
+pub struct DemoMarketDataProvider {
+    pool: PgPool,
+    config: DemoConfig,
+    files: FileDataConfig,
+    window: (NaiveDateTime, NaiveDateTime),
+    seed: Arc<SeedMarketDataProvider>,
+    timeline: OnceCell<Vec<TimelineBar>>,
+    /// Wall-clock minute the first pass started. From then on the demo owns
+    /// the candles of the assets it plays.
+    started_at: Mutex<Option<NaiveDateTime>>,
+    tasks: ProviderTasks,
+}
+
+impl DemoMarketDataProvider {
+    pub fn new(
+        pool: PgPool,
+        calendar: Arc<TradingCalendar>,
+        config: DemoConfig,
+        files: FileDataConfig,
+        window: (NaiveDateTime, NaiveDateTime),
+    ) -> Self {
+        Self {
+            seed: Arc::new(SeedMarketDataProvider::new(pool.clone(), calendar)),
+            pool,
+            config,
+            files,
+            window,
+            timeline: OnceCell::new(),
+            started_at: Mutex::new(None),
+            tasks: ProviderTasks::default(),
+        }
+    }
+
+    /// Every bar of every active asset in the window, in time order.
+    async fn timeline(&self) -> Result<&Vec<TimelineBar>> {
+        self.timeline
+            .get_or_try_init(|| async {
+                let (start, end) = self.window;
+                let active = SeedAsset::load_active(&self.pool).await?;
+                let mut timeline = Vec::new();
+                match self.config.source {
+                    DemoSource::Database => {
+                        for asset in &active {
+                            for c in price_history::candles(&self.pool, asset.id, start, end).await? {
+                                timeline.push(TimelineBar {
+                                    source_time: c.timestamp,
+                                    asset_id: asset.id,
+                                    ohlcv: (
+                                        c.open.to_f64().unwrap_or_default(),
+                                        c.high.to_f64().unwrap_or_default(),
+                                        c.low.to_f64().unwrap_or_default(),
+                                        c.close.to_f64().unwrap_or_default(),
+                                        c.volume.and_then(|v| v.to_f64()).unwrap_or_default(),
+                                    ),
+                                });
+                            }
+                        }
+                    }
+                    DemoSource::Files => {
+                        let active: HashSet<Uuid> = active.iter().map(|a| a.id).collect();
+                        for (asset_id, series) in file_market_data::load_series(&self.pool, &self.files).await? {
+                            if !active.contains(&asset_id) {
+                                continue;
+                            }
+                            timeline.extend(window_bars(&series.bars, asset_id, self.window));
+                        }
+                    }
+                }
+                timeline.sort_by_key(|b| b.source_time);
+                Ok::<_, anyhow::Error>(timeline)
+            })
+            .await
+    }
+
+    async fn run(self: Arc<Self>) -> Result<()> {
+        let timeline = self.timeline().await?;
+        let covered: HashSet<Uuid> = timeline.iter().map(|b| b.asset_id).collect();
+        self.seed.spawn_excluding(covered.clone());
+        if timeline.is_empty() {
+            warn!(
+                "Demo window {} → {} has no candles; running on seed data only",
+                self.window.0, self.window.1
+            );
+            return Ok(());
+        }
+
+        let mut wall_start = playback::next_minute();
+        loop {
+            self.started_at
+                .lock()
+                .expect("demo state lock poisoned")
+                .get_or_insert(wall_start);
+            info!(
+                "Demo: playing {} → {} ({} bars, {} assets) at {}x from {}",
+                self.window.0,
+                self.window.1,
+                timeline.len(),
+                covered.len(),
+                self.config.speed,
+                wall_start
+            );
+            playback::play(&self.pool, timeline, self.config.speed, wall_start).await?;
+            if !self.config.repeat {
+                info!("Demo window finished");
+                return Ok(());
+            }
+            wall_start = next_pass(timeline, self.config.speed, wall_start).max(playback::next_minute());
+        }
+    }
+}
+
+/// Bars of one file series inside the `[start, end]` window.
+fn window_bars<'a>(
+    bars: &'a [(NaiveDateTime, playback::Ohlcv)],
+    asset_id: Uuid,
+    (start, end): (NaiveDateTime, NaiveDateTime),
+) -> impl Iterator<Item = TimelineBar> + 'a {
+    bars.iter()
+        .filter(move |(t, _)| start <= *t && *t <= end)
+        .map(move |(t, b)| TimelineBar {
+            source_time: *t,
+            asset_id,
+            ohlcv: *b,
+        })
+}
+
+/// With `DEMO_REPEAT`, the next pass starts on the minute after the candle
+/// of the last bar of the pass that started at `wall_start`.
+fn next_pass(timeline: &[TimelineBar], speed: f64, wall_start: NaiveDateTime) -> NaiveDateTime {
+    let (Some(first), Some(last)) = (timeline.first(), timeline.last()) else {
+        return wall_start;
+    };
+    let last_due = playback::due_at(wall_start, first.source_time, last.source_time, speed);
+    floor_minute(last_due) + Duration::minutes(1)
+}
+
+#[async_trait]
+impl MarketDataProvider for DemoMarketDataProvider {
+    fn label(&self) -> &'static str {
+        "demo (accelerated historical window) + seed fallback"
+    }
+
+    async fn start(self: Arc<Self>) -> Result<()> {
+        let provider = self.clone();
+        self.tasks.spawn(async move {
+            if let Err(e) = provider.run().await {
+                warn!("Demo playback failed: {:?}", e);
+            }
+        });
+        Ok(())
+    }
+
+    fn stop(&self) {
+        self.tasks.abort_all();
+        self.seed.stop();
+    }
+
+    /// Seed math, except over minutes the demo has already taken over — a
+    /// compressed window leaves minutes without a bar, and those must not be
+    /// filled with synthetic prices.
+    async fn fill_range(
+        &self,
+        asset: &SeedAsset,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<RangeFill> {
+        let started_at = *self.started_at.lock().expect("demo state lock poisoned");
+        let covered = match (started_at, self.timeline.get()) {
+            (Some(_), Some(timeline)) => timeline.iter().any(|b| b.asset_id == asset.id),
+            _ => false,
+        };
+        let end = match started_at {
+            Some(at) if covered => end.min(at - Duration::minutes(1)),
+            _ => end,
+        };
+        if start > end {
+            return Ok(RangeFill { rows: 0, source: "demo" });
+        }
+        self.seed.fill_range(asset, start, end).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn bar(source_time: NaiveDateTime) -> TimelineBar {
+        TimelineBar {
+            source_time,
+            asset_id: Uuid::nil(),
+            ohlcv: (1.0, 1.0, 1.0, 1.0, 0.0),
+        }
+    }
+
+    /// Minute candle a bar lands in on the pass that started at `wall_start`.
+    fn candle_of(timeline: &[TimelineBar], speed: f64, wall_start: NaiveDateTime, i: usize) -> NaiveDateTime {
+        floor_minute(playback::due_at(wall_start, timeline[0].source_time, timeline[i].source_time, speed))
+    }
+
+    #[test]
+    fn window_bars_keep_the_inclusive_window() {
+        let open = utc("2024-03-15 03:45:00");
+        let bars: Vec<_> = (0..5)
+            .map(|i| (open + Duration::minutes(i), (1.0, 1.0, 1.0, 1.0, 0.0)))
+            .collect();
+        let id = Uuid::new_v4();
+        let kept: Vec<_> = window_bars(&bars, id, (open + Duration::minutes(1), open + Duration::minutes(3))).collect();
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept[0].source_time, open + Duration::minutes(1));
+        assert_eq!(kept[2].source_time, open + Duration::minutes(3));
+        assert!(kept.iter().all(|b| b.asset_id == id));
+    }
+
+    #[test]
+    fn source_times_map_onto_the_current_clock() {
+        // An NSE session (03:45-10:00 UTC) at 6.25x plays in an hour.
+        let open = utc("2024-03-15 03:45:00");
+        let timeline: Vec<_> = [0, 1, 60, 374].iter().map(|&m| bar(open + Duration::minutes(m))).collect();
+        let wall = utc("2026-10-18 10:00:00");
+        assert_eq!(candle_of(&timeline, 6.25, wall, 0), wall);
+        // 1 source minute is 9.6s, so consecutive bars share a candle
+        assert_eq!(candle_of(&timeline, 6.25, wall, 1), wall);
+        assert_eq!(candle_of(&timeline, 6.25, wall, 2), utc("2026-10-18 10:09:00"));
+        assert_eq!(candle_of(&timeline, 6.25, wall, 3), utc("2026-10-18 10:59:00"));
+    }
+
+    #[test]
+    fn repeat_passes_follow_the_last_candle() {
+        let open = utc("2024-03-15 03:45:00");
+        let timeline: Vec<_> = [0, 374].iter().map(|&m| bar(open + Duration::minutes(m))).collect();
+        let wall = utc("2026-10-18 10:00:00");
+        let second = next_pass(&timeline, 6.25, wall);
+        assert_eq!(second, utc("2026-10-18 11:00:00"));
+        // The wrapped pass replays the window from its first bar
+        assert_eq!(candle_of(&timeline, 6.25, second, 0), second);
+        assert_eq!(candle_of(&timeline, 6.25, second, 1), utc("2026-10-18 11:59:00"));
+        assert_eq!(next_pass(&timeline, 6.25, second), utc("2026-10-18 12:00:00"));
+
+        // At real time, a single-bar window repeats every minute
+        assert_eq!(next_pass(&timeline[..1], 1.0, wall), utc("2026-10-18 10:01:00"));
+        assert_eq!(next_pass(&[], 1.0, wall), wall);
+    }
+}
@@ -0,0 +1,757 @@
+//! File-based market data (`MARKET_DATA_MODE=file`).
+//!
+//! OHLCV history is read from CSV or Parquet files and imported into the
+//! price tables, so contests and replays can run on real history without a
+//! broker connection. Each symbol's bar spacing decides its tier: minute (and
+//! finer) bars go to `market_prices`, hourly bars to `market_prices_1h`, daily
+//! bars to `market_prices_1d`. The files are the source of truth — a re-import
+//! overwrites the buckets they cover.
+//!
+//! Timestamps with an offset (or Parquet timestamps adjusted to UTC) are used
+//! as-is; naive ones are read in `MARKET_DATA_FILE_TIMEZONE`. Date-only rows
+//! are daily bars for that date.
+//!
+//! Optionally one day of imported minute data is then played forward as if
+//! live, starting at the current minute. Assets without file data fall back
+//! to the seed generator, as in live mode.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, SubsecRound, TimeZone, Timelike};
+use chrono_tz::Tz;
+use parquet::basic::LogicalType;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::record::Field;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::config::FileDataConfig;
+use crate::services::market_data::{
+    f64_to_decimal, CandleBatch, MarketDataProvider, ProviderTasks, RangeFill, SeedAsset,
+    SeedMarketDataProvider,
+};
+use crate::services::playback::{self, Ohlcv, TimelineBar};
+use crate::services::price_history::Resolution;
+use crate::services::TradingCalendar;
+
+/// Rows per multi-row insert.
+const IMPORT_CHUNK: usize = 5_000;
+
+/// When a row happened, as written in the file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BarTime {
+    /// Absolute instant (offset given, epoch number, UTC-adjusted Parquet).
+    Utc(NaiveDateTime),
+    /// Wall-clock time in the configured file timezone.
+    Local(NaiveDateTime),
+    /// A trading date (daily bar).
+    Date(NaiveDate),
+}
+
+/// One row of a data file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileBar {
+    pub symbol: Option<String>,
+    pub time: BarTime,
+    pub ohlcv: Ohlcv,
+}
+
+/// Bars of one asset, bucketed to the tier they are stored in.
+#[derive(Debug, Clone)]
+pub struct Series {
+    pub symbol: String,
+    pub resolution: Resolution,
+    /// UTC bucket start → OHLCV, ordered.
+    pub bars: Vec<(NaiveDateTime, Ohlcv)>,
+}
+
+// ──────────────────────────────────────────────────────────────────────────────
+// Parsing
+// ──────────────────────────────────────────────────────────────────────────────
+
+/// Positions of the recognised columns in a file's header.
+#[derive(Debug, Clone, PartialEq)]
+struct Columns {
+    time: usize,
+    /// Separate time-of-day column next to a `date` column.
+    time_of_day: Option<usize>,
+    symbol: Option<usize>,
+    open: usize,
+    high: usize,
+    low: usize,
+    close: usize,
+    volume: Option<usize>,
+}
+
+impl Columns {
+    fn detect(headers: &[String]) -> Result<Self> {
+        let names: Vec<String> = headers
+            .iter()
+            .map(|h| h.trim().trim_start_matches('\u{feff}').to_ascii_lowercase())
+            .collect();
+        let find = |candidates: &[&str]| candidates.iter().find_map(|c| names.iter().position(|n| n == c));
+        let require = |candidates: &[&str]| {
+            find(candidates).with_context(|| format!("missing '{}' column", candidates[0]))
+        };
+
+        let (time, time_of_day) = match find(&["timestamp", "datetime", "date_time", "ts"]) {
+            Some(i) => (i, None),
+            None => {
+                let date = require(&["date", "time"])?;
+                let time = find(&["time"]).filter(|t| *t != date);
+                (date, time)
+            }
+        };
+
+        Ok(Self {
+            time,
+            time_of_day,
+            symbol: find(&["symbol", "ticker", "tradingsymbol", "instrument"]),
+            open: require(&["open", "o"])?,
+            high: require(&["high", "h"])?,
+            low: require(&["low", "l"])?,
+            close: require(&["close", "c"])?,
+            volume: find(&["volume", "vol", "v"]),
+        })
+    }
+}
+
+/// Parse a textual timestamp. Accepts RFC 3339 / ISO 8601 with or without
+/// an offset, `YYYY-MM-DD[ HH:MM[:SS[.f]]]`, and epoch seconds or millis.
+pub fn parse_time(value: &str) -> Option<BarTime> {
+    let value = value.trim();
+    if !value.is_empty() && value.chars().all(|c| c.is_ascii_digit()) {
+        let n: i64 = value.parse().ok()?;
+        // 13+ digits is epoch millis.
+        let dt = if value.len() >= 13 {
+            DateTime::from_timestamp_millis(n)
+        } else {
+            DateTime::from_timestamp(n, 0)
+        };
+        return dt.map(|d| BarTime::Utc(d.naive_utc()));
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(BarTime::Utc(dt.naive_utc()));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S%.f%:z", "%Y-%m-%d %H:%M:%S%z", "%Y-%m-%dT%H:%M:%S%z"] {
+        if let Ok(dt) = DateTime::parse_from_str(value, format) {
+            return Some(BarTime::Utc(dt.naive_utc()));
+        }
+    }
+    for format in ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(BarTime::Local(dt));
+        }
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok().map(BarTime::Date)
+}
+
+fn parse_number(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    value.replace(',', "").parse::<f64>().ok().filter(|x| x.is_finite())
+}
+
+/// Read a CSV file. Rows with an unparseable time or price are skipped and
+/// counted.
+pub fn read_csv(reader: impl std::io::Read) -> Result<(Vec<FileBar>, usize)> {
+    let mut csv = csv::ReaderBuilder::new().flexible(true).trim(csv::Trim::All).from_reader(reader);
+    let headers: Vec<String> = csv.headers()?.iter().map(str::to_string).collect();
+    let columns = Columns::detect(&headers)?;
+
+    let (mut bars, mut skipped) = (Vec::new(), 0);
+    for record in csv.records() {
+        let record = record?;
+        let field = |i: usize| record.get(i).unwrap_or("");
+        let time = match columns.time_of_day {
+            Some(t) => parse_time(&format!("{} {}", field(columns.time), field(t))),
+            None => parse_time(field(columns.time)),
+        };
+        let prices = (
+            parse_number(field(columns.open)),
+            parse_number(field(columns.high)),
+            parse_number(field(columns.low)),
+            parse_number(field(columns.close)),
+        );
+        match (time, prices) {
+            (Some(time), (Some(o), Some(h), Some(l), Some(c))) => bars.push(FileBar {
+                symbol: columns.symbol.map(|i| field(i).to_string()).filter(|s| !s.is_empty()),
+                time,
+                ohlcv: (o, h, l, c, columns.volume.and_then(|i| parse_number(field(i))).unwrap_or(0.0)),
+            }),
+            _ => skipped += 1,
+        }
+    }
+    Ok((bars, skipped))
+}
+
+/// Read a Parquet file with the row API (no Arrow).
+pub fn read_parquet(path: &Path) -> Result<(Vec<FileBar>, usize)> {
+    let reader = SerializedFileReader::new(std::fs::File::open(path)?)?;
+    let schema = reader.metadata().file_metadata().schema_descr_ptr();
+    let headers: Vec<String> = schema.columns().iter().map(|c| c.name().to_string()).collect();
+    let columns = Columns::detect(&headers)?;
+    let time_is_utc = matches!(
+        schema.column(columns.time).logical_type(),
+        Some(LogicalType::Timestamp { is_adjusted_to_u_t_c: true, .. })
+    );
+
+    let (mut bars, mut skipped) = (Vec::new(), 0);
+    for row in reader.get_row_iter(None)? {
+        let row = row?;
+        let fields: Vec<&Field> = row.get_column_iter().map(|(_, f)| f).collect();
+        let field = |i: usize| fields.get(i).copied();
+        let time = field(columns.time).and_then(|f| field_time(f, time_is_utc)).and_then(|t| {
+            match (t, columns.time_of_day.and_then(field)) {
+                (BarTime::Date(d), Some(Field::Str(tod))) => parse_time(&format!("{} {}", d, tod)),
+                (t, _) => Some(t),
+            }
+        });
+        let number = |i: usize| field(i).and_then(field_number);
+        match (time, number(columns.open), number(columns.high), number(columns.low), number(columns.close)) {
+            (Some(time), Some(o), Some(h), Some(l), Some(c)) => bars.push(FileBar {
+                symbol: columns.symbol.and_then(field).and_then(|f| match f {
+                    Field::Str(s) if !s.is_empty() => Some(s.clone()),
+                    _ => None,
+                }),
+                time,
+                ohlcv: (o, h, l, c, columns.volume.and_then(number).unwrap_or(0.0)),
+            }),
+            _ => skipped += 1,
+        }
+    }
+    Ok((bars, skipped))
+}
+
+fn field_time(field: &Field, utc: bool) -> Option<BarTime> {
+    let wrap = |dt: NaiveDateTime| if utc { BarTime::Utc(dt) } else { BarTime::Local(dt) };
+    match field {
+        Field::TimestampMillis(ms) => DateTime::from_timestamp_millis(*ms).map(|d| wrap(d.naive_utc())),
+        Field::TimestampMicros(us) => DateTime::from_timestamp_micros(*us).map(|d| wrap(d.naive_utc())),
+        Field::Date(days) => NaiveDate::from_ymd_opt(1970, 1, 1)
+            .and_then(|epoch| epoch.checked_add_signed(Duration::days(i64::from(*days))))
+            .map(BarTime::Date),
+        Field::Str(s) => parse_time(s),
+        Field::Long(n) => parse_time(&n.to_string()),
+        _ => None,
+    }
+}
+
+fn field_number(field: &Field) -> Option<f64> {
+    let x = match field {
+        Field::Double(x) => *x,
+        Field::Float(x) => f64::from(*x),
+        Field::Int(x) => f64::from(*x),
+        Field::Long(x) => *x as f64,
+        Field::UInt(x) => f64::from(*x),
+        Field::ULong(x) => *x as f64,
+        Field::Short(x) => f64::from(*x),
+        Field::Decimal(d) => {
+            let bytes = d.data();
+            if bytes.is_empty() || bytes.len() > 16 {
+                return None;
+            }
+            // Big-endian two's complement, sign-extended to 128 bits.
+            let fill = if bytes[0] & 0x80 != 0 { 0xff } else { 0 };
+            let mut buf = [fill; 16];
+            buf[16 - bytes.len()..].copy_from_slice(bytes);
+            Decimal::try_from_i128_with_scale(i128::from_be_bytes(buf), d.scale() as u32)
+                .ok()?
+                .to_f64()?
+        }
+        Field::Str(s) => return parse_number(s),
+        _ => return None,
+    };
+    x.is_finite().then_some(x)
+}
+
+// ──────────────────────────────────────────────────────────────────────────────
+// Bucketing
+// ──────────────────────────────────────────────────────────────────────────────
+
+/// UTC instant of a bar, or `None` for a local time that does not exist
+/// (DST gap).
+fn to_utc(time: BarTime, tz: Tz) -> Option<NaiveDateTime> {
+    match time {
+        BarTime::Utc(t) => Some(t),
+        BarTime::Local(t) => tz.from_local_datetime(&t).earliest().map(|d| d.naive_utc()),
+        BarTime::Date(d) => d.and_hms_opt(0, 0, 0),
+    }
+}
+
+/// Tier for a series from its typical (median) bar spacing.
+pub fn detect_resolution(times: &[BarTime], tz: Tz) -> Resolution {
+    if times.iter().any(|t| matches!(t, BarTime::Date(_))) {
+        return Resolution::Day;
+    }
+    let mut instants: Vec<NaiveDateTime> = times.iter().filter_map(|t| to_utc(*t, tz)).collect();
+    instants.sort();
+    instants.dedup();
+    let mut gaps: Vec<i64> = instants.windows(2).map(|w| (w[1] - w[0]).num_minutes()).collect();
+    if gaps.is_empty() {
+        return Resolution::Minute;
+    }
+    gaps.sort_unstable();
+    match gaps[gaps.len() / 2] {
+        m if m >= 1_440 => Resolution::Day,
+        m if m >= 60 => Resolution::Hour,
+        _ => Resolution::Minute,
+    }
+}
+
+/// Aggregate bars into `resolution` buckets (UTC bucket starts): first open,
+/// max high, min low, last close, summed volume. Daily buckets are the local
+/// trading date.
+pub fn bucket(bars: &[FileBar], resolution: Resolution, tz: Tz) -> Vec<(NaiveDateTime, Ohlcv)> {
+    let mut timed: Vec<(NaiveDateTime, NaiveDateTime, Ohlcv)> = bars
+        .iter()
+        .filter_map(|b| {
+            let instant = to_utc(b.time, tz)?;
+            let key = match (resolution, b.time) {
+                (Resolution::Day, BarTime::Date(d)) => d.and_hms_opt(0, 0, 0)?,
+                (Resolution::Day, BarTime::Local(t)) => t.date().and_hms_opt(0, 0, 0)?,
+                (Resolution::Day, BarTime::Utc(t)) => {
+                    tz.from_utc_datetime(&t).date_naive().and_hms_opt(0, 0, 0)?
+                }
+                (Resolution::Hour, _) => instant.date().and_hms_opt(instant.hour(), 0, 0)?,
+                (Resolution::Minute, _) => instant.trunc_subsecs(0).with_second(0)?,
+            };
+            Some((key, instant, b.ohlcv))
+        })
+        .collect();
+    timed.sort_by_key(|(key, instant, _)| (*key, *instant));
+
+    let mut out: Vec<(NaiveDateTime, Ohlcv)> = Vec::new();
+    for (key, _, (o, h, l, c, v)) in timed {
+        match out.last_mut() {
+            Some((last, agg)) if *last == key => {
+                agg.1 = agg.1.max(h);
+                agg.2 = agg.2.min(l);
+                agg.3 = c;
+                agg.4 += v;
+            }
+            _ => out.push((key, (o, h, l, c, v))),
+        }
+    }
+    out
+}
+
+/// Asset symbol for a file symbol: explicit mapping first (case-insensitive),
+/// otherwise the symbol itself, upper-cased.
+fn map_symbol(symbol: &str, mapping: &[(String, String)]) -> String {
+    mapping
+        .iter()
+        .find(|(from, _)| from.eq_ignore_ascii_case(symbol.trim()))
+        .map(|(_, to)| to.clone())
+        .unwrap_or_else(|| symbol.trim().to_ascii_uppercase())
+}
+
+/// Every `.csv` / `.parquet` file under the configured paths.
+fn data_files(paths: &[String]) -> Result<Vec<PathBuf>> {
+    let is_data = |p: &Path| {
+        matches!(
+            p.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref(),
+            Some("csv" | "parquet" | "pq")
+        )
+    };
+    let mut files = Vec::new();
+    for path in paths.iter().map(PathBuf::from) {
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = std::fs::read_dir(&path)
+                .with_context(|| format!("reading {}", path.display()))?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file() && is_data(p))
+                .collect();
+            entries.sort();
+            files.extend(entries);
+        } else if path.is_file() {
+            files.push(path);
+        } else {
+            anyhow::bail!("market data file {} does not exist", path.display());
+        }
+    }
+    Ok(files)
+}
+
+/// Read every file and group its rows by asset symbol.
+fn read_all(config: &FileDataConfig) -> Result<HashMap<String, Vec<FileBar>>> {
+    let mut by_symbol: HashMap<String, Vec<FileBar>> = HashMap::new();
+    for path in data_files(&config.paths)? {
+        let is_parquet = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("parquet") || e.eq_ignore_ascii_case("pq"));
+        let (bars, skipped) = if is_parquet {
+            read_parquet(&path)
+        } else {
+            read_csv(std::fs::File::open(&path)?)
+        }
+        .with_context(|| format!("reading {}", path.display()))?;
+
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+        info!("Read {} bars from {} ({} rows skipped)", bars.len(), path.display(), skipped);
+        for bar in bars {
+            let symbol = map_symbol(bar.symbol.as_deref().unwrap_or(&stem), &config.symbol_map);
+            by_symbol.entry(symbol).or_default().push(bar);
+        }
+    }
+    Ok(by_symbol)
+}
+
+/// Read the configured files and resolve them to assets, keyed by asset id.
+/// Symbols without a matching asset are logged and skipped.
+pub async fn load_series(pool: &PgPool, config: &FileDataConfig) -> Result<HashMap<Uuid, Series>> {
+    let by_symbol = {
+        let config = config.clone();
+        tokio::task::spawn_blocking(move || read_all(&config)).await??
+    };
+
+    let assets: HashMap<String, Uuid> = sqlx::query_as::<_, (String, Uuid)>("SELECT symbol, id FROM assets")
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+
+    let mut series = HashMap::new();
+    for (symbol, bars) in by_symbol {
+        let Some(&asset_id) = assets.get(&symbol) else {
+            warn!("File data for unknown symbol {} ignored (add a MARKET_DATA_SYMBOL_MAP entry or create the asset)", symbol);
+            continue;
+        };
+        let times: Vec<BarTime> = bars.iter().map(|b| b.time).collect();
+        let resolution = detect_resolution(&times, config.timezone);
+        let bars = bucket(&bars, resolution, config.timezone);
+        series.insert(asset_id, Series { symbol, resolution, bars });
+    }
+    Ok(series)
+}
+
+/// Overwrite `resolution` buckets of an asset with file bars.
+async fn upsert(pool: &PgPool, asset_id: Uuid, resolution: Resolution, bars: &[(NaiveDateTime, Ohlcv)]) -> Result<u64> {
+    let mut rows = 0;
+    for chunk in bars.chunks(IMPORT_CHUNK) {
+        let timestamps: Vec<NaiveDateTime> = chunk.iter().map(|(t, _)| *t).collect();
+        let column = |f: fn(&Ohlcv) -> f64| -> Vec<Decimal> { chunk.iter().map(|(_, b)| f64_to_decimal(f(b))).collect() };
+        rows += sqlx::query(&format!(
+            r#"
+            INSERT INTO {} (asset_id, timestamp, open, high, low, close, volume)
+            SELECT $1, t.*
+            FROM UNNEST($2::timestamp[], $3::numeric[], $4::numeric[],
+                        $5::numeric[], $6::numeric[], $7::numeric[]) AS t
+            ON CONFLICT (asset_id, timestamp) DO UPDATE SET
+                open = EXCLUDED.open, high = EXCLUDED.high, low = EXCLUDED.low,
+                close = EXCLUDED.close, volume = EXCLUDED.volume
+            "#,
+            resolution.table()
+        ))
+        .bind(asset_id)
+        .bind(&timestamps)
+        .bind(column(|b| b.0))
+        .bind(column(|b| b.1))
+        .bind(column(|b| b.2))
+        .bind(column(|b| b.3))
+        .bind(column(|b| b.4))
+        .execute(pool)
+        .await?
+        .rows_affected();
+    }
+    Ok(rows)
+}
+
+// ──────────────────────────────────────────────────────────────────────────────
+// Provider
+// ──────────────────────────────────────────────────────────────────────────────
+
+pub struct FileMarketDataProvider {
+    pool: PgPool,
+    config: FileDataConfig,
+    seed: Arc<SeedMarketDataProvider>,
+    /// File series keyed by asset id, read on first use.
+    series: OnceCell<HashMap<Uuid, Series>>,
+    tasks: ProviderTasks,
+}
+
+impl FileMarketDataProvider {
+    pub fn new(pool: PgPool, calendar: Arc<TradingCalendar>, config: FileDataConfig) -> Self {
+        Self {
+            seed: Arc::new(SeedMarketDataProvider::new(pool.clone(), calendar)),
+            pool,
+            config,
+            series: OnceCell::new(),
+            tasks: ProviderTasks::default(),
+        }
+    }
+
+    async fn series(&self) -> Result<&HashMap<Uuid, Series>> {
+        self.series.get_or_try_init(|| load_series(&self.pool, &self.config)).await
+    }
+
+    /// Write every file series into its tier.
+    async fn import(&self) -> Result<()> {
+        for (asset_id, s) in self.series().await? {
+            let rows = upsert(&self.pool, *asset_id, s.resolution, &s.bars).await?;
+            info!(
+                "Imported {} {} bars for {} ({} → {})",
+                rows,
+                s.resolution.table(),
+                s.symbol,
+                s.bars.first().map(|b| b.0.to_string()).unwrap_or_default(),
+                s.bars.last().map(|b| b.0.to_string()).unwrap_or_default(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Replay the configured day of minute bars with their original spacing
+    /// (divided by the playback speed), stamped from the current minute on.
+    async fn play(&self, date: NaiveDate) -> Result<()> {
+        let tz = self.config.timezone;
+        let active: HashSet<Uuid> = SeedAsset::load_active(&self.pool).await?.into_iter().map(|a| a.id).collect();
+        let mut timeline: Vec<TimelineBar> = self
+            .series()
+            .await?
+            .iter()
+            .filter(|(id, s)| active.contains(id) && s.resolution == Resolution::Minute)
+            .flat_map(|(id, s)| {
+                s.bars.iter().map(move |(t, b)| TimelineBar {
+                    source_time: *t,
+                    asset_id: *id,
+                    ohlcv: *b,
+                })
+            })
+            .filter(|b| tz.from_utc_datetime(&b.source_time).date_naive() == date)
+            .collect();
+        timeline.sort_by_key(|b| b.source_time);
+        if timeline.is_empty() {
+            warn!("No minute data on {} to play back", date);
+            return Ok(());
+        }
+
+        let wall_start = playback::next_minute();
+        info!(
+            "Playing back {} ({} bars) at {}x from {}",
+            date,
+            timeline.len(),
+            self.config.playback_speed,
+            wall_start
+        );
+        playback::play(&self.pool, &timeline, self.config.playback_speed, wall_start).await?;
+        info!("Playback of {} finished", date);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MarketDataProvider for FileMarketDataProvider {
+    fn label(&self) -> &'static str {
+        "file (csv/parquet) + seed fallback"
+    }
+
+    async fn start(self: Arc<Self>) -> Result<()> {
+        let provider = self.clone();
+        self.tasks.spawn(async move {
+            if let Err(e) = provider.import().await {
+                warn!("File market-data import failed: {:?}", e);
+                return;
+            }
+            let covered = provider.series().await.map(|s| s.keys().copied().collect()).unwrap_or_default();
+            provider.seed.spawn_excluding(covered);
+            if let Some(date) = provider.config.playback_date {
+                if let Err(e) = provider.play(date).await {
+                    warn!("File playback failed: {:?}", e);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    fn stop(&self) {
+        self.tasks.abort_all();
+        self.seed.stop();
+    }
+
+    /// Minute bars from the files for assets they cover (other tiers are
+    /// written by the import); seed math for everything else.
+    async fn fill_range(
+        &self,
+        asset: &SeedAsset,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<RangeFill> {
+        let series = match self.series().await {
+            Ok(series) => series,
+            Err(e) => {
+                warn!("File market data unavailable ({:?}); filling {} from seed", e, asset.symbol);
+                return self.seed.fill_range(asset, start, end).await;
+            }
+        };
+        let Some(s) = series.get(&asset.id) else {
+            return self.seed.fill_range(asset, start, end).await;
+        };
+        let mut batch = CandleBatch::default();
+        if s.resolution == Resolution::Minute {
+            let from = s.bars.partition_point(|(t, _)| *t < start);
+            for (t, b) in s.bars[from..].iter().take_while(|(t, _)| *t <= end) {
+                batch.push(*t, *b);
+            }
+        }
+        let rows = batch.insert(&self.pool, asset.id).await?;
+        Ok(RangeFill { rows, source: "file" })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn parses_common_timestamp_shapes() {
+        assert_eq!(parse_time("2024-03-15T09:15:00+05:30"), Some(BarTime::Utc(utc("2024-03-15 03:45:00"))));
+        assert_eq!(parse_time("2024-03-15 09:15:00+0530"), Some(BarTime::Utc(utc("2024-03-15 03:45:00"))));
+        assert_eq!(parse_time("2024-03-15 09:15"), Some(BarTime::Local(utc("2024-03-15 09:15:00"))));
+        assert_eq!(parse_time("1710474300"), Some(BarTime::Utc(utc("2024-03-15 03:45:00"))));
+        assert_eq!(parse_time("1710474300000"), Some(BarTime::Utc(utc("2024-03-15 03:45:00"))));
+        assert_eq!(
+            parse_time("2024-03-15"),
+            Some(BarTime::Date(NaiveDate::from_ymd_opt(2024, 3, 15).unwrap()))
+        );
+        assert_eq!(parse_time("yesterday"), None);
+    }
+
+    #[test]
+    fn reads_csv_with_split_date_and_time_columns() {
+        let data = "Date,Time,Ticker,Open,High,Low,Close,Volume\n\
+                    2024-03-15,09:15,RELIANCE.NS,2900,2910,2895,2905,\"1,200\"\n\
+                    2024-03-15,09:16,RELIANCE.NS,2905,2906,2899,2901,800\n\
+                    2024-03-15,bad,RELIANCE.NS,1,1,1,1,1\n";
+        let (bars, skipped) = read_csv(data.as_bytes()).unwrap();
+        assert_eq!(skipped, 1);
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[0].symbol.as_deref(), Some("RELIANCE.NS"));
+        assert_eq!(bars[0].time, BarTime::Local(utc("2024-03-15 09:15:00")));
+        assert_eq!(bars[0].ohlcv, (2900.0, 2910.0, 2895.0, 2905.0, 1200.0));
+    }
+
+    #[test]
+    fn missing_price_column_is_an_error() {
+        assert!(read_csv("timestamp,open,high,low\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn local_times_are_converted_with_the_file_timezone() {
+        let bars = vec![FileBar {
+            symbol: None,
+            time: BarTime::Local(utc("2024-03-15 09:15:30")),
+            ohlcv: (1.0, 1.0, 1.0, 1.0, 1.0),
+        }];
+        let out = bucket(&bars, Resolution::Minute, chrono_tz::Asia::Kolkata);
+        assert_eq!(out[0].0, utc("2024-03-15 03:45:00"));
+        // New York: 2024-03-10 02:30 does not exist (DST gap).
+        let gap = vec![FileBar { time: BarTime::Local(utc("2024-03-10 02:30:00")), ..bars[0].clone() }];
+        assert!(bucket(&gap, Resolution::Minute, chrono_tz::America::New_York).is_empty());
+    }
+
+    #[test]
+    fn resolution_follows_bar_spacing() {
+        let minutes: Vec<BarTime> = (0..10).map(|i| BarTime::Utc(utc("2024-03-15 03:45:00") + Duration::minutes(i))).collect();
+        let hours: Vec<BarTime> = (0..10).map(|i| BarTime::Utc(utc("2024-03-15 03:00:00") + Duration::hours(i))).collect();
+        let days: Vec<BarTime> = (0..10).map(|i| BarTime::Utc(utc("2024-03-15 00:00:00") + Duration::days(i))).collect();
+        assert_eq!(detect_resolution(&minutes, Tz::UTC), Resolution::Minute);
+        assert_eq!(detect_resolution(&hours, Tz::UTC), Resolution::Hour);
+        assert_eq!(detect_resolution(&days, Tz::UTC), Resolution::Day);
+    }
+
+    #[test]
+    fn bucketing_aggregates_finer_bars() {
+        let bar = |s: &str, o, h, l, c| FileBar {
+            symbol: None,
+            time: BarTime::Utc(utc(s)),
+            ohlcv: (o, h, l, c, 10.0),
+        };
+        let bars = vec![
+            bar("2024-03-15 10:00:30", 101.0, 103.0, 100.0, 102.0),
+            bar("2024-03-15 10:00:00", 100.0, 101.0, 99.0, 101.0),
+            bar("2024-03-15 10:01:00", 102.0, 102.0, 98.0, 99.0),
+        ];
+        let out = bucket(&bars, Resolution::Minute, Tz::UTC);
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0], (utc("2024-03-15 10:00:00"), (100.0, 103.0, 99.0, 102.0, 20.0)));
+        // Daily buckets follow the local trading date: 23:00 UTC is the next day in IST.
+        let late = vec![bar("2024-03-15 23:00:00", 1.0, 1.0, 1.0, 1.0)];
+        let daily = bucket(&late, Resolution::Day, chrono_tz::Asia::Kolkata);
+        assert_eq!(daily[0].0, utc("2024-03-16 00:00:00"));
+    }
+
+    #[test]
+    fn reads_parquet_utc_timestamps() {
+        use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+        use parquet::file::properties::WriterProperties;
+        use parquet::file::writer::SerializedFileWriter;
+        use parquet::schema::parser::parse_message_type;
+
+        let schema = parse_message_type(
+            "message bars {
+                required int64 timestamp (TIMESTAMP(MILLIS, true));
+                required binary symbol (UTF8);
+                required double open;
+                required double high;
+                required double low;
+                required double close;
+            }",
+        )
+        .unwrap();
+        let path = std::env::temp_dir().join(format!("bars-{}.parquet", Uuid::new_v4()));
+        let mut writer = SerializedFileWriter::new(
+            std::fs::File::create(&path).unwrap(),
+            Arc::new(schema),
+            Arc::new(WriterProperties::builder().build()),
+        )
+        .unwrap();
+        let mut group = writer.next_row_group().unwrap();
+        let mut column = group.next_column().unwrap().unwrap();
+        column.typed::<Int64Type>().write_batch(&[1_710_474_300_000], None, None).unwrap();
+        column.close().unwrap();
+        let mut column = group.next_column().unwrap().unwrap();
+        column.typed::<ByteArrayType>().write_batch(&[ByteArray::from("INFY")], None, None).unwrap();
+        column.close().unwrap();
+        for value in [1500.0, 1510.0, 1495.0, 1505.0] {
+            let mut column = group.next_column().unwrap().unwrap();
+            column.typed::<DoubleType>().write_batch(&[value], None, None).unwrap();
+            column.close().unwrap();
+        }
+        group.close().unwrap();
+        writer.close().unwrap();
+
+        let (bars, skipped) = read_parquet(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(skipped, 0);
+        assert_eq!(
+            bars,
+            vec![FileBar {
+                symbol: Some("INFY".to_string()),
+                time: BarTime::Utc(utc("2024-03-15 03:45:00")),
+                ohlcv: (1500.0, 1510.0, 1495.0, 1505.0, 0.0),
+            }]
+        );
+    }
+
+    #[test]
+    fn symbols_map_case_insensitively() {
+        let map = vec![("reliance.ns".to_string(), "RELIANCE".to_string())];
+        assert_eq!(map_symbol("RELIANCE.NS", &map), "RELIANCE");
+        assert_eq!(map_symbol(" btc ", &map), "BTC");
+    }
+}
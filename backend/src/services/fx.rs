@@ -0,0 +1,150 @@
+//! Currency conversion.
+//!
+//! FX rates are ordinary assets of type `fx` whose symbol is the pair
+//! (`USDINR` = INR per 1 USD), so they come from the same providers, history
+//! and retention tiers as every other price. A conversion uses the direct
+//! pair if it exists, otherwise the inverse of the reverse pair.
+
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::price_history::{self, Candle};
+
+/// Upper-cased ISO 4217-style code, or `None` if `code` is not three letters.
+pub fn normalize_currency(code: &str) -> Option<String> {
+    let code = code.trim();
+    (code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()))
+        .then(|| code.to_ascii_uppercase())
+}
+
+/// Quote currency of an asset.
+pub async fn asset_currency(pool: &PgPool, asset_id: Uuid) -> sqlx::Result<Option<String>> {
+    sqlx::query_scalar("SELECT currency FROM assets WHERE id = $1")
+        .bind(asset_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// The FX asset quoting `from` in `to`, and whether its prices must be
+/// inverted to get `to` per 1 `from`.
+async fn pair(pool: &PgPool, from: &str, to: &str) -> sqlx::Result<Option<(Uuid, bool)>> {
+    for (symbol, inverted) in [(format!("{}{}", from, to), false), (format!("{}{}", to, from), true)] {
+        let id: Option<Uuid> = sqlx::query_scalar(
+            "SELECT id FROM assets WHERE symbol = $1 AND asset_type = 'fx' AND is_active = true",
+        )
+        .bind(&symbol)
+        .fetch_optional(pool)
+        .await?;
+        if let Some(id) = id {
+            return Ok(Some((id, inverted)));
+        }
+    }
+    Ok(None)
+}
+
+fn oriented(rate: Decimal, inverted: bool) -> Option<Decimal> {
+    match inverted {
+        false => Some(rate),
+        true if rate.is_zero() => None,
+        true => Some(Decimal::ONE / rate),
+    }
+}
+
+/// Units of `to` per 1 unit of `from` at `at`, or `None` when no pair or no
+/// price for it is known.
+pub async fn rate(
+    pool: &PgPool,
+    from: &str,
+    to: &str,
+    at: NaiveDateTime,
+) -> sqlx::Result<Option<Decimal>> {
+    if from == to {
+        return Ok(Some(Decimal::ONE));
+    }
+    let Some((pair_id, inverted)) = pair(pool, from, to).await? else {
+        return Ok(None);
+    };
+    Ok(price_history::close_at_or_before(pool, pair_id, at)
+        .await?
+        .and_then(|r| oriented(r, inverted)))
+}
+
+/// `amount` of `from` expressed in `to` at `at`.
+pub async fn convert(
+    pool: &PgPool,
+    amount: Decimal,
+    from: &str,
+    to: &str,
+    at: NaiveDateTime,
+) -> sqlx::Result<Option<Decimal>> {
+    Ok(rate(pool, from, to, at).await?.map(|r| (amount * r).round_dp(4)))
+}
+
+/// Re-express a candle series quoted in `from` in `to`, using the latest FX
+/// close at or before each candle. Returns `None` when no rate is available
+/// for the start of the series.
+pub async fn convert_candles(
+    pool: &PgPool,
+    candles: Vec<Candle>,
+    from: &str,
+    to: &str,
+) -> sqlx::Result<Option<Vec<Candle>>> {
+    let (Some(first), Some(last)) = (candles.first(), candles.last()) else {
+        return Ok(Some(candles));
+    };
+    if from == to {
+        return Ok(Some(candles));
+    }
+    let Some((pair_id, inverted)) = pair(pool, from, to).await? else {
+        return Ok(None);
+    };
+    let Some(mut current) = price_history::close_at_or_before(pool, pair_id, first.timestamp)
+        .await?
+        .and_then(|r| oriented(r, inverted))
+    else {
+        return Ok(None);
+    };
+    let rates = price_history::candles(pool, pair_id, first.timestamp, last.timestamp).await?;
+
+    let mut rates = rates.into_iter().peekable();
+    Ok(Some(
+        candles
+            .into_iter()
+            .map(|mut c| {
+                while let Some(r) = rates.next_if(|r| r.timestamp <= c.timestamp) {
+                    current = oriented(r.close, inverted).unwrap_or(current);
+                }
+                c.open = (c.open * current).round_dp(4);
+                c.high = (c.high * current).round_dp(4);
+                c.low = (c.low * current).round_dp(4);
+                c.close = (c.close * current).round_dp(4);
+                c
+            })
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_currency_accepts_three_letter_codes_only() {
+        assert_eq!(normalize_currency(" usd ").as_deref(), Some("USD"));
+        assert_eq!(normalize_currency("INR").as_deref(), Some("INR"));
+        assert_eq!(normalize_currency("US"), None);
+        assert_eq!(normalize_currency("US1"), None);
+        assert_eq!(normalize_currency("USDT"), None);
+    }
+
+    #[test]
+    fn inverse_pairs_are_reciprocal() {
+        let usdinr = Decimal::new(8300, 2);
+        let inrusd = oriented(usdinr, true).unwrap();
+        assert_eq!((inrusd * usdinr).round_dp(8), Decimal::ONE);
+        assert_eq!(oriented(usdinr, false), Some(usdinr));
+        assert_eq!(oriented(Decimal::ZERO, true), None);
+    }
+}
@@ -0,0 +1,393 @@
+//! Per-asset gap detection and repair for `market_prices`.
+//!
+//! For every active asset, compares the minute buckets stored over a lookback
+//! window with the buckets its exchange was actually open for (per the trading
+//! calendar), and asks the active market-data provider to fill whatever is
+//! missing — seed math, or Kite historical candles in live mode.
+//!
+//! Runs once during bootstrap and then periodically in the background. Every
+//! pass that finds a hole writes a row to `market_data_repairs`.
+
+use anyhow::Result;
+use futures_util::{stream, StreamExt};
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::services::market_data::{MarketDataProvider, SeedAsset};
+use crate::services::TradingCalendar;
+
+/// The newest minutes are still being written by the live/seed ticker, so the
+/// repair window stops this far behind "now".
+const SETTLE_MINUTES: i64 = 2;
+
+/// How many assets are scanned/filled concurrently (one connection each).
+const REPAIR_CONCURRENCY: usize = 4;
+
+/// What started a repair pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    Bootstrap,
+    Scheduled,
+    /// An asset was (re)activated through the admin API.
+    Activation,
+}
+
+impl Trigger {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Trigger::Bootstrap => "bootstrap",
+            Trigger::Scheduled => "scheduled",
+            Trigger::Activation => "activation",
+        }
+    }
+}
+
+/// A run of consecutive missing buckets. Closed sessions in between do not
+/// split a gap, so an overnight outage on NSE is reported as one range.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GapRange {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub minutes: i64,
+}
+
+/// Result of comparing stored buckets against the calendar.
+#[derive(Debug, Default)]
+pub struct GapScan {
+    /// Buckets the exchange was open for inside the window.
+    pub expected: i64,
+    pub gaps: Vec<GapRange>,
+}
+
+impl GapScan {
+    pub fn missing(&self) -> i64 {
+        self.gaps.iter().map(|g| g.minutes).sum()
+    }
+}
+
+/// Report of one repair pass over one asset.
+#[derive(Debug, Clone, Serialize)]
+pub struct RepairReport {
+    pub symbol: String,
+    pub trigger: &'static str,
+    pub source: &'static str,
+    pub window_start: NaiveDateTime,
+    pub window_end: NaiveDateTime,
+    pub expected_minutes: i64,
+    pub missing_minutes: i64,
+    pub gaps: Vec<GapRange>,
+    pub filled_rows: u64,
+    /// Buckets still missing after the fill (e.g. the provider had no data).
+    pub remaining_minutes: i64,
+    pub error: Option<String>,
+}
+
+/// Missing buckets of `asset` in `[start, end]`, given the stored timestamps.
+pub fn find_gaps(
+    calendar: &TradingCalendar,
+    asset: &SeedAsset,
+    existing: &HashSet<NaiveDateTime>,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+) -> GapScan {
+    let mut scan = GapScan::default();
+    let mut open: Option<GapRange> = None;
+    let mut ts = start;
+    while ts <= end {
+        if asset.is_trading(calendar, ts) {
+            scan.expected += 1;
+            if existing.contains(&ts) {
+                scan.gaps.extend(open.take());
+            } else {
+                let gap = open.get_or_insert(GapRange {
+                    start: ts,
+                    end: ts,
+                    minutes: 0,
+                });
+                gap.end = ts;
+                gap.minutes += 1;
+            }
+        }
+        ts += ChronoDuration::minutes(1);
+    }
+    scan.gaps.extend(open);
+    scan
+}
+
+pub struct GapRepairer {
+    pool: PgPool,
+    calendar: Arc<TradingCalendar>,
+    provider: Arc<dyn MarketDataProvider>,
+    lookback: ChronoDuration,
+    interval: Duration,
+}
+
+impl GapRepairer {
+    pub fn new(
+        pool: PgPool,
+        calendar: Arc<TradingCalendar>,
+        provider: Arc<dyn MarketDataProvider>,
+        lookback_hours: i64,
+        interval_minutes: u64,
+    ) -> Self {
+        Self {
+            pool,
+            calendar,
+            provider,
+            lookback: ChronoDuration::hours(lookback_hours),
+            interval: Duration::from_secs(interval_minutes * 60),
+        }
+    }
+
+    /// Background loop. The bootstrap pass already ran, so sleep first.
+    pub async fn run(self: Arc<Self>) {
+        info!("Gap repairer started (every {:?})", self.interval);
+        loop {
+            tokio::time::sleep(self.interval).await;
+            match SeedAsset::load_active(&self.pool).await {
+                Ok(assets) => {
+                    if let Err(e) = self.repair_all(&assets, Trigger::Scheduled).await {
+                        error!("Gap repair error: {:?}", e);
+                    }
+                }
+                Err(e) => warn!("gap repair: could not load assets: {:?}", e),
+            }
+        }
+    }
+
+    /// Scan and repair every asset over the lookback window. Returns one
+    /// report per asset that had missing buckets.
+    pub async fn repair_all(
+        &self,
+        assets: &[SeedAsset],
+        trigger: Trigger,
+    ) -> Result<Vec<RepairReport>> {
+        let (start, end) = self.window();
+
+        // Futures are built up front (not in a `Stream::map` closure) so the
+        // spawned `run` future stays provably `Send`.
+        let repairs: Vec<_> = assets
+            .iter()
+            .map(|asset| self.repair_asset(asset, trigger, start, end))
+            .collect();
+        let results = stream::iter(repairs)
+            .buffer_unordered(REPAIR_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+        let mut reports = Vec::new();
+        for result in results {
+            reports.extend(result?);
+        }
+        if reports.is_empty() {
+            info!("Gap repair ({}): no missing buckets", trigger.as_str());
+        }
+        Ok(reports)
+    }
+
+    /// Repair one asset over the lookback window, e.g. right after it was
+    /// activated.
+    pub async fn repair_one(
+        &self,
+        asset: &SeedAsset,
+        trigger: Trigger,
+    ) -> Result<Option<RepairReport>> {
+        let (start, end) = self.window();
+        self.repair_asset(asset, trigger, start, end).await
+    }
+
+    /// The `[start, end]` span each pass scans.
+    pub fn window(&self) -> (NaiveDateTime, NaiveDateTime) {
+        let now = Utc::now().timestamp().div_euclid(60);
+        let end = minute_to_ts(now - SETTLE_MINUTES);
+        (end - self.lookback, end)
+    }
+
+    async fn repair_asset(
+        &self,
+        asset: &SeedAsset,
+        trigger: Trigger,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<Option<RepairReport>> {
+        let scan = find_gaps(
+            &self.calendar,
+            asset,
+            &self.stored_buckets(asset, start, end).await?,
+            start,
+            end,
+        );
+        let (Some(first), Some(last)) = (scan.gaps.first(), scan.gaps.last()) else {
+            return Ok(None);
+        };
+
+        // One fill over the whole span: existing buckets are left untouched.
+        let (source, filled_rows, error) =
+            match self.provider.fill_range(asset, first.start, last.end).await {
+                Ok(fill) => (fill.source, fill.rows, None),
+                Err(e) => ("none", 0, Some(e.to_string())),
+            };
+        let remaining = find_gaps(
+            &self.calendar,
+            asset,
+            &self.stored_buckets(asset, first.start, last.end).await?,
+            first.start,
+            last.end,
+        )
+        .missing();
+
+        let report = RepairReport {
+            symbol: asset.symbol.clone(),
+            trigger: trigger.as_str(),
+            source,
+            window_start: start,
+            window_end: end,
+            expected_minutes: scan.expected,
+            missing_minutes: scan.missing(),
+            filled_rows,
+            remaining_minutes: remaining,
+            gaps: scan.gaps,
+            error,
+        };
+        self.record(asset, &report).await?;
+
+        if report.remaining_minutes > 0 || report.error.is_some() {
+            warn!(
+                "Gap repair: {} had {} missing minute(s) in {} gap(s); filled {} from {}, {} still missing{}",
+                report.symbol,
+                report.missing_minutes,
+                report.gaps.len(),
+                report.filled_rows,
+                report.source,
+                report.remaining_minutes,
+                report.error.as_deref().map(|e| format!(" ({})", e)).unwrap_or_default()
+            );
+        } else {
+            info!(
+                "Gap repair: {} had {} missing minute(s) in {} gap(s); filled {} from {}",
+                report.symbol,
+                report.missing_minutes,
+                report.gaps.len(),
+                report.filled_rows,
+                report.source
+            );
+        }
+        Ok(Some(report))
+    }
+
+    async fn stored_buckets(
+        &self,
+        asset: &SeedAsset,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<HashSet<NaiveDateTime>> {
+        let rows: Vec<NaiveDateTime> = sqlx::query_scalar(
+            "SELECT timestamp FROM market_prices
+             WHERE asset_id = $1 AND timestamp >= $2 AND timestamp <= $3",
+        )
+        .bind(asset.id)
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().collect())
+    }
+
+    async fn record(&self, asset: &SeedAsset, report: &RepairReport) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO market_data_repairs
+                (asset_id, trigger, source, window_start, window_end, expected_minutes,
+                 missing_minutes, gap_count, filled_rows, remaining_minutes, gaps, error)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            "#,
+        )
+        .bind(asset.id)
+        .bind(report.trigger)
+        .bind(report.source)
+        .bind(report.window_start)
+        .bind(report.window_end)
+        .bind(report.expected_minutes as i32)
+        .bind(report.missing_minutes as i32)
+        .bind(report.gaps.len() as i32)
+        .bind(report.filled_rows as i32)
+        .bind(report.remaining_minutes as i32)
+        .bind(sqlx::types::Json(&report.gaps))
+        .bind(report.error.as_deref())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+fn minute_to_ts(unix_minute: i64) -> NaiveDateTime {
+    DateTime::<Utc>::from_timestamp(unix_minute * 60, 0)
+        .expect("valid timestamp")
+        .naive_utc()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::price_model::PriceModel;
+    use uuid::Uuid;
+
+    fn utc(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn asset(exchange: &str) -> SeedAsset {
+        SeedAsset {
+            id: Uuid::nil(),
+            symbol: "TEST".to_string(),
+            exchange: Some(exchange.to_string()),
+            model: PriceModel::fallback(),
+        }
+    }
+
+    #[test]
+    fn finds_runs_of_missing_minutes() {
+        let cal = TradingCalendar::default();
+        let start = utc("2026-01-24 00:00");
+        let end = utc("2026-01-24 00:09");
+        let existing: HashSet<_> = [0, 1, 4, 5, 6, 9]
+            .iter()
+            .map(|m| start + ChronoDuration::minutes(*m))
+            .collect();
+
+        let scan = find_gaps(&cal, &asset("BINANCE"), &existing, start, end);
+        assert_eq!(scan.expected, 10);
+        assert_eq!(
+            scan.gaps,
+            vec![
+                GapRange { start: utc("2026-01-24 00:02"), end: utc("2026-01-24 00:03"), minutes: 2 },
+                GapRange { start: utc("2026-01-24 00:07"), end: utc("2026-01-24 00:08"), minutes: 2 },
+            ]
+        );
+        assert_eq!(scan.missing(), 4);
+    }
+
+    #[test]
+    fn closed_minutes_are_not_gaps_and_do_not_split_them() {
+        let cal = TradingCalendar::default();
+        // Tuesday 09:55 UTC → Wednesday 03:50 UTC, NSE closes 10:00, opens 03:45.
+        let start = utc("2026-01-27 09:55");
+        let end = utc("2026-01-28 03:50");
+        let existing: HashSet<_> = [utc("2026-01-27 09:55"), utc("2026-01-28 03:50")].into();
+
+        let scan = find_gaps(&cal, &asset("NSE"), &existing, start, end);
+        assert_eq!(scan.expected, 5 + 6);
+        assert_eq!(
+            scan.gaps,
+            vec![GapRange {
+                start: utc("2026-01-27 09:56"),
+                end: utc("2026-01-28 03:49"),
+                minutes: 4 + 5,
+            }]
+        );
+    }
+}
@@ -0,0 +1,596 @@
+//! Technical indicators over stored candles.
+//!
+//! The engine is pure: it takes resampled bars and returns one value per bar,
+//! `None` while an indicator is still warming up. [`compute_for_range`] wires
+//! it to `price_history`: it fetches enough bars *before* the requested range
+//! for every indicator to be warm at the first bar, then trims them off, so
+//! the first value a client sees is the same as in a longer request.
+//!
+//! Spec syntax (comma-separated): `sma:20`, `ema:20`, `rsi:14`,
+//! `macd:12:26:9`, `bb:20:2`, `vwap`, `atr:14`. Omitted parameters take
+//! these defaults.
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::services::price_history::{self, Candle};
+
+/// Most bars a single request may return (after warm-up is trimmed).
+pub const MAX_BARS: i64 = 5_000;
+
+/// EMA-based indicators are seeded with an SMA and then need a few periods
+/// to forget it; this many periods of history are fetched per EMA period.
+const EMA_WARMUP_FACTOR: usize = 3;
+
+/// How many times the warm-up lookback is doubled to get past closed
+/// sessions before giving up (the first bars then stay `None`).
+const WARMUP_ATTEMPTS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interval {
+    minutes: i64,
+}
+
+impl Interval {
+    pub fn parse(s: &str) -> Option<Self> {
+        let minutes = match s {
+            "1m" => 1,
+            "5m" => 5,
+            "15m" => 15,
+            "30m" => 30,
+            "1h" => 60,
+            "4h" => 240,
+            "1d" => 1_440,
+            _ => return None,
+        };
+        Some(Self { minutes })
+    }
+
+    pub fn minutes(self) -> i64 {
+        self.minutes
+    }
+
+    pub fn label(self) -> &'static str {
+        match self.minutes {
+            1 => "1m",
+            5 => "5m",
+            15 => "15m",
+            30 => "30m",
+            60 => "1h",
+            240 => "4h",
+            _ => "1d",
+        }
+    }
+
+    fn bucket(self, ts: NaiveDateTime) -> NaiveDateTime {
+        let minute = ts.and_utc().timestamp().div_euclid(60);
+        let start = minute - minute.rem_euclid(self.minutes);
+        DateTime::<Utc>::from_timestamp(start * 60, 0)
+            .expect("valid timestamp")
+            .naive_utc()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IndicatorSpec {
+    Sma(usize),
+    Ema(usize),
+    Rsi(usize),
+    Macd { fast: usize, slow: usize, signal: usize },
+    Bollinger { period: usize, k: f64 },
+    Vwap,
+    Atr(usize),
+}
+
+impl IndicatorSpec {
+    /// Parse a comma-separated list such as `sma:20,rsi,macd:12:26:9`.
+    pub fn parse_list(s: &str) -> std::result::Result<Vec<Self>, String> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(Self::parse)
+            .collect()
+    }
+
+    fn parse(spec: &str) -> std::result::Result<Self, String> {
+        let mut parts = spec.split(':');
+        let name = parts.next().unwrap_or("").to_ascii_lowercase();
+        let args: Vec<&str> = parts.collect();
+        let period = |i: usize, default: usize| -> std::result::Result<usize, String> {
+            match args.get(i) {
+                None => Ok(default),
+                Some(a) => match a.parse::<usize>() {
+                    Ok(n) if (1..=500).contains(&n) => Ok(n),
+                    _ => Err(format!("'{}': period must be 1-500", spec)),
+                },
+            }
+        };
+        let parsed = match name.as_str() {
+            "sma" => Self::Sma(period(0, 20)?),
+            "ema" => Self::Ema(period(0, 20)?),
+            "rsi" => Self::Rsi(period(0, 14)?),
+            "macd" => {
+                let (fast, slow, signal) = (period(0, 12)?, period(1, 26)?, period(2, 9)?);
+                if fast >= slow {
+                    return Err(format!("'{}': fast period must be below slow", spec));
+                }
+                Self::Macd { fast, slow, signal }
+            }
+            "bb" | "bollinger" => {
+                let k = match args.get(1) {
+                    None => 2.0,
+                    Some(a) => a
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|k| *k > 0.0 && *k <= 10.0)
+                        .ok_or_else(|| format!("'{}': width must be in (0, 10]", spec))?,
+                };
+                Self::Bollinger { period: period(0, 20)?, k }
+            }
+            "vwap" => Self::Vwap,
+            "atr" => Self::Atr(period(0, 14)?),
+            _ => return Err(format!("Unknown indicator '{}'", spec)),
+        };
+        if matches!(parsed, Self::Vwap) && !args.is_empty() {
+            return Err("vwap takes no parameters".to_string());
+        }
+        Ok(parsed)
+    }
+
+    /// Bars needed before the first value is meaningful.
+    pub fn warmup(&self) -> usize {
+        match *self {
+            Self::Sma(n) | Self::Bollinger { period: n, .. } => n - 1,
+            Self::Ema(n) => EMA_WARMUP_FACTOR * n,
+            Self::Rsi(n) | Self::Atr(n) => EMA_WARMUP_FACTOR * n + 1,
+            Self::Macd { slow, signal, .. } => EMA_WARMUP_FACTOR * slow + signal,
+            // VWAP resets every UTC day; no earlier bars are needed.
+            Self::Vwap => 0,
+        }
+    }
+
+    /// Output lines, keyed by a stable name such as `macd_12_26_9_signal`.
+    pub fn compute(&self, bars: &[Bar]) -> Vec<(String, Vec<Option<f64>>)> {
+        let closes: Vec<f64> = bars.iter().map(|b| b.close).collect();
+        match *self {
+            Self::Sma(n) => vec![(format!("sma_{}", n), sma(&closes, n))],
+            Self::Ema(n) => vec![(format!("ema_{}", n), ema(&closes, n))],
+            Self::Rsi(n) => vec![(format!("rsi_{}", n), rsi(&closes, n))],
+            Self::Macd { fast, slow, signal } => {
+                let (line, sig, hist) = macd(&closes, fast, slow, signal);
+                let name = format!("macd_{}_{}_{}", fast, slow, signal);
+                vec![
+                    (name.clone(), line),
+                    (format!("{}_signal", name), sig),
+                    (format!("{}_hist", name), hist),
+                ]
+            }
+            Self::Bollinger { period, k } => {
+                let (upper, middle, lower) = bollinger(&closes, period, k);
+                let name = format!("bb_{}_{}", period, k);
+                vec![
+                    (format!("{}_upper", name), upper),
+                    (format!("{}_middle", name), middle),
+                    (format!("{}_lower", name), lower),
+                ]
+            }
+            Self::Vwap => vec![("vwap".to_string(), vwap(bars))],
+            Self::Atr(n) => vec![(format!("atr_{}", n), atr(bars, n))],
+        }
+    }
+}
+
+/// One resampled OHLCV bar, as floats for the indicator math.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bar {
+    pub timestamp: NaiveDateTime,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+// ──────────────────────────────────────────────────────────────────────────────
+// Indicator math. Every function returns one value per input bar.
+// ──────────────────────────────────────────────────────────────────────────────
+
+pub fn sma(values: &[f64], n: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; values.len()];
+    let mut sum = 0.0;
+    for i in 0..values.len() {
+        sum += values[i];
+        if i >= n {
+            sum -= values[i - n];
+        }
+        if i + 1 >= n {
+            out[i] = Some(sum / n as f64);
+        }
+    }
+    out
+}
+
+/// Exponential moving average, seeded with the SMA of the first `n` values.
+pub fn ema(values: &[f64], n: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; values.len()];
+    if values.len() < n {
+        return out;
+    }
+    let alpha = 2.0 / (n as f64 + 1.0);
+    let mut prev = values[..n].iter().sum::<f64>() / n as f64;
+    out[n - 1] = Some(prev);
+    for i in n..values.len() {
+        prev += alpha * (values[i] - prev);
+        out[i] = Some(prev);
+    }
+    out
+}
+
+/// EMA over a series that itself starts with `None`s (e.g. a MACD line).
+fn ema_of(values: &[Option<f64>], n: usize) -> Vec<Option<f64>> {
+    let start = values.iter().position(Option::is_some).unwrap_or(values.len());
+    let defined: Vec<f64> = values[start..].iter().map(|v| v.unwrap_or(0.0)).collect();
+    let mut out = vec![None; start];
+    out.extend(ema(&defined, n));
+    out
+}
+
+/// Wilder's smoothing (alpha = 1/n), seeded with the plain mean.
+fn wilder(values: &[f64], n: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; values.len()];
+    if values.len() < n {
+        return out;
+    }
+    let mut prev = values[..n].iter().sum::<f64>() / n as f64;
+    out[n - 1] = Some(prev);
+    for i in n..values.len() {
+        prev = (prev * (n as f64 - 1.0) + values[i]) / n as f64;
+        out[i] = Some(prev);
+    }
+    out
+}
+
+/// Relative Strength Index (Wilder), 0–100.
+pub fn rsi(closes: &[f64], n: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; closes.len()];
+    if closes.len() < 2 {
+        return out;
+    }
+    let changes: Vec<f64> = closes.windows(2).map(|w| w[1] - w[0]).collect();
+    let gains = wilder(&changes.iter().map(|c| c.max(0.0)).collect::<Vec<_>>(), n);
+    let losses = wilder(&changes.iter().map(|c| (-c).max(0.0)).collect::<Vec<_>>(), n);
+    for i in 0..changes.len() {
+        if let (Some(g), Some(l)) = (gains[i], losses[i]) {
+            out[i + 1] = Some(if l == 0.0 {
+                if g == 0.0 { 50.0 } else { 100.0 }
+            } else {
+                100.0 - 100.0 / (1.0 + g / l)
+            });
+        }
+    }
+    out
+}
+
+/// MACD line, signal line and histogram.
+#[allow(clippy::type_complexity)]
+pub fn macd(
+    closes: &[f64],
+    fast: usize,
+    slow: usize,
+    signal: usize,
+) -> (Vec<Option<f64>>, Vec<Option<f64>>, Vec<Option<f64>>) {
+    let (fast, slow) = (ema(closes, fast), ema(closes, slow));
+    let line: Vec<Option<f64>> = fast
+        .iter()
+        .zip(&slow)
+        .map(|(f, s)| Some((*f)? - (*s)?))
+        .collect();
+    let sig = ema_of(&line, signal);
+    let hist = line.iter().zip(&sig).map(|(l, s)| Some((*l)? - (*s)?)).collect();
+    (line, sig, hist)
+}
+
+/// Bollinger Bands: SMA ± k population standard deviations.
+#[allow(clippy::type_complexity)]
+pub fn bollinger(
+    closes: &[f64],
+    n: usize,
+    k: f64,
+) -> (Vec<Option<f64>>, Vec<Option<f64>>, Vec<Option<f64>>) {
+    let middle = sma(closes, n);
+    let mut upper = vec![None; closes.len()];
+    let mut lower = vec![None; closes.len()];
+    for i in 0..closes.len() {
+        if let Some(mean) = middle[i] {
+            let window = &closes[i + 1 - n..=i];
+            let sd = (window.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / n as f64).sqrt();
+            upper[i] = Some(mean + k * sd);
+            lower[i] = Some(mean - k * sd);
+        }
+    }
+    (upper, middle, lower)
+}
+
+/// Volume-weighted average of the typical price, reset every UTC day.
+pub fn vwap(bars: &[Bar]) -> Vec<Option<f64>> {
+    let mut out = Vec::with_capacity(bars.len());
+    let (mut day, mut pv, mut vol) = (None, 0.0, 0.0);
+    for b in bars {
+        if day != Some(b.timestamp.date()) {
+            day = Some(b.timestamp.date());
+            pv = 0.0;
+            vol = 0.0;
+        }
+        pv += (b.high + b.low + b.close) / 3.0 * b.volume;
+        vol += b.volume;
+        out.push((vol > 0.0).then(|| pv / vol));
+    }
+    out
+}
+
+/// Average True Range (Wilder).
+pub fn atr(bars: &[Bar], n: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; bars.len()];
+    if bars.len() < 2 {
+        return out;
+    }
+    let true_ranges: Vec<f64> = bars
+        .windows(2)
+        .map(|w| {
+            let (prev, b) = (&w[0], &w[1]);
+            (b.high - b.low)
+                .max((b.high - prev.close).abs())
+                .max((b.low - prev.close).abs())
+        })
+        .collect();
+    for (i, v) in wilder(&true_ranges, n).into_iter().enumerate() {
+        out[i + 1] = v;
+    }
+    out
+}
+
+// ──────────────────────────────────────────────────────────────────────────────
+// Resampling + DB wiring
+// ──────────────────────────────────────────────────────────────────────────────
+
+/// Aggregate ascending candles into `interval` buckets. Rollup candles
+/// coarser than `interval` can't be split, so they pass through labelled with
+/// their stored resolution.
+pub fn resample(candles: &[Candle], interval: Interval) -> Vec<Candle> {
+    let mut out: Vec<Candle> = Vec::new();
+    for c in candles {
+        let bucket = interval.bucket(c.timestamp);
+        let resolution = coarser(interval.label(), &c.resolution);
+        match out.last_mut() {
+            Some(last) if last.timestamp == bucket => {
+                last.resolution = coarser(&last.resolution, resolution).to_string();
+                last.high = last.high.max(c.high);
+                last.low = last.low.min(c.low);
+                last.close = c.close;
+                last.volume = match (last.volume, c.volume) {
+                    (Some(a), Some(b)) => Some(a + b),
+                    (a, b) => a.or(b),
+                };
+            }
+            _ => out.push(Candle {
+                timestamp: bucket,
+                resolution: resolution.to_string(),
+                ..c.clone()
+            }),
+        }
+    }
+    out
+}
+
+/// The longer of two resolution labels.
+fn coarser<'a>(a: &'a str, b: &'a str) -> &'a str {
+    let minutes = |label| Interval::parse(label).map_or(0, Interval::minutes);
+    if minutes(b) > minutes(a) { b } else { a }
+}
+
+fn to_bar(c: &Candle) -> Bar {
+    let f = |d: Decimal| d.to_f64().unwrap_or(0.0);
+    Bar {
+        timestamp: c.timestamp,
+        open: f(c.open),
+        high: f(c.high),
+        low: f(c.low),
+        close: f(c.close),
+        volume: c.volume.map(f).unwrap_or(0.0),
+    }
+}
+
+/// Candles in `[from, to]` at `interval`, with indicator lines aligned to them.
+pub struct IndicatorSeries {
+    pub candles: Vec<Candle>,
+    pub lines: BTreeMap<String, Vec<Option<f64>>>,
+}
+
+pub async fn compute_for_range(
+    pool: &PgPool,
+    asset_id: Uuid,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    interval: Interval,
+    specs: &[IndicatorSpec],
+) -> Result<IndicatorSeries> {
+    let warmup = specs.iter().map(IndicatorSpec::warmup).max().unwrap_or(0);
+    let step = Duration::minutes(interval.minutes());
+
+    // Widen the lookback until enough bars precede `from` (closed sessions
+    // and weekends contain no bars).
+    let mut lookback = step * warmup as i32;
+    let mut bars = resample(&price_history::candles(pool, asset_id, from - lookback, to).await?, interval);
+    let mut attempts = 1;
+    while warmup > 0
+        && attempts < WARMUP_ATTEMPTS
+        && bars.iter().take_while(|c| c.timestamp < from).count() < warmup
+    {
+        lookback = lookback * 2 + Duration::days(3);
+        bars = resample(&price_history::candles(pool, asset_id, from - lookback, to).await?, interval);
+        attempts += 1;
+    }
+
+    let skip = bars
+        .iter()
+        .position(|c| c.timestamp >= interval.bucket(from))
+        .unwrap_or(bars.len());
+    let floats: Vec<Bar> = bars.iter().map(to_bar).collect();
+    let lines = specs
+        .iter()
+        .flat_map(|s| s.compute(&floats))
+        .map(|(name, values)| (name, values[skip..].to_vec()))
+        .collect();
+    bars.drain(..skip);
+
+    Ok(IndicatorSeries { candles: bars, lines })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close_eq(a: Option<f64>, b: f64) -> bool {
+        a.is_some_and(|a| (a - b).abs() < 1e-6)
+    }
+
+    fn bar(minute: i64, high: f64, low: f64, close: f64, volume: f64) -> Bar {
+        Bar {
+            timestamp: DateTime::<Utc>::from_timestamp(minute * 60, 0).unwrap().naive_utc(),
+            open: close,
+            high,
+            low,
+            close,
+            volume,
+        }
+    }
+
+    #[test]
+    fn sma_and_ema_warm_up() {
+        let v = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let s = sma(&v, 3);
+        assert_eq!(&s[..2], &[None, None]);
+        assert!(close_eq(s[2], 2.0) && close_eq(s[4], 4.0));
+
+        let e = ema(&v, 3);
+        assert_eq!(&e[..2], &[None, None]);
+        assert!(close_eq(e[2], 2.0)); // SMA seed
+        assert!(close_eq(e[3], 3.0)); // 2 + 0.5 * (4 - 2)
+        assert!(close_eq(e[4], 4.0));
+    }
+
+    #[test]
+    fn rsi_is_bounded_and_saturates() {
+        let up: Vec<f64> = (0..30).map(|i| 100.0 + i as f64).collect();
+        let r = rsi(&up, 14);
+        assert!(r[..14].iter().all(Option::is_none));
+        assert!(close_eq(r[14], 100.0));
+
+        let zigzag: Vec<f64> = (0..60).map(|i| if i % 2 == 0 { 100.0 } else { 101.0 }).collect();
+        let r = rsi(&zigzag, 14);
+        assert!(r.iter().flatten().all(|v| (0.0..=100.0).contains(v)));
+        assert!((r[59].unwrap() - 50.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn macd_signal_starts_after_slow_plus_signal() {
+        let v: Vec<f64> = (0..60).map(|i| (i as f64 / 5.0).sin() * 10.0 + 100.0).collect();
+        let (line, sig, hist) = macd(&v, 12, 26, 9);
+        assert_eq!(line.iter().position(Option::is_some), Some(25));
+        assert_eq!(sig.iter().position(Option::is_some), Some(25 + 8));
+        assert!(close_eq(hist[40], line[40].unwrap() - sig[40].unwrap()));
+    }
+
+    #[test]
+    fn bollinger_of_flat_series_collapses() {
+        let (u, m, l) = bollinger(&[5.0; 10], 4, 2.0);
+        assert!(close_eq(u[3], 5.0) && close_eq(m[3], 5.0) && close_eq(l[3], 5.0));
+        assert_eq!(u[2], None);
+    }
+
+    #[test]
+    fn vwap_resets_each_day_and_atr_uses_true_range() {
+        let bars = [
+            bar(0, 11.0, 9.0, 10.0, 1.0),
+            bar(1, 21.0, 19.0, 20.0, 3.0),
+            bar(1_440, 31.0, 29.0, 30.0, 1.0),
+        ];
+        let v = vwap(&bars);
+        assert!(close_eq(v[1], (10.0 + 3.0 * 20.0) / 4.0));
+        assert!(close_eq(v[2], 30.0));
+
+        let a = atr(&bars, 2);
+        assert_eq!(a[..2], [None, None]);
+        // True ranges: max(2, |21-10|, |19-10|) = 11, then max(2, 11, 9) = 11.
+        assert!(close_eq(a[2], 11.0));
+    }
+
+    #[test]
+    fn parses_specs_with_defaults_and_rejects_garbage() {
+        assert_eq!(
+            IndicatorSpec::parse_list("sma:50, rsi ,macd,bb:20:2.5,vwap").unwrap(),
+            vec![
+                IndicatorSpec::Sma(50),
+                IndicatorSpec::Rsi(14),
+                IndicatorSpec::Macd { fast: 12, slow: 26, signal: 9 },
+                IndicatorSpec::Bollinger { period: 20, k: 2.5 },
+                IndicatorSpec::Vwap,
+            ]
+        );
+        assert!(IndicatorSpec::parse_list("sma:0").is_err());
+        assert!(IndicatorSpec::parse_list("macd:26:12").is_err());
+        assert!(IndicatorSpec::parse_list("foo").is_err());
+        assert!(IndicatorSpec::parse_list("vwap:3").is_err());
+    }
+
+    #[test]
+    fn resample_buckets_by_interval() {
+        let candle = |minute: i64, close: i64| Candle {
+            timestamp: DateTime::<Utc>::from_timestamp(minute * 60, 0).unwrap().naive_utc(),
+            open: Decimal::from(close),
+            high: Decimal::from(close + 1),
+            low: Decimal::from(close - 1),
+            close: Decimal::from(close),
+            volume: Some(Decimal::ONE),
+            resolution: "1m".to_string(),
+        };
+        let out = resample(&[candle(0, 10), candle(3, 12), candle(5, 8)], Interval::parse("5m").unwrap());
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].open, Decimal::from(10));
+        assert_eq!(out[0].high, Decimal::from(13));
+        assert_eq!(out[0].close, Decimal::from(12));
+        assert_eq!(out[0].volume, Some(Decimal::from(2)));
+        assert_eq!(out[0].resolution, "5m");
+        assert_eq!(out[1].close, Decimal::from(8));
+    }
+
+    #[test]
+    fn resample_keeps_the_resolution_of_rollups() {
+        let candle = |minute: i64, resolution: &str| Candle {
+            timestamp: DateTime::<Utc>::from_timestamp(minute * 60, 0).unwrap().naive_utc(),
+            open: Decimal::ONE,
+            high: Decimal::ONE,
+            low: Decimal::ONE,
+            close: Decimal::ONE,
+            volume: None,
+            resolution: resolution.to_string(),
+        };
+        let bars = [candle(0, "1h"), candle(60, "1h"), candle(120, "1m"), candle(121, "1m")];
+        let out = resample(&bars, Interval::parse("1m").unwrap());
+        let labels: Vec<_> = out.iter().map(|c| c.resolution.as_str()).collect();
+        assert_eq!(labels, ["1h", "1h", "1m", "1m"]);
+
+        // A 4h bucket is coarser than the hourly bars it merges
+        let out = resample(&bars, Interval::parse("4h").unwrap());
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].resolution, "4h");
+
+        let out = resample(&[candle(0, "1d")], Interval::parse("4h").unwrap());
+        assert_eq!(out[0].resolution, "1d");
+    }
+}
@@ -1,28 +1,102 @@
-//! Dual-mode market data layer.
+//! Multi-mode market data layer.
 //!
-//! Two providers share one trait. The app always works in `Seed` mode; `Live`
-//! is a strictly-optional plug-in that requires a valid Zerodha Kite token.
+//! Providers share one trait. The app always works in `Seed` mode; `Live`
+//! is a strictly-optional plug-in that requires a valid Zerodha Kite token,
+//! `File` (see `file_market_data`) imports history from CSV/Parquet, and
+//! `Demo` (see `demo_market_data`) replays a historical window at speed.
+//! In seed and live mode, crypto assets can additionally be routed to an
+//! exchange WebSocket feed (see `crypto_market_data`).
 //! If Live fails to start (missing tokens, handshake error, invalid token)
 //! the runtime logs a warning and **falls back to Seed** so the MVP is never
 //! blocked by an external service.
 
 use anyhow::Result;
 use async_trait::async_trait;
-use chrono::{DateTime, Duration as ChronoDuration, Timelike, Utc};
+use futures_util::{stream, StreamExt};
+use chrono::{DateTime, NaiveDateTime, SubsecRound, Timelike, Utc};
 use rust_decimal::Decimal;
 use sqlx::PgPool;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use serde::Serialize;
+use std::future::Future;
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::AbortHandle;
 use tracing::{info, warn};
 use uuid::Uuid;
 
-use crate::config::{AppConfig, MarketDataMode};
+use crate::config::{AppConfig, KiteConfig, MarketDataMode};
+use crate::services::crypto_market_data::CryptoMarketDataProvider;
+use crate::services::demo_market_data::DemoMarketDataProvider;
+use crate::services::file_market_data::FileMarketDataProvider;
+use crate::services::price_model::{candle, day_candles, DayCurve, PriceModel, MINUTES_PER_DAY};
+use crate::services::tick_quality::QualityRules;
+use crate::services::TradingCalendar;
 
-/// Asset row used by the seed generator.
-#[derive(sqlx::FromRow, Clone, Debug)]
+/// Asset plus the price-model parameters used by the seed generator.
+#[derive(Clone, Debug)]
 pub struct SeedAsset {
     pub id: Uuid,
     pub symbol: String,
+    pub exchange: Option<String>,
+    pub model: PriceModel,
+}
+
+impl SeedAsset {
+    /// Every active asset, joined with its `asset_price_models` row.
+    /// Assets without a row fall back to [`PriceModel::fallback`].
+    pub async fn load_active(pool: &PgPool) -> Result<Vec<SeedAsset>> {
+        #[derive(sqlx::FromRow)]
+        struct AssetRow {
+            id: Uuid,
+            symbol: String,
+            exchange: Option<String>,
+        }
+
+        #[derive(sqlx::FromRow)]
+        struct ModelRow {
+            asset_id: Uuid,
+            #[sqlx(flatten)]
+            model: PriceModel,
+        }
+
+        let assets = sqlx::query_as::<_, AssetRow>(
+            "SELECT id, symbol, exchange FROM assets WHERE is_active = true",
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut models: HashMap<Uuid, PriceModel> = sqlx::query_as::<_, ModelRow>(
+            r#"
+            SELECT asset_id, base_price, drift, volatility, regime_multiplier,
+                   regime_probability, jumps_per_day, jump_mean, jump_stddev,
+                   factor, factor_correlation
+            FROM asset_price_models
+            "#,
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|r| (r.asset_id, r.model))
+        .collect();
+
+        Ok(assets
+            .into_iter()
+            .map(|a| SeedAsset {
+                model: models.remove(&a.id).unwrap_or_else(PriceModel::fallback),
+                id: a.id,
+                symbol: a.symbol,
+                exchange: a.exchange,
+            })
+            .collect())
+    }
+
+    /// Whether the asset's exchange is in session at `at` (UTC).
+    /// Assets without an exchange are treated as 24/7.
+    pub fn is_trading(&self, calendar: &TradingCalendar, at: NaiveDateTime) -> bool {
+        calendar.is_open(self.exchange.as_deref().unwrap_or(""), at)
+    }
 }
 
 /// Common interface every market-data provider must implement.
@@ -34,88 +108,129 @@ pub trait MarketDataProvider: Send + Sync {
     /// Spawn any long-running background work (tick generation, websocket stream, ...).
     /// Must return fast. Implementations own whatever tasks they spawn.
     async fn start(self: Arc<Self>) -> Result<()>;
-}
 
-// ──────────────────────────────────────────────────────────────────────────────
-// Deterministic price model
-// ──────────────────────────────────────────────────────────────────────────────
+    /// Abort everything `start` spawned, so another provider can take over.
+    fn stop(&self) {}
 
-/// FNV-1a 64-bit hash — stable, deterministic, no dependencies.
-fn hash64(input: &str) -> u64 {
-    let mut h: u64 = 0xcbf29ce484222325;
-    for b in input.as_bytes() {
-        h ^= *b as u64;
-        h = h.wrapping_mul(0x100000001b3);
+    /// Connection state of the provider's live streams, for the admin API.
+    fn streams(&self) -> Vec<StreamReport> {
+        Vec::new()
     }
-    h
+
+    /// Write 1-minute candles for `asset` over `[start, end]` (UTC) without
+    /// touching buckets that already exist. Used by gap repair.
+    async fn fill_range(
+        &self,
+        asset: &SeedAsset,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<RangeFill>;
+
+    /// The asset catalogue changed (asset activated, deactivated or its
+    /// exchange, type or instrument token edited). Providers that subscribe
+    /// to a fixed instrument list re-read it; the seed provider reloads every
+    /// tick anyway.
+    fn refresh_subscriptions(&self) {}
 }
 
-/// Mix two u64s into a uniform [0, 1) float, purely deterministic.
-fn rand01(seed: u64, nonce: u64) -> f64 {
-    let mut x = seed ^ nonce.wrapping_mul(0x9E3779B97F4A7C15);
-    x ^= x >> 33;
-    x = x.wrapping_mul(0xff51afd7ed558ccd);
-    x ^= x >> 33;
-    x = x.wrapping_mul(0xc4ceb9fe1a85ec53);
-    x ^= x >> 33;
-    ((x >> 11) as f64) / ((1u64 << 53) as f64)
+/// Outcome of [`MarketDataProvider::fill_range`].
+#[derive(Debug, Clone, Copy)]
+pub struct RangeFill {
+    pub rows: u64,
+    /// Where the candles came from (`"seed"`, `"kite"`, ...).
+    pub source: &'static str,
 }
 
-/// Canonical base price per symbol (INR for Indian assets, USD for crypto).
-/// Chosen to roughly reflect market levels so the demo feels realistic.
-fn base_price(symbol: &str) -> f64 {
-    match symbol {
-        "BTC" => 65_000.0,
-        "ETH" => 3_500.0,
-        "SOL" => 150.0,
-        "NIFTY50" => 22_000.0,
-        "BANKNIFTY" => 48_000.0,
-        "INFY" => 1_500.0,
-        "TCS" => 3_800.0,
-        "RELIANCE" => 2_800.0,
-        "NIFTYBEES" => 250.0,
-        "BANKBEES" => 500.0,
-        _ => 1_000.0,
+/// Background tasks spawned by a provider, aborted together on `stop`.
+#[derive(Default)]
+pub struct ProviderTasks(Mutex<Vec<AbortHandle>>);
+
+impl ProviderTasks {
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let handle = tokio::spawn(task).abort_handle();
+        let mut tasks = self.0.lock().expect("provider tasks lock poisoned");
+        tasks.retain(|h| !h.is_finished());
+        tasks.push(handle);
     }
-}
 
-/// Deterministic, continuous price function.
-/// Given `(symbol, unix_minute)` it always returns the same value.
-/// Shape = long wave (day) + medium wave (hour) + small deterministic noise.
-pub fn price_at(symbol: &str, unix_minute: i64) -> f64 {
-    use std::f64::consts::TAU;
-    let base = base_price(symbol);
-    let sh = hash64(symbol);
-    let phase = ((sh % 1000) as f64 / 1000.0) * TAU;
+    pub fn abort_all(&self) {
+        for handle in self.0.lock().expect("provider tasks lock poisoned").drain(..) {
+            handle.abort();
+        }
+    }
+}
 
-    let day = (unix_minute as f64 / (24.0 * 60.0) * TAU + phase).sin() * 0.04;
-    let hour = (unix_minute as f64 / 60.0 * TAU + phase * 2.0).sin() * 0.015;
-    let noise = (rand01(sh, unix_minute as u64) - 0.5) * 0.008;
+/// Last known state of a live stream.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StreamStatus {
+    pub connected: bool,
+    pub connected_at: Option<NaiveDateTime>,
+    pub last_message_at: Option<NaiveDateTime>,
+    /// Messages received since the provider started.
+    pub messages: u64,
+    pub last_error: Option<String>,
+}
 
-    base * (1.0 + day + hour + noise)
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamReport {
+    pub name: &'static str,
+    #[serde(flatten)]
+    pub status: StreamStatus,
 }
 
-/// OHLC candle for a 1-minute bucket starting at `unix_minute`.
-/// Open = price at start of minute, Close = price at start of next minute,
-/// Wicks = small deterministic extensions (≤ 0.25%) beyond the OC range.
-fn candle(symbol: &str, unix_minute: i64) -> (f64, f64, f64, f64, f64) {
-    let o = price_at(symbol, unix_minute);
-    let c = price_at(symbol, unix_minute + 1);
-    let sh = hash64(symbol);
-    let oc_max = o.max(c);
-    let oc_min = o.min(c);
-    let wick_up = rand01(sh, unix_minute as u64 * 2) * 0.0025;
-    let wick_dn = rand01(sh, unix_minute as u64 * 2 + 1) * 0.0025;
-    let high = oc_max * (1.0 + wick_up);
-    let low = oc_min * (1.0 - wick_dn);
-    let vol = 10_000.0 + rand01(sh, unix_minute as u64) * 90_000.0;
-    (o, high, low, c, vol)
+/// Shared [`StreamStatus`], updated by the stream task.
+#[derive(Debug, Default)]
+pub struct StreamMonitor(Mutex<StreamStatus>);
+
+impl StreamMonitor {
+    fn update(&self, f: impl FnOnce(&mut StreamStatus)) {
+        f(&mut self.0.lock().expect("stream monitor lock poisoned"));
+    }
+
+    pub fn connected(&self) {
+        self.update(|s| {
+            s.connected = true;
+            s.connected_at = Some(Utc::now().naive_utc());
+            s.last_error = None;
+        });
+    }
+
+    pub fn message(&self) {
+        self.update(|s| {
+            s.messages += 1;
+            s.last_message_at = Some(Utc::now().naive_utc());
+        });
+    }
+
+    pub fn disconnected(&self, error: Option<String>) {
+        self.update(|s| {
+            s.connected = false;
+            if error.is_some() {
+                s.last_error = error;
+            }
+        });
+    }
+
+    pub fn report(&self, name: &'static str) -> StreamReport {
+        StreamReport {
+            name,
+            status: self.0.lock().expect("stream monitor lock poisoned").clone(),
+        }
+    }
 }
 
-fn f64_to_decimal(x: f64) -> Decimal {
+pub fn f64_to_decimal(x: f64) -> Decimal {
     Decimal::from_f64_retain(x).unwrap_or_default().round_dp(4)
 }
 
+/// Start of the minute `t` falls in, the bucket its candle is stored under.
+pub fn floor_minute(t: NaiveDateTime) -> NaiveDateTime {
+    t.trunc_subsecs(0).with_second(0).expect("valid second")
+}
+
 // ──────────────────────────────────────────────────────────────────────────────
 // Seed provider
 // ──────────────────────────────────────────────────────────────────────────────
@@ -123,42 +238,75 @@ fn f64_to_decimal(x: f64) -> Decimal {
 /// Deterministic, fully-local market-data source. Default in the MVP.
 pub struct SeedMarketDataProvider {
     pool: PgPool,
+    calendar: Arc<TradingCalendar>,
     /// How many seconds between "live" candle writes.
     tick_interval: Duration,
+    /// Assets a live stream is currently writing; the tick loop skips them.
+    streamed: RwLock<HashSet<Uuid>>,
+    tasks: ProviderTasks,
 }
 
 impl SeedMarketDataProvider {
-    pub fn new(pool: PgPool) -> Self {
+    pub fn new(pool: PgPool, calendar: Arc<TradingCalendar>) -> Self {
         Self {
             pool,
+            calendar,
             // 15s keeps recent candles fresh without hammering the DB.
             tick_interval: Duration::from_secs(15),
+            streamed: RwLock::new(HashSet::new()),
+            tasks: ProviderTasks::default(),
         }
     }
 
-    /// Fetch every active asset from the DB.
+    /// Hand `assets` to a connected live stream (or, with an empty set, take
+    /// them back when it drops).
+    pub fn set_streamed(&self, assets: HashSet<Uuid>) {
+        *self.streamed.write().expect("seed streamed lock poisoned") = assets;
+    }
+
+    /// Fetch every active asset (with its price model) from the DB.
     async fn active_assets(&self) -> Result<Vec<SeedAsset>> {
-        let rows = sqlx::query_as::<_, SeedAsset>(
-            "SELECT id, symbol FROM assets WHERE is_active = true",
-        )
-        .fetch_all(&self.pool)
-        .await?;
-        Ok(rows)
+        SeedAsset::load_active(&self.pool).await
     }
 
-    /// Write one "now" candle per asset, idempotently (ON CONFLICT DO UPDATE).
+    /// Start the tick loop for every active asset except `excluded` (assets
+    /// another provider writes live candles for).
+    pub fn spawn_excluding(self: &Arc<Self>, excluded: HashSet<Uuid>) {
+        self.tasks.spawn(self.clone().run_excluding(excluded));
+    }
+
+    async fn run_excluding(self: Arc<Self>, excluded: HashSet<Uuid>) {
+        let interval = self.tick_interval;
+        info!("Seed market-data generator started (tick = {:?})", interval);
+        loop {
+            match self.active_assets().await {
+                Ok(assets) => {
+                    let assets: Vec<SeedAsset> = {
+                        let streamed = self.streamed.read().expect("seed streamed lock poisoned");
+                        assets
+                            .into_iter()
+                            .filter(|a| !excluded.contains(&a.id) && !streamed.contains(&a.id))
+                            .collect()
+                    };
+                    if let Err(e) = self.tick_once(&assets).await {
+                        warn!("seed tick write failed: {:?}", e);
+                    }
+                }
+                Err(e) => warn!("seed provider: could not load assets: {:?}", e),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Write one "now" candle per asset whose market is open, idempotently
+    /// (ON CONFLICT DO UPDATE).
     async fn tick_once(&self, assets: &[SeedAsset]) -> Result<()> {
-        let now = Utc::now().naive_utc();
-        // Floor to the current minute so candles align with 1-minute history.
-        let bucket = now
-            .date()
-            .and_hms_opt(now.time().hour(), now.time().minute(), 0)
-            .unwrap_or(now);
+        let bucket = floor_minute(Utc::now().naive_utc());
 
         let unix_minute = bucket.and_utc().timestamp() / 60;
 
-        for a in assets {
-            let (o, h, l, c, v) = candle(&a.symbol, unix_minute);
+        for a in assets.iter().filter(|a| a.is_trading(&self.calendar, bucket)) {
+            let (o, h, l, c, v) = candle(&a.symbol, &a.model, unix_minute);
             sqlx::query(
                 r#"
                 INSERT INTO market_prices (asset_id, timestamp, open, high, low, close, volume)
@@ -191,67 +339,185 @@ impl MarketDataProvider for SeedMarketDataProvider {
     }
 
     async fn start(self: Arc<Self>) -> Result<()> {
-        let interval = self.tick_interval;
-        tokio::spawn(async move {
-            info!("Seed market-data generator started (tick = {:?})", interval);
-            loop {
-                match self.active_assets().await {
-                    Ok(assets) => {
-                        if let Err(e) = self.tick_once(&assets).await {
-                            warn!("seed tick write failed: {:?}", e);
-                        }
-                    }
-                    Err(e) => warn!("seed provider: could not load assets: {:?}", e),
-                }
-                tokio::time::sleep(interval).await;
-            }
-        });
+        self.spawn_excluding(HashSet::new());
         Ok(())
     }
+
+    fn stop(&self) {
+        self.tasks.abort_all();
+    }
+
+    async fn fill_range(
+        &self,
+        asset: &SeedAsset,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<RangeFill> {
+        let rows = backfill_range(&self.pool, &self.calendar, std::slice::from_ref(asset), start, end).await?;
+        Ok(RangeFill { rows, source: "seed" })
+    }
 }
 
 // ──────────────────────────────────────────────────────────────────────────────
-// Historical backfill (used by seeder.rs, lives here because the math does too)
+// Historical backfill (used by seeder.rs and gap repair, lives next to the
+// seed provider)
 // ──────────────────────────────────────────────────────────────────────────────
 
-/// Backfill ~`days` of 1-minute OHLC candles for the given assets.
-/// Idempotent via ON CONFLICT. Safe to call multiple times.
-pub async fn backfill_history(pool: &PgPool, assets: &[SeedAsset], days: i64) -> Result<usize> {
-    let end = Utc::now().naive_utc();
-    let start = end - ChronoDuration::days(days);
-    let start_minute = start.and_utc().timestamp() / 60;
-    let end_minute = end.and_utc().timestamp() / 60;
-
-    let mut total = 0usize;
-    for asset in assets {
-        let mut tx = pool.begin().await?;
-        let mut m = start_minute;
-        while m <= end_minute {
-            let ts = DateTime::<Utc>::from_timestamp(m * 60, 0)
-                .expect("valid timestamp")
-                .naive_utc();
-            let (o, h, l, c, v) = candle(&asset.symbol, m);
-            sqlx::query(
-                r#"
-                INSERT INTO market_prices (asset_id, timestamp, open, high, low, close, volume)
-                VALUES ($1, $2, $3, $4, $5, $6, $7)
-                ON CONFLICT (asset_id, timestamp) DO NOTHING
-                "#,
-            )
-            .bind(asset.id)
-            .bind(ts)
-            .bind(f64_to_decimal(o))
-            .bind(f64_to_decimal(h))
-            .bind(f64_to_decimal(l))
-            .bind(f64_to_decimal(c))
-            .bind(f64_to_decimal(v))
-            .execute(&mut *tx)
-            .await?;
-            total += 1;
-            m += 1;
+/// How many assets are backfilled concurrently (one connection each).
+const BACKFILL_CONCURRENCY: usize = 4;
+
+/// Column-oriented batch of 1-minute candles for a single asset, written with
+/// one multi-row `UNNEST` insert instead of a round-trip per row.
+#[derive(Default)]
+pub struct CandleBatch {
+    timestamps: Vec<NaiveDateTime>,
+    open: Vec<Decimal>,
+    high: Vec<Decimal>,
+    low: Vec<Decimal>,
+    close: Vec<Decimal>,
+    volume: Vec<Decimal>,
+}
+
+impl CandleBatch {
+    pub fn push(&mut self, ts: NaiveDateTime, (o, h, l, c, v): (f64, f64, f64, f64, f64)) {
+        self.timestamps.push(ts);
+        self.open.push(f64_to_decimal(o));
+        self.high.push(f64_to_decimal(h));
+        self.low.push(f64_to_decimal(l));
+        self.close.push(f64_to_decimal(c));
+        self.volume.push(f64_to_decimal(v));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    /// Insert every candle of the batch, leaving existing buckets untouched.
+    /// Returns the number of rows actually written.
+    pub async fn insert(&self, pool: &PgPool, asset_id: Uuid) -> Result<u64> {
+        if self.is_empty() {
+            return Ok(0);
+        }
+        let res = sqlx::query(
+            r#"
+            INSERT INTO market_prices (asset_id, timestamp, open, high, low, close, volume)
+            SELECT $1, t.*
+            FROM UNNEST($2::timestamp[], $3::numeric[], $4::numeric[],
+                        $5::numeric[], $6::numeric[], $7::numeric[]) AS t
+            ON CONFLICT (asset_id, timestamp) DO NOTHING
+            "#,
+        )
+        .bind(asset_id)
+        .bind(&self.timestamps)
+        .bind(&self.open)
+        .bind(&self.high)
+        .bind(&self.low)
+        .bind(&self.close)
+        .bind(&self.volume)
+        .execute(pool)
+        .await?;
+        Ok(res.rows_affected())
+    }
+}
+
+/// Seed candles for one asset over `[start_minute, end_minute]` (unix minutes)
+/// that fall on UTC `day`, skipping minutes when the exchange is closed.
+fn seed_day_batch(
+    asset: &SeedAsset,
+    calendar: &TradingCalendar,
+    day: i64,
+    start_minute: i64,
+    end_minute: i64,
+) -> CandleBatch {
+    let first = day * MINUTES_PER_DAY;
+    let from = start_minute.max(first);
+    let to = end_minute.min(first + MINUTES_PER_DAY - 1);
+    let mut batch = CandleBatch::default();
+    if from > to {
+        return batch;
+    }
+    let candles = day_candles(&DayCurve::new(&asset.symbol, &asset.model, day));
+    for m in from..=to {
+        let ts = DateTime::<Utc>::from_timestamp(m * 60, 0)
+            .expect("valid timestamp")
+            .naive_utc();
+        if asset.is_trading(calendar, ts) {
+            batch.push(ts, candles[(m - first) as usize]);
         }
-        tx.commit().await?;
     }
+    batch
+}
+
+/// Backfill seed candles for every minute bucket in `[start, end]`, skipping
+/// minutes when the asset's exchange is closed. Idempotent via ON CONFLICT.
+///
+/// Each asset is written one UTC day per statement (≤ 1440 rows), and up to
+/// `BACKFILL_CONCURRENCY` assets run in parallel. Returns rows written.
+pub async fn backfill_range(
+    pool: &PgPool,
+    calendar: &TradingCalendar,
+    assets: &[SeedAsset],
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+) -> Result<u64> {
+    let start_minute = start.and_utc().timestamp().div_euclid(60);
+    let end_minute = end.and_utc().timestamp().div_euclid(60);
+    if end_minute < start_minute {
+        return Ok(0);
+    }
+    let first_day = start_minute.div_euclid(MINUTES_PER_DAY);
+    let last_day = end_minute.div_euclid(MINUTES_PER_DAY);
+    let total_days = last_day - first_day + 1;
+    let started = std::time::Instant::now();
+
+    // Futures are built up front (not in a `Stream::map` closure) so callers
+    // inside spawned tasks stay provably `Send`.
+    let fills: Vec<_> = assets
+        .iter()
+        .enumerate()
+        .map(|(n, asset)| async move {
+            let asset_started = std::time::Instant::now();
+            let mut written = 0u64;
+            for (i, day) in (first_day..=last_day).enumerate() {
+                let batch = seed_day_batch(asset, calendar, day, start_minute, end_minute);
+                written += batch.insert(pool, asset.id).await?;
+                if (i + 1) % 30 == 0 && (i as i64 + 1) < total_days {
+                    info!(
+                        "Backfill: {} {}/{} days ({} rows so far)",
+                        asset.symbol,
+                        i + 1,
+                        total_days,
+                        written
+                    );
+                }
+            }
+            info!(
+                "Backfill: {} done [{}/{}] — {} rows over {} day(s) in {:.1?}",
+                asset.symbol,
+                n + 1,
+                assets.len(),
+                written,
+                total_days,
+                asset_started.elapsed()
+            );
+            Ok::<u64, anyhow::Error>(written)
+        })
+        .collect();
+    let per_asset = stream::iter(fills)
+        .buffer_unordered(BACKFILL_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut total = 0u64;
+    for written in per_asset {
+        total += written?;
+    }
+    info!(
+        "Backfill: {} rows for {} asset(s) in {:.1?}",
+        total,
+        assets.len(),
+        started.elapsed()
+    );
     Ok(total)
 }
 
@@ -267,16 +533,34 @@ pub struct LiveMarketDataProvider {
     pool: PgPool,
     api_key: String,
     access_token: String,
+    depth_snapshot_seconds: i64,
+    quality_rules: QualityRules,
     seed: Arc<SeedMarketDataProvider>,
+    /// Tells the Kite stream to reload its instrument list.
+    resubscribe: Arc<Notify>,
+    monitor: Arc<StreamMonitor>,
+    tasks: ProviderTasks,
 }
 
 impl LiveMarketDataProvider {
-    pub fn new(pool: PgPool, api_key: String, access_token: String) -> Self {
+    pub fn new(
+        pool: PgPool,
+        seed: Arc<SeedMarketDataProvider>,
+        api_key: String,
+        access_token: String,
+        depth_snapshot_seconds: i64,
+        quality_rules: QualityRules,
+    ) -> Self {
         Self {
-            seed: Arc::new(SeedMarketDataProvider::new(pool.clone())),
             pool,
+            seed,
             api_key,
             access_token,
+            depth_snapshot_seconds,
+            quality_rules,
+            resubscribe: Arc::new(Notify::new()),
+            monitor: Arc::new(StreamMonitor::default()),
+            tasks: ProviderTasks::default(),
         }
     }
 }
@@ -294,25 +578,148 @@ impl MarketDataProvider for LiveMarketDataProvider {
         let pool = self.pool.clone();
         let api_key = self.api_key.clone();
         let access_token = self.access_token.clone();
+        let depth_snapshot_seconds = self.depth_snapshot_seconds;
+        let quality_rules = self.quality_rules;
+        let resubscribe = self.resubscribe.clone();
+        let monitor = self.monitor.clone();
 
-        tokio::spawn(async move {
+        self.tasks.spawn(async move {
             info!("Attempting Zerodha Kite live stream...");
             match super::market_data_ingester::run_market_data_service(
                 pool,
                 api_key,
                 access_token,
+                depth_snapshot_seconds,
+                quality_rules,
+                resubscribe,
+                monitor.clone(),
             )
             .await
             {
-                Ok(()) => info!("Kite stream terminated cleanly"),
-                Err(e) => warn!(
-                    "Kite stream unavailable ({}). Continuing on seed data.",
-                    e
-                ),
+                Ok(()) => {
+                    info!("Kite stream terminated cleanly");
+                    monitor.disconnected(None);
+                }
+                Err(e) => {
+                    warn!(
+                        "Kite stream unavailable ({}). Continuing on seed data.",
+                        e
+                    );
+                    monitor.disconnected(Some(e.to_string()));
+                }
             }
         });
         Ok(())
     }
+
+    fn stop(&self) {
+        self.tasks.abort_all();
+        self.seed.stop();
+    }
+
+    fn streams(&self) -> Vec<StreamReport> {
+        vec![self.monitor.report("kite")]
+    }
+
+    fn refresh_subscriptions(&self) {
+        self.resubscribe.notify_one();
+    }
+
+    /// Kite historical candles for instruments we have a token for; seed math
+    /// for everything else, or when the historical API is unavailable.
+    async fn fill_range(
+        &self,
+        asset: &SeedAsset,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<RangeFill> {
+        let Some(token) = super::market_data_ingester::instrument_token(&self.pool, asset.id).await? else {
+            return self.seed.fill_range(asset, start, end).await;
+        };
+        match super::market_data_ingester::fetch_historical_minutes(
+            &self.api_key,
+            &self.access_token,
+            token,
+            start,
+            end,
+        )
+        .await
+        {
+            Ok(candles) => {
+                let mut batch = CandleBatch::default();
+                for (ts, ohlcv) in candles.into_iter().filter(|(ts, _)| start <= *ts && *ts <= end) {
+                    batch.push(ts, ohlcv);
+                }
+                let rows = batch.insert(&self.pool, asset.id).await?;
+                Ok(RangeFill { rows, source: "kite" })
+            }
+            Err(e) => {
+                warn!(
+                    "Kite historical fetch for {} failed ({}). Filling from seed.",
+                    asset.symbol, e
+                );
+                self.seed.fill_range(asset, start, end).await
+            }
+        }
+    }
+}
+
+// ──────────────────────────────────────────────────────────────────────────────
+// Exchange routing
+// ──────────────────────────────────────────────────────────────────────────────
+
+/// Runs the crypto feed beside the main provider: assets on the feed's
+/// exchanges are filled from it, everything else from `main`.
+pub struct ExchangeRouter {
+    main: Arc<dyn MarketDataProvider>,
+    crypto: Arc<CryptoMarketDataProvider>,
+}
+
+impl ExchangeRouter {
+    pub fn new(main: Arc<dyn MarketDataProvider>, crypto: Arc<CryptoMarketDataProvider>) -> Self {
+        Self { main, crypto }
+    }
+}
+
+#[async_trait]
+impl MarketDataProvider for ExchangeRouter {
+    fn label(&self) -> &'static str {
+        "crypto exchange websocket + main provider, routed by exchange"
+    }
+
+    async fn start(self: Arc<Self>) -> Result<()> {
+        self.main.clone().start().await?;
+        self.crypto.clone().start().await
+    }
+
+    fn stop(&self) {
+        self.crypto.stop();
+        self.main.stop();
+    }
+
+    fn streams(&self) -> Vec<StreamReport> {
+        let mut streams = self.main.streams();
+        streams.extend(self.crypto.streams());
+        streams
+    }
+
+    fn refresh_subscriptions(&self) {
+        self.main.refresh_subscriptions();
+        self.crypto.refresh_subscriptions();
+    }
+
+    async fn fill_range(
+        &self,
+        asset: &SeedAsset,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<RangeFill> {
+        if self.crypto.routes_to(asset) {
+            self.crypto.fill_range(asset, start, end).await
+        } else {
+            self.main.fill_range(asset, start, end).await
+        }
+    }
 }
 
 // ──────────────────────────────────────────────────────────────────────────────
@@ -325,105 +732,100 @@ impl MarketDataProvider for LiveMarketDataProvider {
 pub fn build(
     pool: PgPool,
     config: &AppConfig,
+    calendar: Arc<TradingCalendar>,
+) -> (Arc<dyn MarketDataProvider>, MarketDataMode) {
+    let seed = Arc::new(SeedMarketDataProvider::new(pool.clone(), calendar.clone()));
+    let (main, mode) = build_main(pool.clone(), config, calendar, seed.clone());
+    match &config.crypto {
+        Some(crypto) if matches!(mode, MarketDataMode::Seed | MarketDataMode::Live) => {
+            info!(
+                "Routing {} assets to the crypto feed at {}; other assets use {}",
+                crypto.exchanges.join("/"),
+                crypto.ws_url,
+                main.label()
+            );
+            let crypto = Arc::new(CryptoMarketDataProvider::new(pool, seed, crypto.clone()));
+            (Arc::new(ExchangeRouter::new(main, crypto)), mode)
+        }
+        Some(_) => {
+            warn!(
+                "CRYPTO_FEED is ignored in {} mode; the historical source covers every asset.",
+                mode.as_str()
+            );
+            (main, mode)
+        }
+        None => (main, mode),
+    }
+}
+
+fn build_main(
+    pool: PgPool,
+    config: &AppConfig,
+    calendar: Arc<TradingCalendar>,
+    seed: Arc<SeedMarketDataProvider>,
 ) -> (Arc<dyn MarketDataProvider>, MarketDataMode) {
     match (config.market_data_mode, &config.kite) {
-        (MarketDataMode::Live, Some(kite)) => (
+        (MarketDataMode::Live, Some(KiteConfig { api_key, access_token: Some(access_token), .. })) => (
             Arc::new(LiveMarketDataProvider::new(
                 pool,
-                kite.api_key.clone(),
-                kite.access_token.clone(),
+                seed,
+                api_key.clone(),
+                access_token.clone(),
+                config.depth_snapshot_seconds,
+                QualityRules {
+                    max_jump_pct: config.tick_max_jump_pct,
+                    max_jump_sigma: config.tick_max_jump_sigma,
+                    max_future_seconds: config.tick_max_future_seconds,
+                },
             )),
             MarketDataMode::Live,
         ),
-        (MarketDataMode::Live, None) => {
+        (MarketDataMode::Live, _) => {
             warn!(
                 "MARKET_DATA_MODE=live requested but no Kite credentials. \
                  Falling back to seed mode."
             );
-            (
-                Arc::new(SeedMarketDataProvider::new(pool)),
-                MarketDataMode::Seed,
-            )
+            (seed, MarketDataMode::Seed)
         }
-        (MarketDataMode::Seed, _) => (
-            Arc::new(SeedMarketDataProvider::new(pool)),
-            MarketDataMode::Seed,
+        (MarketDataMode::File, _) if !config.files.paths.is_empty() => (
+            Arc::new(FileMarketDataProvider::new(pool, calendar, config.files.clone())),
+            MarketDataMode::File,
         ),
+        (MarketDataMode::File, _) => {
+            warn!("MARKET_DATA_MODE=file requested but MARKET_DATA_FILES is empty. Falling back to seed mode.");
+            (seed, MarketDataMode::Seed)
+        }
+        (MarketDataMode::Demo, _) => match (config.demo.window_start, config.demo.window_end) {
+            (Some(start), Some(end)) if start < end => (
+                Arc::new(DemoMarketDataProvider::new(
+                    pool,
+                    calendar,
+                    config.demo.clone(),
+                    config.files.clone(),
+                    (start, end),
+                )),
+                MarketDataMode::Demo,
+            ),
+            _ => {
+                warn!(
+                    "MARKET_DATA_MODE=demo requested without a valid DEMO_WINDOW_START/DEMO_WINDOW_END. \
+                     Falling back to seed mode."
+                );
+                (seed, MarketDataMode::Seed)
+            }
+        },
+        (MarketDataMode::Seed, _) => (seed, MarketDataMode::Seed),
     }
 }
 
 // ──────────────────────────────────────────────────────────────────────────────
-// Unit tests for the deterministic price model
+// Unit tests for the helpers here; the price model is tested in price_model.rs
 // ──────────────────────────────────────────────────────────────────────────────
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn rand01_is_in_unit_interval() {
-        for i in 0..10_000u64 {
-            let r = rand01(0xdead_beef, i);
-            assert!((0.0..1.0).contains(&r), "rand01 out of range: {}", r);
-        }
-    }
-
-    #[test]
-    fn price_at_is_deterministic() {
-        let a = price_at("BTC", 29_000_000);
-        let b = price_at("BTC", 29_000_000);
-        assert_eq!(a, b, "price_at must be deterministic for same inputs");
-    }
-
-    #[test]
-    fn price_at_differs_across_symbols_and_time() {
-        let btc = price_at("BTC", 29_000_000);
-        let eth = price_at("ETH", 29_000_000);
-        assert!((btc - eth).abs() > 1.0, "BTC/ETH prices must diverge");
-        let next = price_at("BTC", 29_000_005);
-        assert!(btc != next, "Price must evolve across minutes");
-    }
-
-    #[test]
-    fn base_price_is_positive_for_known_symbols() {
-        for s in &["BTC", "ETH", "RELIANCE", "TCS", "NIFTYBEES"] {
-            let b = base_price(s);
-            assert!(b > 0.0, "base price must be > 0 for {}", s);
-        }
-    }
-
-    #[test]
-    fn candle_ohlc_invariants_hold() {
-        // For 5000 random minutes across a few symbols, high >= max(o,c),
-        // low <= min(o,c), volume > 0, and candles are fully deterministic.
-        for minute in 28_000_000i64..28_005_000 {
-            for sym in &["BTC", "ETH", "RELIANCE"] {
-                let (o, h, l, c, v) = candle(sym, minute);
-                assert!(h >= o.max(c) - 1e-9, "high < max(o,c): {} {}", sym, minute);
-                assert!(l <= o.min(c) + 1e-9, "low > min(o,c): {} {}", sym, minute);
-                assert!(v > 0.0, "volume must be positive");
-                // determinism
-                let again = candle(sym, minute);
-                assert_eq!((o, h, l, c, v), again, "candle must be deterministic");
-            }
-        }
-    }
-
-    #[test]
-    fn wicks_are_tight_enough_to_be_realistic() {
-        // Worst-case wick stretch is below 1% — keeps the synthetic series
-        // visually plausible and avoids settlement noise in contests.
-        for minute in 28_000_000i64..28_001_000 {
-            let (o, h, l, c, _) = candle("BTC", minute);
-            let span = h - l;
-            let reference = o.max(c);
-            assert!(
-                span / reference < 0.02,
-                "minute {minute}: candle too wide ({span})"
-            );
-        }
-    }
-
     #[test]
     fn f64_to_decimal_rounds_to_four_dp() {
         let d = f64_to_decimal(1.234_567_89);
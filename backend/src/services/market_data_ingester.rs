@@ -1,13 +1,140 @@
 use std::collections::HashMap;
-use tokio::sync::RwLock;
-use std::sync::Arc;
+use tokio::sync::{Notify, RwLock};
+use std::sync::{Arc, Mutex};
 use futures_util::StreamExt;
-use zerodha_tl::{KiteConnect, config::StreamConfig, models::{Mode, Tick}};
-use sqlx::PgPool;
-use chrono::Utc;
+use zerodha_tl::{KiteConnect, config::StreamConfig, models::{Depth, Mode, Tick}};
+use sqlx::{PgExecutor, PgPool};
+use chrono::{DateTime, Duration, NaiveDateTime, SubsecRound, Utc};
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
 use uuid::Uuid;
 use anyhow::Result;
+use sqlx::types::Json;
+use sha2::{Digest, Sha256};
+
+use crate::services::market_data::{floor_minute, StreamMonitor};
+use crate::services::market_depth::{self, DepthLevel, DepthSnapshot};
+use crate::services::price_history;
+use crate::services::tick_quality::{self, InstrumentState, QualityRules, QuarantinedTick, TickSample, Violation};
+
+/// Kite instrument token of an asset (`assets.instrument_token`), if set.
+pub async fn instrument_token(pool: &PgPool, asset_id: Uuid) -> Result<Option<u32>> {
+    let token: Option<Option<i64>> =
+        sqlx::query_scalar("SELECT instrument_token FROM assets WHERE id = $1")
+            .bind(asset_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(token.flatten().and_then(|t| u32::try_from(t).ok()))
+}
+
+/// Kite allows at most 60 days per minute-interval historical request.
+const KITE_HISTORICAL_MAX_DAYS: i64 = 60;
+
+/// One-minute OHLCV candles for an instrument from the Kite historical API,
+/// as `(utc_timestamp, (open, high, low, close, volume))`.
+/// `from`/`to` are UTC; Kite itself speaks IST.
+pub async fn fetch_historical_minutes(
+    api_key: &str,
+    access_token: &str,
+    instrument_token: u32,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> Result<Vec<(NaiveDateTime, (f64, f64, f64, f64, f64))>> {
+    #[derive(serde::Deserialize)]
+    struct HistoricalResponse {
+        data: HistoricalData,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct HistoricalData {
+        candles: Vec<(String, f64, f64, f64, f64, f64)>,
+    }
+
+    let ist = Duration::minutes(330);
+    let client = reqwest::Client::new();
+    let mut out = Vec::new();
+    let mut chunk_start = from;
+
+    while chunk_start <= to {
+        let chunk_end = to.min(chunk_start + Duration::days(KITE_HISTORICAL_MAX_DAYS) - Duration::minutes(1));
+        let url = format!(
+            "https://api.kite.trade/instruments/historical/{}/minute",
+            instrument_token
+        );
+        let response = client
+            .get(&url)
+            .query(&[
+                ("from", (chunk_start + ist).format("%Y-%m-%d %H:%M:%S").to_string()),
+                ("to", (chunk_end + ist).format("%Y-%m-%d %H:%M:%S").to_string()),
+            ])
+            .header("X-Kite-Version", "3")
+            .header(
+                "Authorization",
+                format!("token {}:{}", api_key.trim(), access_token.trim()),
+            )
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<HistoricalResponse>()
+            .await?;
+
+        for (ts, o, h, l, c, v) in response.data.candles {
+            let ts = DateTime::parse_from_str(&ts, "%Y-%m-%dT%H:%M:%S%z")
+                .map_err(|e| anyhow::anyhow!("Invalid Kite timestamp '{}': {}", ts, e))?
+                .naive_utc();
+            out.push((ts, (o, h, l, c, v)));
+        }
+        chunk_start = chunk_end + Duration::minutes(1);
+    }
+
+    Ok(out)
+}
+
+/// A Kite login session, from [`exchange_request_token`].
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct KiteSession {
+    pub access_token: String,
+    pub user_id: Option<String>,
+}
+
+/// Exchange the `request_token` Kite hands back after a login for an access
+/// token (`POST /session/token`, signed with the API secret).
+pub async fn exchange_request_token(
+    api_key: &str,
+    api_secret: &str,
+    request_token: &str,
+) -> Result<KiteSession> {
+    #[derive(serde::Deserialize)]
+    struct SessionResponse {
+        data: Option<KiteSession>,
+        message: Option<String>,
+    }
+
+    let checksum: String = Sha256::digest(format!("{}{}{}", api_key.trim(), request_token.trim(), api_secret.trim()))
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let response = reqwest::Client::new()
+        .post("https://api.kite.trade/session/token")
+        .header("X-Kite-Version", "3")
+        .form(&[
+            ("api_key", api_key.trim()),
+            ("request_token", request_token.trim()),
+            ("checksum", checksum.as_str()),
+        ])
+        .send()
+        .await?;
+    let status = response.status();
+    let body = response.json::<SessionResponse>().await?;
+    match body.data {
+        Some(session) if status.is_success() => Ok(session),
+        _ => Err(anyhow::anyhow!(
+            "Kite rejected the request token ({}): {}",
+            status,
+            body.message.unwrap_or_default()
+        )),
+    }
+}
 
 /// Market data ingestion service using zerodha-ss
 pub struct MarketDataIngester {
@@ -15,60 +142,71 @@ pub struct MarketDataIngester {
     api_key: String,
     access_token: String,
     asset_tokens: Arc<RwLock<HashMap<u32, Uuid>>>, // Maps instrument token to asset_id
+    /// Minimum spacing of stored depth snapshots per instrument.
+    depth_interval: Duration,
+    last_depth: Mutex<HashMap<u32, NaiveDateTime>>,
+    rules: QualityRules,
+    quality: Mutex<HashMap<u32, InstrumentState>>,
+    monitor: Arc<StreamMonitor>,
 }
 
 impl MarketDataIngester {
-    pub fn new(pool: PgPool, api_key: String, access_token: String) -> Self {
+    pub fn new(
+        pool: PgPool,
+        api_key: String,
+        access_token: String,
+        depth_snapshot_seconds: i64,
+        rules: QualityRules,
+        monitor: Arc<StreamMonitor>,
+    ) -> Self {
         Self {
             pool,
             api_key,
             access_token,
             asset_tokens: Arc::new(RwLock::new(HashMap::new())),
+            depth_interval: Duration::seconds(depth_snapshot_seconds),
+            last_depth: Mutex::new(HashMap::new()),
+            rules,
+            quality: Mutex::new(HashMap::new()),
+            monitor,
         }
     }
     
-    /// Load asset mappings from database
-    pub async fn load_asset_mappings(&self) -> Result<()> {
+    /// Load asset mappings from database (active assets with an instrument
+    /// token). Returns the instruments to subscribe to.
+    pub async fn load_asset_mappings(&self) -> Result<Vec<u32>> {
         #[derive(sqlx::FromRow)]
         struct AssetData {
             id: Uuid,
-            symbol: String,
-            #[allow(dead_code)]
-            exchange: String,
+            instrument_token: i64,
         }
         
         let assets = sqlx::query_as::<_, AssetData>(
             r#"
-            SELECT id, symbol, exchange 
+            SELECT id, instrument_token
             FROM assets 
-            WHERE is_active = true AND asset_type IN ('crypto', 'equity')
+            WHERE is_active = true AND instrument_token IS NOT NULL
             "#
         )
         .fetch_all(&self.pool)
         .await?;
         
         let mut mappings = self.asset_tokens.write().await;
+        mappings.clear();
         
         for asset in assets {
-            // Map known instruments (this should be configured properly)
-            // Example: BTC -> 256265 (instrument token for example)
-            // This mapping should come from a config or database
-            let instrument_token = match asset.symbol.as_str() {
-                "NIFTY50" => 256265,
-                "INFY" => 408065,
-                // Add more mappings as needed
-                _ => continue,
-            };
-            
-            mappings.insert(instrument_token, asset.id);
+            if let Ok(token) = u32::try_from(asset.instrument_token) {
+                mappings.insert(token, asset.id);
+            }
         }
         
         tracing::info!("Loaded {} asset mappings", mappings.len());
-        Ok(())
+        Ok(mappings.keys().copied().collect())
     }
     
-    /// Start streaming live market data
-    pub async fn start_streaming(&self, instruments: Vec<u32>) -> Result<()> {
+    /// Start streaming live market data. Returns `Ok(true)` when the stream
+    /// was stopped because `resubscribe` fired, `Ok(false)` when it ended.
+    pub async fn start_streaming(&self, instruments: Vec<u32>, resubscribe: &Notify) -> Result<bool> {
         tracing::info!("Starting market data stream for {} instruments", instruments.len());
         
         let kite = KiteConnect::new(self.api_key.clone(), self.access_token.clone());
@@ -78,21 +216,36 @@ impl MarketDataIngester {
             .map_err(|e| anyhow::anyhow!("Failed to connect to Kite stream: {}", e))?;
         
         tracing::info!("Connected to Kite WebSocket");
+        self.monitor.connected();
         
-        while let Some(tick) = stream.next().await {
-            if let Err(e) = self.process_tick(tick).await {
-                tracing::error!("Failed to process tick: {}", e);
+        loop {
+            tokio::select! {
+                tick = stream.next() => match tick {
+                    Some(tick) => {
+                        self.monitor.message();
+                        if let Err(e) = self.process_tick(tick).await {
+                            tracing::error!("Failed to process tick: {}", e);
+                        }
+                    }
+                    None => break,
+                },
+                _ = resubscribe.notified() => {
+                    tracing::info!("Instrument list changed, reconnecting Kite stream");
+                    self.monitor.disconnected(None);
+                    return Ok(true);
+                }
             }
         }
         
         tracing::warn!("Market data stream ended");
-        Ok(())
+        self.monitor.disconnected(None);
+        Ok(false)
     }
     
-    /// Process a single tick and store in database
+    /// Check a single tick and store it, or quarantine it if it fails the
+    /// data-quality rules.
     async fn process_tick(&self, tick: Tick) -> Result<()> {
-        let mappings = self.asset_tokens.read().await;
-        let asset_id = match mappings.get(&tick.instrument_token) {
+        let asset_id = match self.asset_tokens.read().await.get(&tick.instrument_token) {
             Some(id) => *id,
             None => {
                 tracing::warn!("Unknown instrument token: {}", tick.instrument_token);
@@ -101,31 +254,53 @@ impl MarketDataIngester {
         };
         
         let timestamp = Utc::now().naive_utc();
+        let sample = TickSample {
+            ltp: tick.ltp,
+            exchange_time: tick
+                .exchange_timestamp
+                .filter(|ts| *ts > 0)
+                .and_then(|ts| DateTime::from_timestamp(i64::from(ts), 0))
+                .map(|dt| dt.naive_utc()),
+            day_ohlc: match (tick.open, tick.high, tick.low) {
+                (Some(open), Some(high), Some(low)) => Some((open, high, low)),
+                _ => None,
+            },
+        };
+        let price = Decimal::from_f64_retain(tick.ltp).map(|p| p.round_dp(4));
+        let volume = tick.volume.map(Decimal::from);
         
-        // Store OHLC data (for simplicity, using current price for all)
-        // In production, you'd aggregate ticks into candles
-        let price = Decimal::from_f64_retain(tick.ltp).unwrap_or_default();
+        let (mut violations, reference_price) = self.check_tick(asset_id, tick.instrument_token, &sample).await?;
+        if price.is_none() && !violations.contains(&Violation::InvalidPrice) {
+            violations.push(Violation::InvalidPrice);
+        }
         
-        sqlx::query(
-            r#"
-            INSERT INTO market_prices (asset_id, timestamp, open, high, low, close, volume)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
-            ON CONFLICT (asset_id, timestamp) 
-            DO UPDATE SET 
-                high = GREATEST(market_prices.high, EXCLUDED.high),
-                low = LEAST(market_prices.low, EXCLUDED.low),
-                close = EXCLUDED.close
-            "#
-        )
-        .bind(asset_id)
-        .bind(timestamp)
-        .bind(price)
-        .bind(price)
-        .bind(price)
-        .bind(price)
-        .bind(tick.volume.map(Decimal::from))
-        .execute(&self.pool)
-        .await?;
+        let Some(price) = price.filter(|_| violations.is_empty()) else {
+            tracing::warn!(
+                "Quarantined tick for instrument {} @ {}: {:?}",
+                tick.instrument_token,
+                tick.ltp,
+                violations
+            );
+            tick_quality::quarantine(
+                &self.pool,
+                &QuarantinedTick {
+                    asset_id,
+                    instrument_token: i64::from(tick.instrument_token),
+                    received_at: timestamp,
+                    exchange_time: sample.exchange_time,
+                    price: price.filter(|p| p.abs() < Decimal::from(100_000_000)),
+                    volume,
+                    reference_price,
+                    violations: &violations,
+                    raw: raw_tick(&tick),
+                },
+            )
+            .await?;
+            return Ok(());
+        };
+        
+        // Ticks update the candle of the minute they arrived in
+        store_price(&self.pool, asset_id, floor_minute(timestamp), price, volume).await?;
         
         tracing::debug!(
             "Stored price for instrument {}: {} @ {}",
@@ -134,30 +309,150 @@ impl MarketDataIngester {
             timestamp
         );
         
+        self.maybe_store_depth(asset_id, &tick, timestamp).await?;
+        
+        Ok(())
+    }
+    
+    /// Run the quality rules against the instrument's last accepted tick.
+    /// The first tick after a (re)start is compared with the last stored
+    /// close. Returns the violations and the price compared against.
+    async fn check_tick(
+        &self,
+        asset_id: Uuid,
+        token: u32,
+        sample: &TickSample,
+    ) -> Result<(Vec<Violation>, Option<f64>)> {
+        let known = self.quality.lock().expect("tick quality lock poisoned").contains_key(&token);
+        let anchor = if known {
+            None
+        } else {
+            price_history::close_at_or_before(&self.pool, asset_id, Utc::now().naive_utc())
+                .await?
+                .and_then(|p| p.to_f64())
+        };
+        
+        let mut states = self.quality.lock().expect("tick quality lock poisoned");
+        let state = states
+            .entry(token)
+            .or_insert_with(|| anchor.map(InstrumentState::anchored).unwrap_or_default());
+        let reference = state.last_price();
+        Ok((state.observe(&self.rules, sample, Utc::now().naive_utc()), reference))
+    }
+    
+    /// Persist the tick's 5-level depth, if it carries one and the last
+    /// snapshot for this instrument is at least `depth_interval` old.
+    async fn maybe_store_depth(&self, asset_id: Uuid, tick: &Tick, now: NaiveDateTime) -> Result<()> {
+        let (Some(bids), Some(offers)) = (&tick.bids, &tick.offers) else {
+            return Ok(());
+        };
+        let now = now.trunc_subsecs(0);
+        {
+            let mut last = self.last_depth.lock().expect("depth throttle lock poisoned");
+            if last
+                .get(&tick.instrument_token)
+                .is_some_and(|at| now - *at < self.depth_interval)
+            {
+                return Ok(());
+            }
+            last.insert(tick.instrument_token, now);
+        }
+        
+        let snapshot = DepthSnapshot {
+            timestamp: now,
+            bids: Json(market_depth::clean_levels(bids.iter().map(depth_level))),
+            asks: Json(market_depth::clean_levels(offers.iter().map(depth_level))),
+            total_buy_quantity: tick.total_buy_quantity.map(i64::from),
+            total_sell_quantity: tick.total_sell_quantity.map(i64::from),
+        };
+        if snapshot.bids.is_empty() && snapshot.asks.is_empty() {
+            return Ok(());
+        }
+        market_depth::insert(&self.pool, asset_id, &snapshot).await?;
         Ok(())
     }
 }
 
-/// Run market data ingestion as a background service
+/// Merge a price into the stored candle at `timestamp`.
+pub async fn store_price<'e, E: PgExecutor<'e>>(
+    executor: E,
+    asset_id: Uuid,
+    timestamp: NaiveDateTime,
+    price: Decimal,
+    volume: Option<Decimal>,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO market_prices (asset_id, timestamp, open, high, low, close, volume)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (asset_id, timestamp) 
+        DO UPDATE SET 
+            high = GREATEST(market_prices.high, EXCLUDED.high),
+            low = LEAST(market_prices.low, EXCLUDED.low),
+            close = EXCLUDED.close
+        "#
+    )
+    .bind(asset_id)
+    .bind(timestamp)
+    .bind(price)
+    .bind(price)
+    .bind(price)
+    .bind(price)
+    .bind(volume)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// The tick fields kept with a quarantined tick.
+fn raw_tick(tick: &Tick) -> serde_json::Value {
+    serde_json::json!({
+        "instrument_token": tick.instrument_token,
+        "ltp": tick.ltp.is_finite().then_some(tick.ltp),
+        "volume": tick.volume,
+        "open": tick.open,
+        "high": tick.high,
+        "low": tick.low,
+        "close": tick.close,
+        "last_traded_timestamp": tick.last_traded_timestamp,
+        "exchange_timestamp": tick.exchange_timestamp,
+    })
+}
+
+fn depth_level(depth: &Depth) -> DepthLevel {
+    DepthLevel {
+        price: Decimal::from_f64_retain(depth.price).unwrap_or_default().round_dp(4),
+        quantity: i64::from(depth.quantity),
+        orders: i32::from(depth.orders),
+    }
+}
+
+/// Run market data ingestion as a background service. Notifying
+/// `resubscribe` reloads the instrument list from the database and reconnects.
 pub async fn run_market_data_service(
     pool: PgPool,
     api_key: String,
     access_token: String,
+    depth_snapshot_seconds: i64,
+    rules: QualityRules,
+    resubscribe: Arc<Notify>,
+    monitor: Arc<StreamMonitor>,
 ) -> Result<()> {
-    let ingester = MarketDataIngester::new(pool, api_key, access_token);
+    let ingester =
+        MarketDataIngester::new(pool, api_key, access_token, depth_snapshot_seconds, rules, monitor);
     
-    // Load asset mappings
-    ingester.load_asset_mappings().await?;
-    
-    // Define instruments to track (this should come from config)
-    let instruments = vec![
-        256265,  // Example: Nifty 50
-        408065,  // Example: Infosys
-        // Add more instruments
-    ];
-    
-    // Start streaming (this runs forever)
-    ingester.start_streaming(instruments).await?;
-    
-    Ok(())
+    loop {
+        // Instruments to track: active assets with an instrument token
+        let instruments = ingester.load_asset_mappings().await?;
+        if instruments.is_empty() {
+            tracing::info!("No assets with instrument tokens; waiting for subscriptions");
+            resubscribe.notified().await;
+            continue;
+        }
+        
+        // Stream until it ends or the instrument list changes
+        if !ingester.start_streaming(instruments, &resubscribe).await? {
+            return Ok(());
+        }
+    }
 }
@@ -0,0 +1,250 @@
+//! Order-book depth snapshots.
+//!
+//! The live ingester subscribes in Kite `Full` mode, which carries the top
+//! five bid and offer levels on every tick. A snapshot per asset is stored at
+//! most every `DEPTH_SNAPSHOT_SECONDS`; the retention job prunes them after
+//! `DEPTH_RETENTION_DAYS`. Spread, mid and imbalance are derived on read.
+
+use chrono::NaiveDateTime;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Most snapshots a single range query returns.
+pub const MAX_SNAPSHOTS: i64 = 2_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DepthLevel {
+    pub price: Decimal,
+    pub quantity: i64,
+    pub orders: i32,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct DepthSnapshot {
+    pub timestamp: NaiveDateTime,
+    /// Best (highest) bid first.
+    pub bids: Json<Vec<DepthLevel>>,
+    /// Best (lowest) ask first.
+    pub asks: Json<Vec<DepthLevel>>,
+    /// Pending buy/sell quantity across the whole book, not just the top five.
+    pub total_buy_quantity: Option<i64>,
+    pub total_sell_quantity: Option<i64>,
+}
+
+/// Figures derived from the visible levels of a snapshot.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DepthMetrics {
+    pub best_bid: Option<Decimal>,
+    pub best_ask: Option<Decimal>,
+    pub mid: Option<Decimal>,
+    pub spread: Option<Decimal>,
+    /// Spread relative to mid, in basis points.
+    pub spread_bps: Option<f64>,
+    /// `(bid qty - ask qty) / (bid qty + ask qty)` over the visible levels;
+    /// +1 is all bids, -1 all asks.
+    pub imbalance: Option<f64>,
+}
+
+/// A snapshot together with its metrics, as served to clients.
+#[derive(Debug, Clone, Serialize)]
+pub struct DepthFrame {
+    #[serde(flatten)]
+    pub snapshot: DepthSnapshot,
+    #[serde(flatten)]
+    pub metrics: DepthMetrics,
+}
+
+impl From<DepthSnapshot> for DepthFrame {
+    fn from(snapshot: DepthSnapshot) -> Self {
+        let metrics = metrics(&snapshot.bids, &snapshot.asks);
+        Self { snapshot, metrics }
+    }
+}
+
+/// Drop empty levels (Kite pads a thin book with zero price/quantity).
+pub fn clean_levels(levels: impl IntoIterator<Item = DepthLevel>) -> Vec<DepthLevel> {
+    levels
+        .into_iter()
+        .filter(|l| l.price > Decimal::ZERO && l.quantity > 0)
+        .collect()
+}
+
+pub fn metrics(bids: &[DepthLevel], asks: &[DepthLevel]) -> DepthMetrics {
+    let best_bid = bids.iter().map(|l| l.price).max();
+    let best_ask = asks.iter().map(|l| l.price).min();
+    let (mid, spread, spread_bps) = match (best_bid, best_ask) {
+        (Some(bid), Some(ask)) => {
+            let mid = (bid + ask) / Decimal::TWO;
+            let spread = ask - bid;
+            let bps = (!mid.is_zero())
+                .then(|| (spread / mid * Decimal::from(10_000)).round_dp(4).to_f64())
+                .flatten();
+            (Some(mid.round_dp(4)), Some(spread), bps)
+        }
+        _ => (None, None, None),
+    };
+
+    let bid_qty: i64 = bids.iter().map(|l| l.quantity).sum();
+    let ask_qty: i64 = asks.iter().map(|l| l.quantity).sum();
+    let imbalance = (bid_qty + ask_qty > 0).then(|| {
+        let ratio = (bid_qty - ask_qty) as f64 / (bid_qty + ask_qty) as f64;
+        (ratio * 10_000.0).round() / 10_000.0
+    });
+
+    DepthMetrics {
+        best_bid,
+        best_ask,
+        mid,
+        spread,
+        spread_bps,
+        imbalance,
+    }
+}
+
+/// Store a snapshot; a second snapshot in the same second is ignored.
+pub async fn insert(pool: &PgPool, asset_id: Uuid, snapshot: &DepthSnapshot) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO market_depth_snapshots
+            (asset_id, timestamp, bids, asks, total_buy_quantity, total_sell_quantity)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (asset_id, timestamp) DO NOTHING
+        "#,
+    )
+    .bind(asset_id)
+    .bind(snapshot.timestamp)
+    .bind(&snapshot.bids)
+    .bind(&snapshot.asks)
+    .bind(snapshot.total_buy_quantity)
+    .bind(snapshot.total_sell_quantity)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+const SNAPSHOT_COLUMNS: &str = "timestamp, bids, asks, total_buy_quantity, total_sell_quantity";
+
+/// Latest snapshot at or before `at`.
+pub async fn at_or_before(
+    pool: &PgPool,
+    asset_id: Uuid,
+    at: NaiveDateTime,
+) -> sqlx::Result<Option<DepthSnapshot>> {
+    sqlx::query_as::<_, DepthSnapshot>(&format!(
+        "SELECT {} FROM market_depth_snapshots
+         WHERE asset_id = $1 AND timestamp <= $2
+         ORDER BY timestamp DESC LIMIT 1",
+        SNAPSHOT_COLUMNS
+    ))
+    .bind(asset_id)
+    .bind(at)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Snapshots in `[from, to]`, oldest first, at most `limit`.
+pub async fn range(
+    pool: &PgPool,
+    asset_id: Uuid,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    limit: i64,
+) -> sqlx::Result<Vec<DepthSnapshot>> {
+    sqlx::query_as::<_, DepthSnapshot>(&format!(
+        "SELECT {} FROM market_depth_snapshots
+         WHERE asset_id = $1 AND timestamp >= $2 AND timestamp <= $3
+         ORDER BY timestamp LIMIT $4",
+        SNAPSHOT_COLUMNS
+    ))
+    .bind(asset_id)
+    .bind(from)
+    .bind(to)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// The last snapshot in each `bucket_minutes` bar (epoch-aligned, like
+/// `indicators::Interval`) between `from` and `to`, i.e. the book as it
+/// stood at each bar's close. Returned as `(bar_start, snapshot)`.
+pub async fn last_per_bar(
+    pool: &PgPool,
+    asset_id: Uuid,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    bucket_minutes: i64,
+) -> sqlx::Result<Vec<(NaiveDateTime, DepthSnapshot)>> {
+    #[derive(sqlx::FromRow)]
+    struct Row {
+        bar: NaiveDateTime,
+        #[sqlx(flatten)]
+        snapshot: DepthSnapshot,
+    }
+
+    let rows = sqlx::query_as::<_, Row>(&format!(
+        "SELECT DISTINCT ON (bar) bar, {}
+         FROM (
+             SELECT *, date_bin(make_interval(mins => $4), timestamp, TIMESTAMP '1970-01-01') AS bar
+             FROM market_depth_snapshots
+             WHERE asset_id = $1 AND timestamp >= $2 AND timestamp < $3
+         ) s
+         ORDER BY bar, timestamp DESC",
+        SNAPSHOT_COLUMNS
+    ))
+    .bind(asset_id)
+    .bind(from)
+    .bind(to)
+    .bind(bucket_minutes as i32)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|r| (r.bar, r.snapshot)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(price: i64, quantity: i64) -> DepthLevel {
+        DepthLevel {
+            price: Decimal::new(price, 2),
+            quantity,
+            orders: 1,
+        }
+    }
+
+    #[test]
+    fn metrics_from_top_of_book() {
+        let bids = vec![level(10_000, 300), level(9_995, 100)];
+        let asks = vec![level(10_010, 100), level(10_020, 100)];
+        let m = metrics(&bids, &asks);
+        assert_eq!(m.best_bid, Some(Decimal::new(10_000, 2)));
+        assert_eq!(m.best_ask, Some(Decimal::new(10_010, 2)));
+        assert_eq!(m.mid, Some(Decimal::new(10_005, 2)));
+        assert_eq!(m.spread, Some(Decimal::new(10, 2)));
+        // 0.10 / 100.05 ≈ 9.995 bps
+        assert!((m.spread_bps.unwrap() - 9.995).abs() < 0.001);
+        // (400 - 200) / 600
+        assert_eq!(m.imbalance, Some(0.3333));
+    }
+
+    #[test]
+    fn one_sided_book_has_no_spread() {
+        let m = metrics(&[level(10_000, 50)], &[]);
+        assert_eq!(m.best_bid, Some(Decimal::new(10_000, 2)));
+        assert_eq!(m.spread, None);
+        assert_eq!(m.mid, None);
+        assert_eq!(m.imbalance, Some(1.0));
+        assert_eq!(metrics(&[], &[]).imbalance, None);
+    }
+
+    #[test]
+    fn clean_levels_drops_padding() {
+        let levels = clean_levels(vec![level(10_000, 10), level(0, 0), level(9_990, 0)]);
+        assert_eq!(levels, vec![level(10_000, 10)]);
+    }
+}
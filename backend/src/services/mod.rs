@@ -1,6 +1,30 @@
+pub mod asset_stats;
 pub mod contest_executor;
+pub mod crypto_market_data;
+pub mod demo_market_data;
+pub mod file_market_data;
+pub mod fx;
+pub mod gap_repair;
+pub mod indicators;
 pub mod market_data;
 pub mod market_data_ingester;
+pub mod market_depth;
+pub mod playback;
+pub mod price_history;
+pub mod price_model;
+pub mod provider_control;
+pub mod replay;
+pub mod replay_orders;
+pub mod replay_predictions;
+pub mod replay_report;
+pub mod replay_scenarios;
+pub mod retention;
 pub mod seeder;
+pub mod tick_quality;
+pub mod trading_calendar;
 
 pub use contest_executor::ContestExecutor;
+pub use gap_repair::GapRepairer;
+pub use provider_control::MarketDataControl;
+pub use retention::RetentionJob;
+pub use trading_calendar::TradingCalendar;
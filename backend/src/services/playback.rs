@@ -0,0 +1,90 @@
+//! Re-emitting historical bars as current-time candles.
+//!
+//! Shared by file playback and the demo-day provider: a timeline of bars is
+//! written to `market_prices` with its original spacing divided by `speed`,
+//! stamped from a wall-clock start. Everything downstream (contests,
+//! leaderboards, replays) sees ordinary live candles.
+
+use anyhow::Result;
+use chrono::{Duration, NaiveDateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::market_data::{f64_to_decimal, floor_minute};
+
+pub type Ohlcv = (f64, f64, f64, f64, f64);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimelineBar {
+    pub source_time: NaiveDateTime,
+    pub asset_id: Uuid,
+    pub ohlcv: Ohlcv,
+}
+
+/// Start of the next UTC minute, so played bars line up with minute candles.
+pub fn next_minute() -> NaiveDateTime {
+    floor_minute(Utc::now().naive_utc()) + Duration::minutes(1)
+}
+
+/// Wall-clock time at which a bar `source_time` after `source_start` is due.
+pub fn due_at(wall_start: NaiveDateTime, source_start: NaiveDateTime, source_time: NaiveDateTime, speed: f64) -> NaiveDateTime {
+    let offset_ms = ((source_time - source_start).num_milliseconds() as f64 / speed) as i64;
+    wall_start + Duration::milliseconds(offset_ms)
+}
+
+/// Write `timeline` (sorted by source time) as live candles from
+/// `wall_start`, sleeping until each bar is due. Returns when the last bar
+/// has been written.
+pub async fn play(pool: &PgPool, timeline: &[TimelineBar], speed: f64, wall_start: NaiveDateTime) -> Result<()> {
+    let Some(first) = timeline.first().map(|b| b.source_time) else {
+        return Ok(());
+    };
+    for bar in timeline {
+        let due = due_at(wall_start, first, bar.source_time, speed);
+        if let Ok(wait) = (due - Utc::now().naive_utc()).to_std() {
+            tokio::time::sleep(wait).await;
+        }
+        let (o, h, l, c, v) = bar.ohlcv;
+        // Faster-than-real-time playback merges several bars into one minute.
+        sqlx::query(
+            r#"
+            INSERT INTO market_prices (asset_id, timestamp, open, high, low, close, volume)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (asset_id, timestamp) DO UPDATE SET
+                high   = GREATEST(market_prices.high, EXCLUDED.high),
+                low    = LEAST(market_prices.low, EXCLUDED.low),
+                close  = EXCLUDED.close,
+                volume = market_prices.volume + EXCLUDED.volume
+            "#,
+        )
+        .bind(bar.asset_id)
+        .bind(floor_minute(due))
+        .bind(f64_to_decimal(o))
+        .bind(f64_to_decimal(h))
+        .bind(f64_to_decimal(l))
+        .bind(f64_to_decimal(c))
+        .bind(f64_to_decimal(v))
+        .execute(pool)
+        .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn due_times_scale_with_speed() {
+        let wall = utc("2026-10-18 10:00:00");
+        let source = utc("2024-03-15 03:45:00");
+        // 375 minutes of an NSE session at 6.25x take an hour.
+        assert_eq!(due_at(wall, source, source + Duration::minutes(375), 6.25), utc("2026-10-18 11:00:00"));
+        assert_eq!(due_at(wall, source, source + Duration::minutes(3), 1.0), utc("2026-10-18 10:03:00"));
+        assert_eq!(due_at(wall, source, source, 60.0), wall);
+    }
+}
@@ -0,0 +1,146 @@
+//! Resolution-aware reads of stored candles.
+//!
+//! Recent history lives in `market_prices` at 1-minute resolution; older
+//! history has been rolled into `market_prices_1h` / `market_prices_1d` by the
+//! retention job. Readers go through this module so a range that straddles the
+//! retention horizon comes back as one series, using the finest resolution
+//! still stored for each part of it.
+
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Storage tier of a candle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Minute,
+    Hour,
+    Day,
+}
+
+impl Resolution {
+    pub fn table(self) -> &'static str {
+        match self {
+            Resolution::Minute => "market_prices",
+            Resolution::Hour => "market_prices_1h",
+            Resolution::Day => "market_prices_1d",
+        }
+    }
+
+    /// `date_trunc` unit of the bucket.
+    pub fn trunc_unit(self) -> &'static str {
+        match self {
+            Resolution::Minute => "minute",
+            Resolution::Hour => "hour",
+            Resolution::Day => "day",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Candle {
+    pub timestamp: NaiveDateTime,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Option<Decimal>,
+    /// `"1m"`, `"1h"` or `"1d"`.
+    pub resolution: String,
+}
+
+/// Candles of asset `$1` in `[$2, $3]` across all tiers. Each tier only
+/// contributes buckets older than the oldest row of the next finer tier, so
+/// rollups never overlap the minute data they were built from.
+const TIERED_CANDLES: &str = r#"
+    WITH horizon AS (
+        SELECT
+            (SELECT MIN(timestamp) FROM market_prices WHERE asset_id = $1) AS minute_from,
+            (SELECT MIN(timestamp) FROM market_prices_1h WHERE asset_id = $1) AS hour_from
+    )
+    SELECT timestamp, open, high, low, close, volume, '1m' AS resolution
+    FROM market_prices
+    WHERE asset_id = $1 AND timestamp BETWEEN $2 AND $3
+    UNION ALL
+    SELECT timestamp, open, high, low, close, volume, '1h'
+    FROM market_prices_1h, horizon
+    WHERE asset_id = $1 AND timestamp BETWEEN $2 AND $3
+      AND timestamp < COALESCE(minute_from, 'infinity')
+    UNION ALL
+    SELECT timestamp, open, high, low, close, volume, '1d'
+    FROM market_prices_1d, horizon
+    WHERE asset_id = $1 AND timestamp BETWEEN $2 AND $3
+      AND timestamp < COALESCE(hour_from, minute_from, 'infinity')
+"#;
+
+/// Every candle of `asset_id` in `[from, to]`, at the finest resolution
+/// still stored for each part of the range.
+pub async fn candles(
+    pool: &PgPool,
+    asset_id: Uuid,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> sqlx::Result<Vec<Candle>> {
+    sqlx::query_as::<_, Candle>(&format!("{} ORDER BY timestamp ASC", TIERED_CANDLES))
+        .bind(asset_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await
+}
+
+/// One candle per UTC day of `asset_id` in `[from, to]`, aggregated from
+/// whatever tiers cover the range.
+pub async fn daily_candles(
+    pool: &PgPool,
+    asset_id: Uuid,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> sqlx::Result<Vec<Candle>> {
+    sqlx::query_as::<_, Candle>(&format!(
+        r#"
+        SELECT date_trunc('day', timestamp) AS timestamp,
+               (array_agg(open ORDER BY timestamp ASC))[1] AS open,
+               MAX(high) AS high,
+               MIN(low) AS low,
+               (array_agg(close ORDER BY timestamp DESC))[1] AS close,
+               SUM(volume) AS volume,
+               '1d' AS resolution
+        FROM ({}) tiers
+        GROUP BY 1
+        ORDER BY 1 ASC
+        "#,
+        TIERED_CANDLES
+    ))
+    .bind(asset_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+}
+
+/// Most recent close at or before `at`, falling back to the rollup tiers when
+/// the minute rows for that time have been pruned.
+pub async fn close_at_or_before(
+    pool: &PgPool,
+    asset_id: Uuid,
+    at: NaiveDateTime,
+) -> sqlx::Result<Option<Decimal>> {
+    for resolution in [Resolution::Minute, Resolution::Hour, Resolution::Day] {
+        let close: Option<Decimal> = sqlx::query_scalar(&format!(
+            "SELECT close FROM {} WHERE asset_id = $1 AND timestamp <= $2
+             ORDER BY timestamp DESC LIMIT 1",
+            resolution.table()
+        ))
+        .bind(asset_id)
+        .bind(at)
+        .fetch_optional(pool)
+        .await?;
+        if close.is_some() {
+            return Ok(close);
+        }
+    }
+    Ok(None)
+}
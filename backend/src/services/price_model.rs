@@ -0,0 +1,604 @@
+//! Deterministic stochastic price model behind the seed provider.
+//!
+//! Every asset follows a geometric Brownian motion with its own drift and
+//! volatility, plus volatility regimes, occasional jumps, and a shared factor
+//! that correlates assets of the same market. Parameters live in the
+//! `asset_price_models` table; everything random is derived from hashes of
+//! `(symbol, factor, time)`, so a given `(symbol, minute)` always yields the
+//! same price without storing or replaying the path.
+//!
+//! The path is built with a Brownian-bridge (Lévy) construction instead of a
+//! running sum, which keeps evaluation at O(log n) for any minute:
+//!   1. Day levels: the log price is pinned to `ln(base_price)` at
+//!      `ANCHOR_DAY` and the rest of the daily path is filled in by recursive
+//!      midpoint bisection over `±HORIZON_DAYS`.
+//!   2. Intraday: a bridge connects the level at 00:00 UTC to the next day's
+//!      level, bisected down to single minutes.
+//!   3. Jumps: a Poisson number of shocks per day gap the price at a random
+//!      minute and decay linearly back into the bridge by the end of the day.
+
+use std::f64::consts::TAU;
+
+/// Day (days since 1970-01-01) at which the log price equals `ln(base_price)`.
+/// 2026-01-01 — keeps the generated levels close to the configured base for
+/// any window the app realistically backfills.
+const ANCHOR_DAY: i64 = 20_454;
+
+/// Half-width of the day-level bridge (2^14 days ≈ 45 years). Days outside
+/// `ANCHOR_DAY ± HORIZON_DAYS` are clamped to the edge.
+const HORIZON_DAYS: i64 = 1 << 14;
+
+/// Length of a volatility regime. Must divide `HORIZON_DAYS` so regime blocks
+/// line up with bisection intervals.
+const REGIME_BLOCK_DAYS: i64 = 16;
+
+pub const MINUTES_PER_DAY: i64 = 1_440;
+const DAYS_PER_YEAR: f64 = 365.0;
+const MINUTES_PER_YEAR: f64 = DAYS_PER_YEAR * MINUTES_PER_DAY as f64;
+
+/// Upper bound on jumps drawn for a single day, so a misconfigured intensity
+/// cannot turn one evaluation into an unbounded loop.
+const MAX_JUMPS_PER_DAY: u64 = 8;
+
+// Salts keep the independent random streams from colliding on equal nonces.
+const SALT_DAY: u64 = 0x6461_795f_6c65_7631;
+const SALT_INTRADAY: u64 = 0x696e_7472_6164_6179;
+const SALT_REGIME: u64 = 0x7265_6769_6d65_5f31;
+const SALT_JUMP: u64 = 0x6a75_6d70_5f73_3031;
+const SALT_JUMP_SIZE: u64 = 0x6a75_6d70_5f73_697a;
+const SALT_CANDLE: u64 = 0x6361_6e64_6c65_5f31;
+
+/// FNV-1a 64-bit hash — stable, deterministic, no dependencies.
+pub(crate) fn hash64(input: &str) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for b in input.as_bytes() {
+        h ^= *b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    h
+}
+
+/// Mix two u64s into a uniform [0, 1) float, purely deterministic.
+pub(crate) fn rand01(seed: u64, nonce: u64) -> f64 {
+    let mut x = seed ^ nonce.wrapping_mul(0x9E3779B97F4A7C15);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51afd7ed558ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ceb9fe1a85ec53);
+    x ^= x >> 33;
+    ((x >> 11) as f64) / ((1u64 << 53) as f64)
+}
+
+/// Standard normal draw via Box–Muller over two `rand01` streams.
+fn std_normal(seed: u64, nonce: u64) -> f64 {
+    let u1 = 1.0 - rand01(seed, nonce); // (0, 1] — keeps ln() finite
+    let u2 = rand01(seed ^ 0x5bd1_e995_5bd1_e995, nonce);
+    (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos()
+}
+
+/// Fold a salt and two coordinates into one nonce.
+fn nonce(salt: u64, a: i64, b: i64) -> u64 {
+    salt ^ (a as u64).wrapping_mul(0xd6e8_feb8_6659_fd93) ^ (b as u64).rotate_left(29)
+}
+
+/// Per-asset parameters of the seed model. One row per asset in
+/// `asset_price_models`; assets without a row use [`PriceModel::fallback`].
+#[derive(sqlx::FromRow, Clone, Debug, PartialEq)]
+pub struct PriceModel {
+    /// Price level at `ANCHOR_DAY` (quote currency of the asset).
+    pub base_price: f64,
+    /// Annualized drift `μ`.
+    pub drift: f64,
+    /// Annualized volatility `σ` in the calm regime.
+    pub volatility: f64,
+    /// Volatility multiplier applied during turbulent regimes (≥ 1).
+    pub regime_multiplier: f64,
+    /// Probability that a given regime block is turbulent.
+    pub regime_probability: f64,
+    /// Expected number of jumps per day (Poisson intensity).
+    pub jumps_per_day: f64,
+    /// Mean log size of a jump.
+    pub jump_mean: f64,
+    /// Standard deviation of the log size of a jump.
+    pub jump_stddev: f64,
+    /// Name of the shared factor (e.g. `crypto`, `nse`). Assets with the same
+    /// factor are correlated and share volatility regimes.
+    pub factor: String,
+    /// Loading on the shared factor, in `[0, 1]`.
+    pub factor_correlation: f64,
+}
+
+impl PriceModel {
+    /// Conservative model for assets that have no configured row yet.
+    pub fn fallback() -> Self {
+        Self {
+            base_price: 1_000.0,
+            drift: 0.05,
+            volatility: 0.25,
+            regime_multiplier: 2.0,
+            regime_probability: 0.15,
+            jumps_per_day: 0.05,
+            jump_mean: 0.0,
+            jump_stddev: 0.02,
+            factor: "market".to_string(),
+            factor_correlation: 0.5,
+        }
+    }
+}
+
+/// Seeds of the two noise streams an asset draws from. Every Gaussian the
+/// model needs is `ρ·factor + √(1-ρ²)·idiosyncratic` at the same nonce, which
+/// is what produces cross-asset correlation.
+struct Streams {
+    idio: u64,
+    factor: u64,
+    rho: f64,
+    rho_c: f64,
+}
+
+impl Streams {
+    fn new(symbol: &str, model: &PriceModel) -> Self {
+        let rho = model.factor_correlation.clamp(0.0, 1.0);
+        Self {
+            idio: hash64(symbol),
+            factor: hash64(&model.factor),
+            rho,
+            rho_c: (1.0 - rho * rho).sqrt(),
+        }
+    }
+
+    fn normal(&self, salt: u64, a: i64, b: i64) -> f64 {
+        let n = nonce(salt, a, b);
+        self.rho * std_normal(self.factor, n) + self.rho_c * std_normal(self.idio, n)
+    }
+}
+
+/// Volatility multiplier for the regime block containing `day`. Drawn from
+/// the factor stream, so a turbulent fortnight hits the whole group at once.
+fn regime_multiplier(model: &PriceModel, streams: &Streams, day: i64) -> f64 {
+    let block = (day - ANCHOR_DAY).div_euclid(REGIME_BLOCK_DAYS);
+    if rand01(streams.factor ^ SALT_REGIME, block as u64) < model.regime_probability {
+        model.regime_multiplier.max(1.0)
+    } else {
+        1.0
+    }
+}
+
+/// Log price at 00:00 UTC of `day`.
+///
+/// The endpoints `ANCHOR_DAY ± HORIZON_DAYS` are drawn as a single GBM step
+/// from the anchor; each bisection then samples the midpoint from its exact
+/// Brownian-bridge distribution. Intervals no longer than a regime block are
+/// scaled by that block's regime multiplier.
+fn log_level_at_day(model: &PriceModel, streams: &Streams, day: i64) -> f64 {
+    let sigma = model.volatility / DAYS_PER_YEAR.sqrt();
+    let mu = (model.drift - 0.5 * model.volatility * model.volatility) / DAYS_PER_YEAR;
+    let x0 = model.base_price.ln();
+
+    let day = day.clamp(ANCHOR_DAY - HORIZON_DAYS, ANCHOR_DAY + HORIZON_DAYS);
+    if day == ANCHOR_DAY {
+        return x0;
+    }
+
+    let h = HORIZON_DAYS as f64;
+    let (mut a, mut xa, mut b, mut xb) = if day > ANCHOR_DAY {
+        let z = streams.normal(SALT_DAY, ANCHOR_DAY, 1);
+        (
+            ANCHOR_DAY,
+            x0,
+            ANCHOR_DAY + HORIZON_DAYS,
+            x0 + mu * h + sigma * h.sqrt() * z,
+        )
+    } else {
+        let z = streams.normal(SALT_DAY, ANCHOR_DAY, -1);
+        (
+            ANCHOR_DAY - HORIZON_DAYS,
+            x0 - mu * h - sigma * h.sqrt() * z,
+            ANCHOR_DAY,
+            x0,
+        )
+    };
+
+    while b - a > 1 {
+        let c = a + (b - a) / 2;
+        let mut s = sigma;
+        if b - a <= REGIME_BLOCK_DAYS {
+            s *= regime_multiplier(model, streams, a);
+        }
+        let xc = 0.5 * (xa + xb)
+            + s * ((b - a) as f64 / 4.0).sqrt() * streams.normal(SALT_DAY, c, b - a);
+        if day < c {
+            b = c;
+            xb = xc;
+        } else {
+            a = c;
+            xa = xc;
+        }
+    }
+
+    if day == a {
+        xa
+    } else {
+        xb
+    }
+}
+
+/// One UTC day of a single asset's path. Holds everything that is shared by
+/// all minutes of the day so consecutive evaluations stay cheap.
+pub struct DayCurve {
+    streams: Streams,
+    day: i64,
+    open: f64,
+    close: f64,
+    /// Per-minute log volatility for the day (regime applied).
+    sigma_minute: f64,
+    /// `(minute_of_day, log_size)` of the day's jumps.
+    jumps: Vec<(i64, f64)>,
+}
+
+impl DayCurve {
+    pub fn new(symbol: &str, model: &PriceModel, day: i64) -> Self {
+        let streams = Streams::new(symbol, model);
+        let open = log_level_at_day(model, &streams, day);
+        let close = log_level_at_day(model, &streams, day + 1);
+        let regime = regime_multiplier(model, &streams, day);
+        let sigma_minute = model.volatility * regime / MINUTES_PER_YEAR.sqrt();
+
+        // Poisson count by inverse CDF, then position and size per jump.
+        let jump_seed = streams.idio ^ SALT_JUMP;
+        let lambda = model.jumps_per_day.max(0.0);
+        let u = rand01(jump_seed, day as u64);
+        let mut p = (-lambda).exp();
+        let mut cdf = p;
+        let mut count = 0u64;
+        while u > cdf && count < MAX_JUMPS_PER_DAY {
+            count += 1;
+            p *= lambda / count as f64;
+            cdf += p;
+        }
+        let jumps = (0..count)
+            .map(|n| {
+                let key = (day as u64).wrapping_mul(MAX_JUMPS_PER_DAY + 1) + n + 1;
+                let at = 1 + (rand01(jump_seed, key) * (MINUTES_PER_DAY - 1) as f64) as i64;
+                let size = model.jump_mean
+                    + model.jump_stddev * std_normal(streams.idio ^ SALT_JUMP_SIZE, key);
+                (at, size)
+            })
+            .collect();
+
+        Self {
+            streams,
+            day,
+            open,
+            close,
+            sigma_minute,
+            jumps,
+        }
+    }
+
+    /// Price at `minute` minutes after 00:00 UTC (0..=1440).
+    pub fn price(&self, minute: i64) -> f64 {
+        let i = minute.clamp(0, MINUTES_PER_DAY);
+        self.compose(i, self.bridge(i))
+    }
+
+    /// Prices at every minute 0..=1440 of the day. Fills the whole bridge in
+    /// one pass, which is far cheaper than 1441 separate [`DayCurve::price`] calls.
+    pub fn prices(&self) -> Vec<f64> {
+        let n = MINUTES_PER_DAY as usize;
+        let mut bridge = vec![0.0; n + 1];
+        self.fill_bridge(&mut bridge, 0, MINUTES_PER_DAY);
+        bridge
+            .iter()
+            .enumerate()
+            .map(|(i, y)| self.compose(i as i64, *y))
+            .collect()
+    }
+
+    /// Trend + bridge + decaying jumps at minute `i`, as a price.
+    fn compose(&self, i: i64, bridge: f64) -> f64 {
+        let n = MINUTES_PER_DAY;
+        let t = i as f64 / n as f64;
+
+        let trend = self.open + (self.close - self.open) * t;
+        let jumps: f64 = self
+            .jumps
+            .iter()
+            .filter(|(at, _)| *at <= i)
+            .map(|(at, size)| size * (n - i) as f64 / (n - at) as f64)
+            .sum();
+
+        (trend + bridge + jumps).exp()
+    }
+
+    /// Bridge value at the midpoint `c` of `[a, b]` given its endpoints.
+    fn bridge_midpoint(&self, a: i64, b: i64, ya: f64, yb: f64) -> (i64, f64) {
+        let c = a + (b - a) / 2;
+        let w = (c - a) as f64 / (b - a) as f64;
+        let var = self.sigma_minute.powi(2) * ((c - a) * (b - c)) as f64 / (b - a) as f64;
+        let yc = ya + (yb - ya) * w + var.sqrt() * self.streams.normal(SALT_INTRADAY, self.day, c);
+        (c, yc)
+    }
+
+    /// Recursively fill `out[a+1..b]` given `out[a]` and `out[b]`.
+    fn fill_bridge(&self, out: &mut [f64], a: i64, b: i64) {
+        if b - a <= 1 {
+            return;
+        }
+        let (c, yc) = self.bridge_midpoint(a, b, out[a as usize], out[b as usize]);
+        out[c as usize] = yc;
+        self.fill_bridge(out, a, c);
+        self.fill_bridge(out, c, b);
+    }
+
+    /// Zero-pinned Brownian bridge over the day, evaluated at minute `i` by
+    /// bisecting `[0, 1440]`. Every interior minute is the midpoint of exactly
+    /// one interval, so `(day, midpoint)` is a unique key for its draw.
+    fn bridge(&self, i: i64) -> f64 {
+        let (mut a, mut b) = (0i64, MINUTES_PER_DAY);
+        let (mut ya, mut yb) = (0.0f64, 0.0f64);
+        while b - a > 1 {
+            if i == a {
+                return ya;
+            }
+            if i == b {
+                return yb;
+            }
+            let (c, yc) = self.bridge_midpoint(a, b, ya, yb);
+            if i < c {
+                b = c;
+                yb = yc;
+            } else {
+                a = c;
+                ya = yc;
+            }
+        }
+        if i == a {
+            ya
+        } else {
+            yb
+        }
+    }
+}
+
+/// Deterministic, continuous price function.
+/// Given `(symbol, model, unix_minute)` it always returns the same value.
+#[cfg(test)]
+pub fn price_at(symbol: &str, model: &PriceModel, unix_minute: i64) -> f64 {
+    let day = unix_minute.div_euclid(MINUTES_PER_DAY);
+    DayCurve::new(symbol, model, day).price(unix_minute.rem_euclid(MINUTES_PER_DAY))
+}
+
+/// OHLCV candle for a 1-minute bucket starting at `unix_minute`.
+/// Open = price at start of minute, Close = price at start of next minute,
+/// Wicks = deterministic extensions of up to one minute-sigma beyond the OC range.
+pub fn candle(symbol: &str, model: &PriceModel, unix_minute: i64) -> (f64, f64, f64, f64, f64) {
+    let day = unix_minute.div_euclid(MINUTES_PER_DAY);
+    let curve = DayCurve::new(symbol, model, day);
+    candle_on(&curve, unix_minute)
+}
+
+/// Same as [`candle`], reusing an already built curve for the minute's day.
+pub fn candle_on(curve: &DayCurve, unix_minute: i64) -> (f64, f64, f64, f64, f64) {
+    let i = unix_minute.rem_euclid(MINUTES_PER_DAY);
+    candle_between(curve, unix_minute, curve.price(i), curve.price(i + 1))
+}
+
+/// Every candle of the curve's day, in minute order, from one bridge fill.
+pub fn day_candles(curve: &DayCurve) -> Vec<(f64, f64, f64, f64, f64)> {
+    let prices = curve.prices();
+    let first = curve.day * MINUTES_PER_DAY;
+    prices
+        .windows(2)
+        .enumerate()
+        .map(|(i, w)| candle_between(curve, first + i as i64, w[0], w[1]))
+        .collect()
+}
+
+/// Wicks and volume around a known open/close pair.
+fn candle_between(curve: &DayCurve, unix_minute: i64, o: f64, c: f64) -> (f64, f64, f64, f64, f64) {
+    let seed = curve.streams.idio ^ SALT_CANDLE;
+    let wick_up = rand01(seed, unix_minute as u64 * 2) * curve.sigma_minute;
+    let wick_dn = rand01(seed, unix_minute as u64 * 2 + 1) * curve.sigma_minute;
+    let high = o.max(c) * (1.0 + wick_up);
+    let low = o.min(c) * (1.0 - wick_dn);
+
+    // Volume scales with how hard the price moved this minute.
+    let activity = 1.0 + ((c / o).ln().abs() / curve.sigma_minute.max(1e-9)).min(5.0);
+    let vol = (10_000.0 + rand01(seed, unix_minute as u64) * 90_000.0) * activity;
+    (o, high, low, c, vol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(base_price: f64, volatility: f64, factor: &str, rho: f64) -> PriceModel {
+        PriceModel {
+            base_price,
+            volatility,
+            factor: factor.to_string(),
+            factor_correlation: rho,
+            ..PriceModel::fallback()
+        }
+    }
+
+    fn minute_returns(symbol: &str, m: &PriceModel, from: i64, n: i64) -> Vec<f64> {
+        (from..from + n)
+            .map(|t| (price_at(symbol, m, t + 1) / price_at(symbol, m, t)).ln())
+            .collect()
+    }
+
+    #[test]
+    fn rand01_is_in_unit_interval() {
+        for i in 0..10_000u64 {
+            let r = rand01(0xdead_beef, i);
+            assert!((0.0..1.0).contains(&r), "rand01 out of range: {}", r);
+        }
+    }
+
+    #[test]
+    fn base_price_is_positive_for_known_symbols() {
+        assert!(PriceModel::fallback().base_price > 0.0);
+        let models = [
+            ("BTC", model(65_000.0, 0.6, "crypto", 0.8)),
+            ("ETH", model(3_500.0, 0.7, "crypto", 0.8)),
+            ("RELIANCE", model(2_800.0, 0.28, "nse", 0.65)),
+            ("TCS", model(3_800.0, 0.22, "nse", 0.6)),
+            ("NIFTYBEES", model(250.0, 0.14, "nse", 0.95)),
+        ];
+        for (sym, m) in &models {
+            for d in (ANCHOR_DAY - 365..ANCHOR_DAY + 365).step_by(7) {
+                let p = price_at(sym, m, d * MINUTES_PER_DAY);
+                assert!(p > 0.0, "price must be > 0 for {} on day {}", sym, d);
+            }
+        }
+    }
+
+    #[test]
+    fn price_at_is_deterministic() {
+        let m = model(65_000.0, 0.6, "crypto", 0.8);
+        let a = price_at("BTC", &m, 29_000_000);
+        let b = price_at("BTC", &m, 29_000_000);
+        assert_eq!(a, b, "price_at must be deterministic for same inputs");
+    }
+
+    #[test]
+    fn price_at_differs_across_symbols_and_time() {
+        let btc = price_at("BTC", &model(65_000.0, 0.6, "crypto", 0.8), 29_000_000);
+        let eth = price_at("ETH", &model(3_500.0, 0.7, "crypto", 0.8), 29_000_000);
+        assert!((btc - eth).abs() > 1.0, "BTC/ETH prices must diverge");
+        let next = price_at("BTC", &model(65_000.0, 0.6, "crypto", 0.8), 29_000_005);
+        assert!(btc != next, "Price must evolve across minutes");
+    }
+
+    #[test]
+    fn price_is_anchored_at_base_price() {
+        let m = model(2_800.0, 0.3, "nse", 0.6);
+        let p = price_at("RELIANCE", &m, ANCHOR_DAY * MINUTES_PER_DAY);
+        assert!((p - 2_800.0).abs() < 1e-6, "anchor price drifted: {p}");
+    }
+
+    #[test]
+    fn path_is_continuous_across_midnight() {
+        let m = model(65_000.0, 0.6, "crypto", 0.8);
+        let day = ANCHOR_DAY + 200;
+        let end_of_day = DayCurve::new("BTC", &m, day).price(MINUTES_PER_DAY);
+        let next_open = DayCurve::new("BTC", &m, day + 1).price(0);
+        assert!((end_of_day - next_open).abs() < 1e-9);
+    }
+
+    #[test]
+    fn bulk_day_candles_match_single_evaluation() {
+        let m = model(3_500.0, 0.65, "crypto", 0.85);
+        let day = ANCHOR_DAY + 42;
+        let curve = DayCurve::new("ETH", &m, day);
+        let bulk = day_candles(&curve);
+        assert_eq!(bulk.len(), MINUTES_PER_DAY as usize);
+        for (i, c) in bulk.iter().enumerate() {
+            let single = candle("ETH", &m, day * MINUTES_PER_DAY + i as i64);
+            assert!((c.0 - single.0).abs() < 1e-9 && (c.3 - single.3).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn levels_stay_plausible_within_a_year() {
+        let m = model(22_000.0, 0.15, "nse", 0.9);
+        for d in (ANCHOR_DAY - 365..ANCHOR_DAY + 365).step_by(7) {
+            let p = price_at("NIFTY50", &m, d * MINUTES_PER_DAY);
+            assert!(
+                (11_000.0..44_000.0).contains(&p),
+                "day {d}: NIFTY50 at {p} is implausible"
+            );
+        }
+    }
+
+    #[test]
+    fn assets_sharing_a_factor_are_correlated() {
+        let start = (ANCHOR_DAY + 30) * MINUTES_PER_DAY;
+        let a = minute_returns("NIFTY50", &model(22_000.0, 0.15, "nse", 0.95), start, 2_000);
+        let b = minute_returns("NIFTYBEES", &model(250.0, 0.15, "nse", 0.95), start, 2_000);
+        let c = minute_returns("BTC", &model(65_000.0, 0.6, "crypto", 0.95), start, 2_000);
+
+        let corr = |x: &[f64], y: &[f64]| {
+            let n = x.len() as f64;
+            let (mx, my) = (x.iter().sum::<f64>() / n, y.iter().sum::<f64>() / n);
+            let cov: f64 = x.iter().zip(y).map(|(a, b)| (a - mx) * (b - my)).sum();
+            let vx: f64 = x.iter().map(|a| (a - mx).powi(2)).sum();
+            let vy: f64 = y.iter().map(|b| (b - my).powi(2)).sum();
+            cov / (vx * vy).sqrt()
+        };
+
+        assert!(corr(&a, &b) > 0.7, "same-factor correlation too low");
+        assert!(
+            corr(&a, &c).abs() < 0.2,
+            "cross-factor correlation too high"
+        );
+    }
+
+    #[test]
+    fn jumps_show_up_when_configured() {
+        let calm = model(150.0, 0.5, "crypto", 0.0);
+        let jumpy = PriceModel {
+            jumps_per_day: 4.0,
+            jump_stddev: 0.05,
+            ..calm.clone()
+        };
+        let days = ANCHOR_DAY + 10..ANCHOR_DAY + 20;
+        let count = |m: &PriceModel| -> usize {
+            days.clone()
+                .map(|d| DayCurve::new("SOL", m, d).jumps.len())
+                .sum()
+        };
+        assert_eq!(
+            count(&PriceModel {
+                jumps_per_day: 0.0,
+                ..calm
+            }),
+            0
+        );
+        assert!(count(&jumpy) > 10, "expected ~40 jumps over 10 days");
+    }
+
+    #[test]
+    fn candle_ohlc_invariants_hold() {
+        // For 5000 minutes across a few symbols, high >= max(o,c),
+        // low <= min(o,c), volume > 0, and candles are fully deterministic.
+        let models = [
+            ("BTC", model(65_000.0, 0.6, "crypto", 0.8)),
+            ("ETH", model(3_500.0, 0.7, "crypto", 0.8)),
+            ("RELIANCE", model(2_800.0, 0.28, "nse", 0.65)),
+        ];
+        for minute in 28_000_000i64..28_005_000 {
+            for (sym, m) in &models {
+                let (o, h, l, c, v) = candle(sym, m, minute);
+                assert!(h >= o.max(c) - 1e-9, "high < max(o,c): {} {}", sym, minute);
+                assert!(l <= o.min(c) + 1e-9, "low > min(o,c): {} {}", sym, minute);
+                assert!(v > 0.0, "volume must be positive");
+                // determinism
+                let again = candle(sym, m, minute);
+                assert_eq!((o, h, l, c, v), again, "candle must be deterministic");
+            }
+        }
+    }
+
+    #[test]
+    fn wicks_are_tight_enough_to_be_realistic() {
+        // Wicks extend at most one minute-sigma beyond the body, which keeps
+        // the synthetic series visually plausible even in turbulent regimes.
+        let m = model(65_000.0, 0.6, "crypto", 0.8);
+        for minute in 28_000_000i64..28_001_000 {
+            let (o, h, l, c, _) = candle("BTC", &m, minute);
+            let reference = o.max(c);
+            assert!(
+                (h - reference) / reference < 0.01 && (o.min(c) - l) / reference < 0.01,
+                "minute {minute}: wick too long ({h} / {l})"
+            );
+            let span = h - l;
+            assert!(
+                span / reference < 0.02,
+                "minute {minute}: candle too wide ({span})"
+            );
+        }
+    }
+}
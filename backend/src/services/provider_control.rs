@@ -0,0 +1,329 @@
+//! Runtime control of the market-data provider.
+//!
+//! `market_data::build` still decides what runs, but through
+//! [`MarketDataControl`], which admins can ask to rebuild it: switch between
+//! seed and live, or restart the Kite stream with a rotated access token.
+//! Gap repair and the admin API hold the control, so they follow every
+//! switch. Rotated tokens live in `kite_access_tokens` and are preferred over
+//! `KITE_ACCESS_TOKEN` until Kite expires them, so a restart keeps them.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDateTime, NaiveTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::sync::{Arc, RwLock};
+use tracing::{error, info, warn};
+
+use crate::config::{AppConfig, MarketDataMode};
+use crate::services::market_data::{self, MarketDataProvider, RangeFill, SeedAsset, StreamReport};
+use crate::services::TradingCalendar;
+
+/// Kite invalidates every access token at 06:00 IST (00:30 UTC).
+fn kite_expiry_time() -> NaiveTime {
+    NaiveTime::from_hms_opt(0, 30, 0).expect("valid time")
+}
+
+/// Whether a token issued at `issued_at` has passed a daily Kite expiry by `now`.
+pub fn kite_token_expired(issued_at: NaiveDateTime, now: NaiveDateTime) -> bool {
+    let today = now.date().and_time(kite_expiry_time());
+    let last_expiry = if now >= today { today } else { today - Duration::days(1) };
+    issued_at < last_expiry
+}
+
+/// Where the Kite token in use came from.
+#[derive(Debug, Clone, Serialize)]
+pub struct KiteTokenInfo {
+    /// `env` (KITE_ACCESS_TOKEN) or `admin` (rotated in through the API).
+    pub source: &'static str,
+    pub kite_user_id: Option<String>,
+    pub created_by: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    #[serde(skip)]
+    access_token: String,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct StoredToken {
+    access_token: String,
+    kite_user_id: Option<String>,
+    created_by: String,
+    created_at: NaiveDateTime,
+}
+
+/// Newest stored token that Kite has not expired yet.
+async fn stored_token(pool: &PgPool) -> Result<Option<KiteTokenInfo>> {
+    let token = sqlx::query_as::<_, StoredToken>(
+        "SELECT access_token, kite_user_id, created_by, created_at
+         FROM kite_access_tokens ORDER BY created_at DESC LIMIT 1",
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(token
+        .filter(|t| !kite_token_expired(t.created_at, Utc::now().naive_utc()))
+        .map(|t| KiteTokenInfo {
+            source: "admin",
+            kite_user_id: t.kite_user_id,
+            created_by: Some(t.created_by),
+            created_at: Some(t.created_at),
+            access_token: t.access_token,
+        }))
+}
+
+/// Snapshot for the admin API.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderStatus {
+    /// Mode actually running (live falls back to seed without a token).
+    pub mode: &'static str,
+    pub requested_mode: &'static str,
+    pub label: &'static str,
+    pub since: NaiveDateTime,
+    pub kite_configured: bool,
+    /// Whether a `request_token` can be exchanged (KITE_API_SECRET set).
+    pub kite_login_enabled: bool,
+    pub kite_token: Option<KiteTokenInfo>,
+    pub streams: Vec<StreamReport>,
+}
+
+#[derive(Clone)]
+struct Active {
+    provider: Arc<dyn MarketDataProvider>,
+    mode: MarketDataMode,
+    requested: MarketDataMode,
+    since: NaiveDateTime,
+}
+
+pub struct MarketDataControl {
+    pool: PgPool,
+    config: AppConfig,
+    calendar: Arc<TradingCalendar>,
+    active: RwLock<Active>,
+    kite_token: RwLock<Option<KiteTokenInfo>>,
+    /// Serialises switches so two admins can't start two providers.
+    switching: tokio::sync::Mutex<()>,
+}
+
+impl MarketDataControl {
+    /// Build (but don't start) the provider `config` asks for, using the
+    /// newest stored Kite token if there is one.
+    pub async fn new(pool: PgPool, config: AppConfig, calendar: Arc<TradingCalendar>) -> Result<Self> {
+        let kite_token = match stored_token(&pool).await? {
+            Some(token) => Some(token),
+            None => config.kite.as_ref().and_then(|k| k.access_token.clone()).map(|access_token| KiteTokenInfo {
+                source: "env",
+                kite_user_id: None,
+                created_by: None,
+                created_at: None,
+                access_token,
+            }),
+        };
+        let requested = config.market_data_mode;
+        let (provider, mode) = build(&pool, &config, &calendar, requested, kite_token.as_ref());
+        Ok(Self {
+            active: RwLock::new(Active {
+                provider,
+                mode,
+                requested,
+                since: Utc::now().naive_utc(),
+            }),
+            kite_token: RwLock::new(kite_token),
+            switching: tokio::sync::Mutex::new(()),
+            pool,
+            config,
+            calendar,
+        })
+    }
+
+    fn active(&self) -> Active {
+        self.active.read().expect("provider lock poisoned").clone()
+    }
+
+    pub fn mode(&self) -> MarketDataMode {
+        self.active().mode
+    }
+
+    pub fn kite_configured(&self) -> bool {
+        self.config.kite.is_some()
+    }
+
+    pub fn has_kite_token(&self) -> bool {
+        self.valid_kite_token().is_some()
+    }
+
+    /// The cached token, unless Kite has expired it since it was loaded. An
+    /// env token's issue time is unknown, so it is trusted.
+    fn valid_kite_token(&self) -> Option<KiteTokenInfo> {
+        let now = Utc::now().naive_utc();
+        self.kite_token
+            .read()
+            .expect("kite token lock poisoned")
+            .clone()
+            .filter(|t| t.created_at.is_none_or(|at| !kite_token_expired(at, now)))
+    }
+
+    pub fn status(&self) -> ProviderStatus {
+        let active = self.active();
+        ProviderStatus {
+            mode: active.mode.as_str(),
+            requested_mode: active.requested.as_str(),
+            label: active.provider.label(),
+            since: active.since,
+            kite_configured: self.kite_configured(),
+            kite_login_enabled: self.config.kite.as_ref().is_some_and(|k| k.api_secret.is_some()),
+            kite_token: self.valid_kite_token(),
+            streams: active.provider.streams(),
+        }
+    }
+
+    /// Stop the running provider and start the one `mode` asks for. If the
+    /// new one fails to start, the previous one is started again and the
+    /// start error returned.
+    pub async fn switch(&self, mode: MarketDataMode) -> Result<MarketDataMode> {
+        let _guard = self.switching.lock().await;
+        let token = self.valid_kite_token();
+        let (provider, effective) = build(&self.pool, &self.config, &self.calendar, mode, token.as_ref());
+
+        let previous = {
+            let mut active = self.active.write().expect("provider lock poisoned");
+            std::mem::replace(
+                &mut *active,
+                Active {
+                    provider: provider.clone(),
+                    mode: effective,
+                    requested: mode,
+                    since: Utc::now().naive_utc(),
+                },
+            )
+        };
+        previous.provider.stop();
+        if let Err(e) = provider.clone().start().await {
+            provider.stop();
+            *self.active.write().expect("provider lock poisoned") = previous.clone();
+            warn!(
+                "Market-data provider {} failed to start: {:#}; restarting {}",
+                effective.as_str(),
+                e,
+                previous.mode.as_str()
+            );
+            if let Err(restore) = previous.provider.clone().start().await {
+                error!(
+                    "Market-data provider {} failed to restart: {:#}",
+                    previous.mode.as_str(),
+                    restore
+                );
+            }
+            return Err(e);
+        }
+        info!(
+            "Market-data provider switched from {} to {} ({})",
+            previous.mode.as_str(),
+            effective.as_str(),
+            provider.label()
+        );
+        Ok(effective)
+    }
+
+    /// Store a new Kite access token and, if live mode is requested, restart
+    /// the stream with it.
+    pub async fn rotate_kite_token(
+        &self,
+        access_token: String,
+        kite_user_id: Option<String>,
+        source: &str,
+        admin_email: &str,
+    ) -> Result<()> {
+        let created_at: NaiveDateTime = sqlx::query_scalar(
+            "INSERT INTO kite_access_tokens (access_token, kite_user_id, source, created_by)
+             VALUES ($1, $2, $3, $4)
+             RETURNING created_at",
+        )
+        .bind(&access_token)
+        .bind(&kite_user_id)
+        .bind(source)
+        .bind(admin_email)
+        .fetch_one(&self.pool)
+        .await?;
+
+        *self.kite_token.write().expect("kite token lock poisoned") = Some(KiteTokenInfo {
+            source: "admin",
+            kite_user_id,
+            created_by: Some(admin_email.to_string()),
+            created_at: Some(created_at),
+            access_token,
+        });
+        if self.active().requested == MarketDataMode::Live {
+            self.switch(MarketDataMode::Live).await?;
+        }
+        Ok(())
+    }
+}
+
+/// `market_data::build` with `mode` and `token` in place of the boot config.
+fn build(
+    pool: &PgPool,
+    config: &AppConfig,
+    calendar: &Arc<TradingCalendar>,
+    mode: MarketDataMode,
+    token: Option<&KiteTokenInfo>,
+) -> (Arc<dyn MarketDataProvider>, MarketDataMode) {
+    let mut config = config.clone();
+    config.market_data_mode = mode;
+    if let Some(kite) = config.kite.as_mut() {
+        kite.access_token = token.map(|t| t.access_token.clone());
+    }
+    market_data::build(pool.clone(), &config, calendar.clone())
+}
+
+#[async_trait]
+impl MarketDataProvider for MarketDataControl {
+    fn label(&self) -> &'static str {
+        self.active().provider.label()
+    }
+
+    async fn start(self: Arc<Self>) -> Result<()> {
+        self.active().provider.start().await
+    }
+
+    fn stop(&self) {
+        self.active().provider.stop();
+    }
+
+    fn streams(&self) -> Vec<StreamReport> {
+        self.active().provider.streams()
+    }
+
+    async fn fill_range(
+        &self,
+        asset: &SeedAsset,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<RangeFill> {
+        self.active().provider.fill_range(asset, start, end).await
+    }
+
+    fn refresh_subscriptions(&self) {
+        self.active().provider.refresh_subscriptions();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn kite_tokens_expire_at_six_ist() {
+        // Issued 09:00 IST on the 16th, valid until 06:00 IST on the 17th.
+        let issued = utc("2026-10-16 03:30:00");
+        assert!(!kite_token_expired(issued, utc("2026-10-16 23:59:00")));
+        assert!(!kite_token_expired(issued, utc("2026-10-17 00:29:59")));
+        assert!(kite_token_expired(issued, utc("2026-10-17 00:30:00")));
+        // Issued after midnight UTC but before 06:00 IST: expires that morning.
+        let early = utc("2026-10-17 00:10:00");
+        assert!(kite_token_expired(early, utc("2026-10-17 00:31:00")));
+        assert!(!kite_token_expired(utc("2026-10-17 00:30:00"), utc("2026-10-17 12:00:00")));
+    }
+}
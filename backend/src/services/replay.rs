@@ -0,0 +1,773 @@
+//! Replay playback state.
+//!
+//! A replay streams a fixed series of bars. [`ReplayPlayer`] tracks which bar
+//! the student is on and how fast the stream moves; the WebSocket handler
+//! feeds it client commands and its own timer, and sends whatever bars the
+//! player says are due. The bar last sent is saved on the session as its
+//! cursor, which is where paper trades fill.
+//!
+//! A session may cover several assets (sharing one quote currency) on one
+//! clock: the cursor is a time, and each asset is priced at its last close
+//! at or before it.
+//!
+//! Bar-by-bar sessions are turn-based: nothing plays on its own, each
+//! `step` reveals the next bar, and like scenario sessions they can't seek.
+//!
+//! Scenario sessions play forward only: they can't seek back before the
+//! furthest bar sent, so their returns can be ranked.
+//!
+//! Every session resumes where the last connection left off: the cursor,
+//! pause state and speed are saved as they change, and a reconnecting
+//! client says which bar it saw last so the bars it missed are sent again.
+//!
+//! Each session is also a paper-trading account: it starts with cash, and
+//! fills move cash and average-cost positions (long only). The portfolio is
+//! marked at the cursor.
+
+use chrono::{DateTime, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashMap;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Playback speed limits; 1x is one bar per second.
+pub const MIN_SPEED: f64 = 0.5;
+pub const MAX_SPEED: f64 = 60.0;
+
+/// Most bars a single `step` may send.
+const MAX_STEP: usize = 500;
+
+/// Control message from the client, e.g. `{"action": "seek", "timestamp": "..."}`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ReplayCommand {
+    Play,
+    Pause,
+    /// Send the next `count` bars (default 1) and pause.
+    Step {
+        #[serde(default = "one")]
+        count: usize,
+    },
+    /// Jump to the last bar at or before `timestamp` (RFC 3339) and send it.
+    Seek { timestamp: String },
+    /// Playback speed multiplier, `MIN_SPEED..=MAX_SPEED`.
+    Speed { value: f64 },
+}
+
+fn one() -> usize {
+    1
+}
+
+impl ReplayCommand {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReplayCommand::Play => "play",
+            ReplayCommand::Pause => "pause",
+            ReplayCommand::Step { .. } => "step",
+            ReplayCommand::Seek { .. } => "seek",
+            ReplayCommand::Speed { .. } => "speed",
+        }
+    }
+}
+
+/// Where playback stands, sent with every acknowledgement.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReplayCursor {
+    /// Index and time of the last bar sent; `None` before the first.
+    pub index: Option<usize>,
+    pub timestamp: Option<NaiveDateTime>,
+    pub total: usize,
+    pub playing: bool,
+    pub speed: f64,
+    /// Every bar has been sent; seek back to watch again.
+    pub finished: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayPlayer {
+    timestamps: Vec<NaiveDateTime>,
+    /// Index of the next bar to send.
+    next: usize,
+    playing: bool,
+    speed: f64,
+    /// Furthest bar sent, when seeking back is not allowed.
+    furthest: Option<usize>,
+    /// Bars are revealed only by `step`.
+    turn_based: bool,
+}
+
+impl ReplayPlayer {
+    /// A player over bars at `timestamps` (ascending), playing at 1x.
+    pub fn new(timestamps: Vec<NaiveDateTime>) -> Self {
+        Self {
+            playing: !timestamps.is_empty(),
+            timestamps,
+            next: 0,
+            speed: 1.0,
+            furthest: None,
+            turn_based: false,
+        }
+    }
+
+    /// A paused, forward-only player that reveals bars only on `step`.
+    pub fn turn_based(mut self) -> Self {
+        self.playing = false;
+        self.turn_based = true;
+        self.forward_only()
+    }
+
+    /// A player that can't seek back before the furthest bar sent.
+    pub fn forward_only(mut self) -> Self {
+        self.furthest = Some(self.next.saturating_sub(1));
+        self
+    }
+
+    /// Restore a session's saved pause state and speed.
+    pub fn restore(mut self, paused: bool, speed: f64) -> Self {
+        self.playing &= !paused;
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        self
+    }
+
+    /// Pick up after the bar at or before `cursor`, the session's saved
+    /// cursor. Returns the bars to send again: those after `seen` (the last
+    /// bar the client received) up to the cursor, or only the cursor's bar
+    /// when the client doesn't say.
+    pub fn resume(&mut self, cursor: Option<NaiveDateTime>, seen: Option<NaiveDateTime>) -> Vec<usize> {
+        let end = match cursor {
+            Some(cursor) => self.timestamps.partition_point(|t| *t <= cursor),
+            None => 0,
+        };
+        if end == 0 {
+            return Vec::new();
+        }
+        let from = match seen {
+            Some(seen) => self.timestamps.partition_point(|t| *t <= seen).min(end),
+            None => end - 1,
+        };
+        self.next = end;
+        self.reached(end - 1);
+        if self.next >= self.timestamps.len() {
+            self.playing = false;
+        }
+        (from..end).collect()
+    }
+
+    fn reached(&mut self, index: usize) {
+        if let Some(furthest) = self.furthest.as_mut() {
+            *furthest = (*furthest).max(index);
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Wall-clock time between bars at the current speed.
+    pub fn delay(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.speed)
+    }
+
+    pub fn cursor(&self) -> ReplayCursor {
+        let index = self.next.checked_sub(1);
+        ReplayCursor {
+            index,
+            timestamp: index.map(|i| self.timestamps[i]),
+            total: self.timestamps.len(),
+            playing: self.playing,
+            speed: self.speed,
+            finished: self.next >= self.timestamps.len(),
+        }
+    }
+
+    /// The next bar, when the playback timer fires. Playback pauses itself
+    /// after the last bar.
+    pub fn advance(&mut self) -> Option<usize> {
+        if !self.playing {
+            return None;
+        }
+        let index = (self.next < self.timestamps.len()).then_some(self.next);
+        if let Some(index) = index {
+            self.next += 1;
+            self.reached(index);
+        }
+        if self.next >= self.timestamps.len() {
+            self.playing = false;
+        }
+        index
+    }
+
+    /// Apply a client command. Returns the bars to send right away.
+    pub fn apply(&mut self, command: &ReplayCommand) -> Result<Vec<usize>, String> {
+        if self.turn_based && matches!(command, ReplayCommand::Play | ReplayCommand::Seek { .. }) {
+            return Err("Bar-by-bar replays move with step".to_string());
+        }
+        match command {
+            ReplayCommand::Play => {
+                if self.next >= self.timestamps.len() {
+                    return Err("Replay finished; seek back to play again".to_string());
+                }
+                self.playing = true;
+                Ok(Vec::new())
+            }
+            ReplayCommand::Pause => {
+                self.playing = false;
+                Ok(Vec::new())
+            }
+            ReplayCommand::Step { count } => {
+                if *count == 0 || *count > MAX_STEP {
+                    return Err(format!("count must be between 1 and {}", MAX_STEP));
+                }
+                self.playing = false;
+                let end = (self.next + count).min(self.timestamps.len());
+                let bars = (self.next..end).collect();
+                self.next = end;
+                self.reached(end.saturating_sub(1));
+                Ok(bars)
+            }
+            ReplayCommand::Seek { timestamp } => {
+                let target = DateTime::parse_from_rfc3339(timestamp)
+                    .map_err(|_| "timestamp must be RFC 3339".to_string())?
+                    .naive_utc();
+                match (self.timestamps.first(), self.timestamps.last()) {
+                    (Some(first), Some(last)) if *first <= target && target <= *last => {}
+                    _ => return Err("timestamp is outside the replay".to_string()),
+                }
+                let index = self.timestamps.partition_point(|t| *t <= target) - 1;
+                if self.furthest.is_some_and(|furthest| index < furthest) {
+                    return Err("This replay plays forward only".to_string());
+                }
+                self.next = index + 1;
+                self.reached(index);
+                if self.next >= self.timestamps.len() {
+                    self.playing = false;
+                }
+                Ok(vec![index])
+            }
+            ReplayCommand::Speed { value } => {
+                if !(MIN_SPEED..=MAX_SPEED).contains(value) {
+                    return Err(format!("speed must be between {} and {}", MIN_SPEED, MAX_SPEED));
+                }
+                self.speed = *value;
+                Ok(Vec::new())
+            }
+        }
+    }
+}
+
+/// Cash a replay starts with unless the request sets it, in the assets' currency.
+pub const DEFAULT_STARTING_CASH: i64 = 100_000;
+
+/// Most assets one replay session can cover.
+pub const MAX_ASSETS: usize = 10;
+
+/// How a session's bars are revealed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayMode {
+    /// Timed playback, with play/pause/seek/speed.
+    Stream,
+    /// One bar per `step`, with next-bar predictions.
+    BarByBar,
+}
+
+impl ReplayMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "stream" => Some(ReplayMode::Stream),
+            "bar_by_bar" => Some(ReplayMode::BarByBar),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReplayMode::Stream => "stream",
+            ReplayMode::BarByBar => "bar_by_bar",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+impl TradeSide {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "buy" => Some(TradeSide::Buy),
+            "sell" => Some(TradeSide::Sell),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TradeSide::Buy => "buy",
+            TradeSide::Sell => "sell",
+        }
+    }
+}
+
+/// One asset's position in a replay account.
+#[derive(Debug, Clone, Copy, Default, PartialEq, sqlx::FromRow)]
+pub struct Holding {
+    pub quantity: Decimal,
+    pub avg_cost: Decimal,
+    pub realized_pnl: Decimal,
+}
+
+impl Holding {
+    /// Apply a fill of `quantity` at `price`, moving `cash`. Buys may not
+    /// spend more than `cash` and sells may not exceed the position; on
+    /// error nothing changes.
+    pub fn fill(
+        &mut self,
+        side: TradeSide,
+        quantity: Decimal,
+        price: Decimal,
+        cash: &mut Decimal,
+    ) -> Result<(), String> {
+        let notional = (quantity * price).round_dp(4);
+        match side {
+            TradeSide::Buy => {
+                if notional > *cash {
+                    return Err(format!(
+                        "Insufficient cash: need {}, have {}",
+                        notional.normalize(),
+                        cash.normalize()
+                    ));
+                }
+                let held = self.quantity + quantity;
+                self.avg_cost = ((self.quantity * self.avg_cost + quantity * price) / held).round_dp(8);
+                self.quantity = held;
+                *cash -= notional;
+            }
+            TradeSide::Sell => {
+                if quantity > self.quantity {
+                    // No figures: this ends up as an order's reject reason,
+                    // which a blind replay shows as is
+                    return Err("Cannot sell more than the position".to_string());
+                }
+                self.realized_pnl += ((price - self.avg_cost) * quantity).round_dp(4);
+                self.quantity -= quantity;
+                if self.quantity.is_zero() {
+                    self.avg_cost = Decimal::ZERO;
+                }
+                *cash += notional;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionValue {
+    pub asset_id: Uuid,
+    pub quantity: Decimal,
+    pub avg_cost: Decimal,
+    /// Mark price; `None` (valued at cost) before the replay has a bar for it.
+    pub price: Option<Decimal>,
+    pub market_value: Decimal,
+    pub unrealized_pnl: Decimal,
+    pub realized_pnl: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayPortfolio {
+    /// Replay time the portfolio is marked at.
+    pub timestamp: Option<NaiveDateTime>,
+    pub starting_cash: Decimal,
+    pub cash: Decimal,
+    pub positions: Vec<PositionValue>,
+    pub market_value: Decimal,
+    pub equity: Decimal,
+    pub realized_pnl: Decimal,
+    pub unrealized_pnl: Decimal,
+    /// Equity less starting cash.
+    pub total_pnl: Decimal,
+}
+
+/// A fill to book against a replay account; `order_id` is set when a resting
+/// order filled.
+#[derive(Debug, Clone)]
+pub struct Fill {
+    pub replay_id: Uuid,
+    pub asset_id: Uuid,
+    pub order_id: Option<Uuid>,
+    pub side: TradeSide,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    /// Replay time of the bar it filled on.
+    pub timestamp: NaiveDateTime,
+}
+
+/// The account's position in `asset_id` (empty if it never traded it).
+pub async fn holding(
+    tx: &mut Transaction<'_, Postgres>,
+    replay_id: Uuid,
+    asset_id: Uuid,
+) -> sqlx::Result<Holding> {
+    Ok(sqlx::query_as::<_, Holding>(
+        "SELECT quantity, avg_cost, realized_pnl
+         FROM replay_positions WHERE replay_id = $1 AND asset_id = $2",
+    )
+    .bind(replay_id)
+    .bind(asset_id)
+    .fetch_optional(&mut **tx)
+    .await?
+    .unwrap_or_default())
+}
+
+/// Persist `fill` with the cash and position [`Holding::fill`] left behind.
+/// The caller holds the session row lock.
+pub async fn record_fill(
+    tx: &mut Transaction<'_, Postgres>,
+    fill: &Fill,
+    cash: Decimal,
+    holding: &Holding,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        "INSERT INTO replay_positions (replay_id, asset_id, quantity, avg_cost, realized_pnl)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (replay_id, asset_id) DO UPDATE
+         SET quantity = EXCLUDED.quantity, avg_cost = EXCLUDED.avg_cost,
+             realized_pnl = EXCLUDED.realized_pnl",
+    )
+    .bind(fill.replay_id)
+    .bind(fill.asset_id)
+    .bind(holding.quantity)
+    .bind(holding.avg_cost)
+    .bind(holding.realized_pnl)
+    .execute(&mut **tx)
+    .await?;
+
+    sqlx::query("UPDATE replay_sessions SET cash = $2 WHERE id = $1")
+        .bind(fill.replay_id)
+        .bind(cash)
+        .execute(&mut **tx)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO replay_trades (replay_id, asset_id, order_id, side, price, quantity, timestamp)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(fill.replay_id)
+    .bind(fill.asset_id)
+    .bind(fill.order_id)
+    .bind(fill.side.as_str())
+    .bind(fill.price)
+    .bind(fill.quantity)
+    .bind(fill.timestamp)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+/// Mark `holdings` at `prices`; positions without a price are valued at cost.
+/// Closed positions still report their realized P&L.
+pub fn value_portfolio(
+    timestamp: Option<NaiveDateTime>,
+    starting_cash: Decimal,
+    cash: Decimal,
+    holdings: &[(Uuid, Holding)],
+    prices: &HashMap<Uuid, Decimal>,
+) -> ReplayPortfolio {
+    let positions: Vec<PositionValue> = holdings
+        .iter()
+        .map(|(asset_id, h)| {
+            let price = prices.get(asset_id).copied();
+            let market_value = (h.quantity * price.unwrap_or(h.avg_cost)).round_dp(4);
+            PositionValue {
+                asset_id: *asset_id,
+                quantity: h.quantity,
+                avg_cost: h.avg_cost,
+                price,
+                market_value,
+                unrealized_pnl: market_value - (h.quantity * h.avg_cost).round_dp(4),
+                realized_pnl: h.realized_pnl,
+            }
+        })
+        .collect();
+    let market_value: Decimal = positions.iter().map(|p| p.market_value).sum();
+    let equity = cash + market_value;
+    ReplayPortfolio {
+        timestamp,
+        starting_cash,
+        cash,
+        market_value,
+        equity,
+        realized_pnl: positions.iter().map(|p| p.realized_pnl).sum(),
+        unrealized_pnl: positions.iter().map(|p| p.unrealized_pnl).sum(),
+        total_pnl: equity - starting_cash,
+        positions,
+    }
+}
+
+/// A session's account, marked at its cursor. `None` if the session doesn't exist.
+pub async fn load_portfolio(pool: &PgPool, replay_id: Uuid) -> sqlx::Result<Option<ReplayPortfolio>> {
+    #[derive(sqlx::FromRow)]
+    struct Account {
+        starting_cash: Decimal,
+        cash: Decimal,
+        cursor_time: Option<NaiveDateTime>,
+    }
+
+    #[derive(sqlx::FromRow)]
+    struct PositionRow {
+        asset_id: Uuid,
+        #[sqlx(flatten)]
+        holding: Holding,
+    }
+
+    let Some(account) = sqlx::query_as::<_, Account>(
+        "SELECT starting_cash, cash, cursor_time FROM replay_sessions WHERE id = $1",
+    )
+    .bind(replay_id)
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let holdings: Vec<(Uuid, Holding)> = sqlx::query_as::<_, PositionRow>(
+        "SELECT asset_id, quantity, avg_cost, realized_pnl
+         FROM replay_positions WHERE replay_id = $1 ORDER BY asset_id",
+    )
+    .bind(replay_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|p| (p.asset_id, p.holding))
+    .collect();
+
+    let prices: Vec<(Uuid, Decimal)> = sqlx::query_as(
+        "SELECT asset_id, cursor_price FROM replay_session_assets
+         WHERE replay_id = $1 AND cursor_price IS NOT NULL",
+    )
+    .bind(replay_id)
+    .fetch_all(pool)
+    .await?;
+    let prices: HashMap<Uuid, Decimal> = prices.into_iter().collect();
+    Ok(Some(value_portfolio(
+        account.cursor_time,
+        account.starting_cash,
+        account.cash,
+        &holdings,
+        &prices,
+    )))
+}
+
+/// Move the session's cursor to `timestamp`, with each asset's last close
+/// at or before it (`None` before its first bar); `finished` at the last bar.
+pub async fn save_cursor(
+    pool: &PgPool,
+    replay_id: Uuid,
+    timestamp: NaiveDateTime,
+    prices: &[(Uuid, Option<Decimal>)],
+    finished: bool,
+) -> sqlx::Result<()> {
+    let (asset_ids, prices): (Vec<Uuid>, Vec<Option<Decimal>>) = prices.iter().copied().unzip();
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "UPDATE replay_sessions
+         SET cursor_time = $2, finished = $3, cursor_updated_at = NOW() AT TIME ZONE 'UTC'
+         WHERE id = $1",
+    )
+    .bind(replay_id)
+    .bind(timestamp)
+    .bind(finished)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        "UPDATE replay_session_assets rsa SET cursor_price = v.price
+         FROM UNNEST($2::uuid[], $3::numeric[]) AS v(asset_id, price)
+         WHERE rsa.replay_id = $1 AND rsa.asset_id = v.asset_id",
+    )
+    .bind(replay_id)
+    .bind(asset_ids)
+    .bind(prices)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await
+}
+
+/// Save whether the stream is paused and its speed, for the next connection.
+pub async fn save_playback(pool: &PgPool, replay_id: Uuid, paused: bool, speed: f64) -> sqlx::Result<()> {
+    sqlx::query("UPDATE replay_sessions SET paused = $2, speed = $3 WHERE id = $1")
+        .bind(replay_id)
+        .bind(paused)
+        .bind(speed)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Whether `user_id` owns the session. Other users' sessions are treated
+/// as missing.
+pub async fn is_owner(pool: &PgPool, replay_id: Uuid, user_id: Uuid) -> sqlx::Result<bool> {
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM replay_sessions WHERE id = $1 AND user_id = $2)")
+        .bind(replay_id)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(bars: i64) -> ReplayPlayer {
+        let start = NaiveDateTime::parse_from_str("2026-10-16 05:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        ReplayPlayer::new((0..bars).map(|i| start + chrono::Duration::minutes(i)).collect())
+    }
+
+    fn seek(timestamp: &str) -> ReplayCommand {
+        ReplayCommand::Seek { timestamp: timestamp.to_string() }
+    }
+
+    #[test]
+    fn parses_client_commands() {
+        let parse = |s: &str| serde_json::from_str::<ReplayCommand>(s).unwrap();
+        assert_eq!(parse(r#"{"action":"step"}"#), ReplayCommand::Step { count: 1 });
+        assert_eq!(parse(r#"{"action":"speed","value":0.5}"#), ReplayCommand::Speed { value: 0.5 });
+        assert!(serde_json::from_str::<ReplayCommand>(r#"{"action":"rewind"}"#).is_err());
+    }
+
+    #[test]
+    fn plays_to_the_end_and_stops() {
+        let mut p = player(3);
+        assert_eq!((p.advance(), p.advance(), p.advance()), (Some(0), Some(1), Some(2)));
+        assert!(!p.is_playing());
+        assert_eq!(p.advance(), None);
+        assert!(p.cursor().finished);
+        assert!(p.apply(&ReplayCommand::Play).is_err());
+    }
+
+    #[test]
+    fn step_pauses_and_seek_lands_on_the_bar_at_or_before() {
+        let mut p = player(10);
+        assert_eq!(p.apply(&ReplayCommand::Step { count: 2 }).unwrap(), vec![0, 1]);
+        assert!(!p.is_playing());
+        assert_eq!(p.advance(), None);
+
+        assert_eq!(p.apply(&seek("2026-10-16T05:07:30Z")).unwrap(), vec![7]);
+        assert_eq!(p.cursor().index, Some(7));
+        // Offsets are converted to UTC.
+        assert_eq!(p.apply(&seek("2026-10-16T10:33:00+05:30")).unwrap(), vec![3]);
+        assert!(p.apply(&seek("2026-10-16T04:59:00Z")).is_err());
+        assert!(p.apply(&seek("2026-10-16T05:10:00Z")).is_err());
+
+        p.apply(&ReplayCommand::Play).unwrap();
+        assert_eq!(p.advance(), Some(4));
+    }
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn forward_only_players_never_seek_back() {
+        let mut p = player(10).forward_only();
+        assert_eq!(p.resume(Some(at("2026-10-16 05:04:00")), None), vec![4]);
+        assert_eq!(p.advance(), Some(5));
+        assert_eq!(p.apply(&seek("2026-10-16T05:05:00Z")).unwrap(), vec![5]);
+        assert_eq!(p.apply(&ReplayCommand::Step { count: 3 }).unwrap(), vec![6, 7, 8]);
+        assert!(p.apply(&seek("2026-10-16T05:07:00Z")).is_err());
+        assert_eq!(p.apply(&seek("2026-10-16T05:09:00Z")).unwrap(), vec![9]);
+        assert!(p.cursor().finished);
+
+        let mut fresh = player(3).forward_only();
+        assert_eq!(fresh.apply(&seek("2026-10-16T05:00:00Z")).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn resumes_after_the_last_bar_the_client_saw() {
+        let cursor = Some(at("2026-10-16 05:06:00"));
+        // The client missed the last two bars sent before it dropped.
+        let mut p = player(10).restore(false, 4.0);
+        assert_eq!(p.resume(cursor, Some(at("2026-10-16 05:04:00"))), vec![5, 6]);
+        assert_eq!((p.cursor().index, p.cursor().speed), (Some(6), 4.0));
+        assert_eq!(p.advance(), Some(7));
+
+        // Up to date, or not saying: nothing to resend, or the cursor's bar.
+        assert!(player(10).resume(cursor, cursor).is_empty());
+        assert_eq!(player(10).resume(cursor, None), vec![6]);
+        // A cursor between bars (another bar size) resumes on the bar before it.
+        assert_eq!(player(10).resume(Some(at("2026-10-16 05:06:30")), None), vec![6]);
+        assert!(player(10).resume(None, None).is_empty());
+
+        let mut paused = player(10).restore(true, 1.0);
+        assert_eq!(paused.resume(cursor, None), vec![6]);
+        assert_eq!(paused.advance(), None);
+        let mut done = player(10);
+        done.resume(Some(at("2026-10-16 05:09:00")), None);
+        assert!(!done.is_playing() && done.cursor().finished);
+    }
+
+    #[test]
+    fn turn_based_players_only_step() {
+        let mut p = player(5).turn_based();
+        assert!(!p.is_playing());
+        assert_eq!(p.advance(), None);
+        assert!(p.apply(&ReplayCommand::Play).is_err());
+        assert!(p.apply(&seek("2026-10-16T05:03:00Z")).is_err());
+        assert_eq!(p.apply(&ReplayCommand::Step { count: 1 }).unwrap(), vec![0]);
+        assert_eq!(p.apply(&ReplayCommand::Step { count: 1 }).unwrap(), vec![1]);
+    }
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn fills_track_cash_average_cost_and_realized_pnl() {
+        let mut cash = dec("1000");
+        let mut h = Holding::default();
+        h.fill(TradeSide::Buy, dec("2"), dec("100"), &mut cash).unwrap();
+        h.fill(TradeSide::Buy, dec("2"), dec("130"), &mut cash).unwrap();
+        assert_eq!((h.quantity, h.avg_cost, cash), (dec("4"), dec("115"), dec("540")));
+
+        h.fill(TradeSide::Sell, dec("3"), dec("120"), &mut cash).unwrap();
+        assert_eq!((h.quantity, h.realized_pnl, cash), (dec("1"), dec("15"), dec("900")));
+
+        // Oversells and overspending leave the account untouched.
+        assert!(h.fill(TradeSide::Sell, dec("2"), dec("120"), &mut cash).is_err());
+        assert!(h.fill(TradeSide::Buy, dec("10"), dec("100"), &mut cash).is_err());
+        assert_eq!((h.quantity, cash), (dec("1"), dec("900")));
+
+        h.fill(TradeSide::Sell, dec("1"), dec("110"), &mut cash).unwrap();
+        assert_eq!((h.quantity, h.avg_cost, h.realized_pnl), (Decimal::ZERO, Decimal::ZERO, dec("10")));
+    }
+
+    #[test]
+    fn portfolio_marks_positions_at_the_cursor() {
+        let (a, b) = (Uuid::from_u128(1), Uuid::from_u128(2));
+        let holding = |quantity: &str, avg_cost: &str, realized_pnl: &str| Holding {
+            quantity: dec(quantity),
+            avg_cost: dec(avg_cost),
+            realized_pnl: dec(realized_pnl),
+        };
+        let holdings = [(a, holding("2", "100", "5")), (b, holding("1", "50", "0"))];
+        let prices = HashMap::from([(a, dec("110"))]);
+        let p = value_portfolio(None, dec("1000"), dec("800"), &holdings, &prices);
+        // b has no price yet and is held at cost.
+        assert_eq!(p.positions[1].unrealized_pnl, Decimal::ZERO);
+        assert_eq!((p.market_value, p.equity), (dec("270"), dec("1070")));
+        assert_eq!((p.unrealized_pnl, p.realized_pnl, p.total_pnl), (dec("20"), dec("5"), dec("70")));
+    }
+
+    #[test]
+    fn speed_is_bounded() {
+        let mut p = player(2);
+        p.apply(&ReplayCommand::Speed { value: 4.0 }).unwrap();
+        assert_eq!(p.delay(), Duration::from_millis(250));
+        assert!(p.apply(&ReplayCommand::Speed { value: 0.25 }).is_err());
+        assert!(p.apply(&ReplayCommand::Speed { value: 61.0 }).is_err());
+    }
+}
@@ -0,0 +1,525 @@
+//! Resting orders in replay sessions.
+//!
+//! Limit, stop and stop-limit orders wait in `replay_orders` and are matched
+//! against each bar the replay socket streams, using only that bar's OHLC:
+//!
+//! - Orders go live at the bar on screen when placed and can fill from the
+//!   next bar on, never on a bar the student has already seen.
+//! - A buy limit fills when the low reaches the limit, at the limit or at
+//!   the open if the bar gapped through it; sell limits mirror this on the
+//!   high.
+//! - A buy stop triggers when the high reaches the stop and fills at the
+//!   stop, or at the open on a gap; sell stops mirror this on the low.
+//! - A stop-limit triggers like a stop. It fills at the trigger price if that
+//!   is within its limit, and otherwise rests as a limit from the next bar.
+//! - A bracket is an entry with stop-loss and take-profit legs (long only).
+//!   The legs wait until the entry fills, go live from the next bar, and
+//!   cancel each other. If one bar reaches both, the stop-loss wins.
+//!
+//! A fill the account can't take (too little cash, more than the position)
+//! rejects the order instead.
+
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::services::price_history::Candle;
+use crate::services::replay::{self, Fill, TradeSide};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderType {
+    Market,
+    Limit,
+    Stop,
+    StopLimit,
+}
+
+impl OrderType {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "market" => Some(OrderType::Market),
+            "limit" => Some(OrderType::Limit),
+            "stop" => Some(OrderType::Stop),
+            "stop_limit" => Some(OrderType::StopLimit),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OrderType::Market => "market",
+            OrderType::Limit => "limit",
+            OrderType::Stop => "stop",
+            OrderType::StopLimit => "stop_limit",
+        }
+    }
+}
+
+/// A new order as the student asked for it.
+#[derive(Debug, Clone)]
+pub struct OrderRequest {
+    pub side: TradeSide,
+    pub order_type: OrderType,
+    pub quantity: Decimal,
+    pub limit_price: Option<Decimal>,
+    pub stop_price: Option<Decimal>,
+    /// Bracket legs; either makes the order a bracket entry.
+    pub stop_loss: Option<Decimal>,
+    pub take_profit: Option<Decimal>,
+}
+
+impl OrderRequest {
+    pub fn is_bracket(&self) -> bool {
+        self.stop_loss.is_some() || self.take_profit.is_some()
+    }
+
+    /// Check prices against the order type. `market_price` is the cursor
+    /// close, used as the entry price of a market bracket.
+    pub fn validate(&self, market_price: Decimal) -> Result<(), String> {
+        if self.quantity <= Decimal::ZERO {
+            return Err("Quantity must be positive".to_string());
+        }
+        let prices = [self.limit_price, self.stop_price, self.stop_loss, self.take_profit];
+        if prices.iter().flatten().any(|p| *p <= Decimal::ZERO) {
+            return Err("Prices must be positive".to_string());
+        }
+        let (needs_limit, needs_stop) = match self.order_type {
+            OrderType::Market => (false, false),
+            OrderType::Limit => (true, false),
+            OrderType::Stop => (false, true),
+            OrderType::StopLimit => (true, true),
+        };
+        if needs_limit != self.limit_price.is_some() {
+            return Err(match needs_limit {
+                true => format!("{} orders need limit_price", self.order_type.as_str()),
+                false => format!("{} orders take no limit_price", self.order_type.as_str()),
+            });
+        }
+        if needs_stop != self.stop_price.is_some() {
+            return Err(match needs_stop {
+                true => format!("{} orders need stop_price", self.order_type.as_str()),
+                false => format!("{} orders take no stop_price", self.order_type.as_str()),
+            });
+        }
+
+        if self.is_bracket() {
+            if self.side != TradeSide::Buy {
+                return Err("Brackets are long only: the entry must be a buy".to_string());
+            }
+            let entry = self.limit_price.or(self.stop_price).unwrap_or(market_price);
+            if self.stop_loss.is_some_and(|sl| sl >= entry) {
+                return Err(format!("stop_loss must be below the entry price {}", entry.normalize()));
+            }
+            if self.take_profit.is_some_and(|tp| tp <= entry) {
+                return Err(format!("take_profit must be above the entry price {}", entry.normalize()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What a bar does to a resting order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BarMatch {
+    Rest,
+    /// A stop-limit's stop was hit but its limit wasn't; it now rests as a limit.
+    Triggered,
+    Fill(Decimal),
+}
+
+/// Match one resting order against `bar` (see the module docs for the rules).
+pub fn match_bar(
+    side: TradeSide,
+    order_type: OrderType,
+    limit_price: Option<Decimal>,
+    stop_price: Option<Decimal>,
+    triggered: bool,
+    bar: &Candle,
+) -> BarMatch {
+    let limit = |limit: Decimal| match side {
+        TradeSide::Buy if bar.low <= limit => BarMatch::Fill(bar.open.min(limit)),
+        TradeSide::Sell if bar.high >= limit => BarMatch::Fill(bar.open.max(limit)),
+        _ => BarMatch::Rest,
+    };
+    let stop = |stop: Decimal| match side {
+        TradeSide::Buy if bar.high >= stop => Some(bar.open.max(stop)),
+        TradeSide::Sell if bar.low <= stop => Some(bar.open.min(stop)),
+        _ => None,
+    };
+
+    match (order_type, limit_price, stop_price) {
+        (OrderType::Market, _, _) => BarMatch::Fill(bar.open),
+        (OrderType::Limit, Some(l), _) => limit(l),
+        (OrderType::Stop, _, Some(s)) => stop(s).map_or(BarMatch::Rest, BarMatch::Fill),
+        (OrderType::StopLimit, Some(l), _) if triggered => limit(l),
+        (OrderType::StopLimit, Some(l), Some(s)) => match stop(s) {
+            None => BarMatch::Rest,
+            Some(p) if (side == TradeSide::Buy && p <= l) || (side == TradeSide::Sell && p >= l) => {
+                BarMatch::Fill(p)
+            }
+            Some(_) => BarMatch::Triggered,
+        },
+        _ => BarMatch::Rest,
+    }
+}
+
+pub const ORDER_COLUMNS: &str = "id, asset_id, parent_id, role, side, order_type, quantity, \
+     limit_price, stop_price, triggered, status, active_from, fill_price, filled_at, reason, created_at";
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ReplayOrder {
+    pub id: Uuid,
+    pub asset_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    /// `entry`, `stop_loss` or `take_profit`.
+    pub role: String,
+    pub side: String,
+    pub order_type: String,
+    pub quantity: Decimal,
+    pub limit_price: Option<Decimal>,
+    pub stop_price: Option<Decimal>,
+    pub triggered: bool,
+    /// `pending`, `open`, `filled`, `cancelled` or `rejected`.
+    pub status: String,
+    pub active_from: Option<NaiveDateTime>,
+    pub fill_price: Option<Decimal>,
+    pub filled_at: Option<NaiveDateTime>,
+    pub reason: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Something that happened to an order on a bar, for the replay socket.
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderEvent {
+    /// `filled`, `triggered`, `rejected` or `cancelled`.
+    pub event: &'static str,
+    pub order: ReplayOrder,
+}
+
+/// Insert an order; it is live from `active_from`, or `pending` without one.
+#[allow(clippy::too_many_arguments)]
+pub async fn insert(
+    tx: &mut Transaction<'_, Postgres>,
+    replay_id: Uuid,
+    asset_id: Uuid,
+    parent_id: Option<Uuid>,
+    role: &str,
+    side: TradeSide,
+    order_type: OrderType,
+    quantity: Decimal,
+    limit_price: Option<Decimal>,
+    stop_price: Option<Decimal>,
+    active_from: Option<NaiveDateTime>,
+) -> sqlx::Result<ReplayOrder> {
+    sqlx::query_as::<_, ReplayOrder>(&format!(
+        "INSERT INTO replay_orders
+             (replay_id, asset_id, parent_id, role, side, order_type, quantity,
+              limit_price, stop_price, status, active_from)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9,
+                 CASE WHEN $10::timestamp IS NULL THEN 'pending' ELSE 'open' END, $10)
+         RETURNING {}",
+        ORDER_COLUMNS
+    ))
+    .bind(replay_id)
+    .bind(asset_id)
+    .bind(parent_id)
+    .bind(role)
+    .bind(side.as_str())
+    .bind(order_type.as_str())
+    .bind(quantity)
+    .bind(limit_price)
+    .bind(stop_price)
+    .bind(active_from)
+    .fetch_one(&mut **tx)
+    .await
+}
+
+/// Mark an order filled at `price` on the bar at `at`.
+pub async fn mark_filled(
+    tx: &mut Transaction<'_, Postgres>,
+    order_id: Uuid,
+    price: Decimal,
+    at: NaiveDateTime,
+) -> sqlx::Result<ReplayOrder> {
+    sqlx::query_as::<_, ReplayOrder>(&format!(
+        "UPDATE replay_orders SET status = 'filled', fill_price = $2, filled_at = $3
+         WHERE id = $1 RETURNING {}",
+        ORDER_COLUMNS
+    ))
+    .bind(order_id)
+    .bind(price)
+    .bind(at)
+    .fetch_one(&mut **tx)
+    .await
+}
+
+/// Close every live order matching `filter` (a `WHERE` clause on `$1`) with
+/// `status` and `reason`, returning them.
+async fn close_where(
+    tx: &mut Transaction<'_, Postgres>,
+    filter: &str,
+    id: Uuid,
+    status: &str,
+    reason: &str,
+) -> sqlx::Result<Vec<ReplayOrder>> {
+    sqlx::query_as::<_, ReplayOrder>(&format!(
+        "UPDATE replay_orders SET status = $2, reason = $3
+         WHERE {} AND status IN ('pending', 'open')
+         RETURNING {}",
+        filter, ORDER_COLUMNS
+    ))
+    .bind(id)
+    .bind(status)
+    .bind(reason)
+    .fetch_all(&mut **tx)
+    .await
+}
+
+/// Cancel a live order, and the bracket legs of an entry.
+pub async fn cancel(
+    tx: &mut Transaction<'_, Postgres>,
+    order_id: Uuid,
+    reason: &str,
+) -> sqlx::Result<Vec<ReplayOrder>> {
+    close_where(tx, "(id = $1 OR parent_id = $1)", order_id, "cancelled", reason).await
+}
+
+/// Put a filled entry's bracket legs live from `at`.
+pub async fn activate_legs(
+    tx: &mut Transaction<'_, Postgres>,
+    entry_id: Uuid,
+    at: NaiveDateTime,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        "UPDATE replay_orders SET status = 'open', active_from = $2
+         WHERE parent_id = $1 AND status = 'pending'",
+    )
+    .bind(entry_id)
+    .bind(at)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+/// Quantity of `asset_id` that live sell orders would sell. The two legs of
+/// a bracket cancel each other, so they count once; legs still waiting on
+/// their entry sell shares that aren't held yet and don't count.
+pub async fn committed_sells(
+    tx: &mut Transaction<'_, Postgres>,
+    replay_id: Uuid,
+    asset_id: Uuid,
+) -> sqlx::Result<Decimal> {
+    sqlx::query_scalar(
+        "SELECT COALESCE(SUM(quantity), 0) FROM (
+             SELECT quantity FROM replay_orders
+             WHERE replay_id = $1 AND asset_id = $2 AND side = 'sell' AND status = 'open'
+               AND parent_id IS NULL
+             UNION ALL
+             SELECT MAX(quantity) FROM replay_orders
+             WHERE replay_id = $1 AND asset_id = $2 AND side = 'sell' AND status = 'open'
+               AND parent_id IS NOT NULL
+             GROUP BY parent_id
+         ) committed",
+    )
+    .bind(replay_id)
+    .bind(asset_id)
+    .fetch_one(&mut **tx)
+    .await
+}
+
+/// Whether the session has orders waiting on a bar. Bracket legs only go
+/// live when an open entry fills, so open orders are enough.
+pub async fn has_open(pool: &PgPool, replay_id: Uuid) -> sqlx::Result<bool> {
+    sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM replay_orders WHERE replay_id = $1 AND status = 'open')",
+    )
+    .bind(replay_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// Match the session's live orders in `asset_id` against a streamed bar,
+/// booking fills to the account.
+pub async fn process_bar(
+    pool: &PgPool,
+    replay_id: Uuid,
+    asset_id: Uuid,
+    bar: &Candle,
+) -> sqlx::Result<Vec<OrderEvent>> {
+    let mut tx = pool.begin().await?;
+    let Some(mut cash) =
+        sqlx::query_scalar::<_, Decimal>("SELECT cash FROM replay_sessions WHERE id = $1 FOR UPDATE")
+            .bind(replay_id)
+            .fetch_optional(&mut *tx)
+            .await?
+    else {
+        return Ok(Vec::new());
+    };
+    // Stop-losses first, so a bar that reaches both bracket legs stops out.
+    let orders = sqlx::query_as::<_, ReplayOrder>(&format!(
+        "SELECT {} FROM replay_orders
+         WHERE replay_id = $1 AND asset_id = $2 AND status = 'open' AND active_from < $3
+         ORDER BY role = 'stop_loss' DESC, created_at, id",
+        ORDER_COLUMNS
+    ))
+    .bind(replay_id)
+    .bind(asset_id)
+    .bind(bar.timestamp)
+    .fetch_all(&mut *tx)
+    .await?;
+    if orders.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut holding = replay::holding(&mut tx, replay_id, asset_id).await?;
+    let mut events = Vec::new();
+    let mut closed: HashSet<Uuid> = HashSet::new();
+    for order in orders {
+        if closed.contains(&order.id) {
+            continue;
+        }
+        let (Some(side), Some(order_type)) =
+            (TradeSide::parse(&order.side), OrderType::parse(&order.order_type))
+        else {
+            continue;
+        };
+        let price = match match_bar(side, order_type, order.limit_price, order.stop_price, order.triggered, bar) {
+            BarMatch::Rest => continue,
+            BarMatch::Triggered => {
+                let order = sqlx::query_as::<_, ReplayOrder>(&format!(
+                    "UPDATE replay_orders SET triggered = TRUE WHERE id = $1 RETURNING {}",
+                    ORDER_COLUMNS
+                ))
+                .bind(order.id)
+                .fetch_one(&mut *tx)
+                .await?;
+                events.push(OrderEvent { event: "triggered", order });
+                continue;
+            }
+            BarMatch::Fill(price) => price,
+        };
+
+        if let Err(reason) = holding.fill(side, order.quantity, price, &mut cash) {
+            for order in close_where(&mut tx, "(id = $1 OR parent_id = $1)", order.id, "rejected", &reason).await? {
+                closed.insert(order.id);
+                events.push(OrderEvent { event: "rejected", order });
+            }
+            continue;
+        }
+        let fill = Fill {
+            replay_id,
+            asset_id,
+            order_id: Some(order.id),
+            side,
+            quantity: order.quantity,
+            price,
+            timestamp: bar.timestamp,
+        };
+        replay::record_fill(&mut tx, &fill, cash, &holding).await?;
+        let filled = mark_filled(&mut tx, order.id, price, bar.timestamp).await?;
+        events.push(OrderEvent { event: "filled", order: filled });
+
+        if order.role == "entry" {
+            activate_legs(&mut tx, order.id, bar.timestamp).await?;
+        } else if let Some(parent_id) = order.parent_id {
+            let siblings = close_where(&mut tx, "parent_id = $1", parent_id, "cancelled", "Other bracket leg filled").await?;
+            for sibling in siblings {
+                closed.insert(sibling.id);
+                events.push(OrderEvent { event: "cancelled", order: sibling });
+            }
+        }
+    }
+    tx.commit().await?;
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    fn bar(open: &str, high: &str, low: &str, close: &str) -> Candle {
+        Candle {
+            timestamp: NaiveDateTime::parse_from_str("2026-10-16 05:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            open: dec(open),
+            high: dec(high),
+            low: dec(low),
+            close: dec(close),
+            volume: None,
+            resolution: "1m".to_string(),
+        }
+    }
+
+    #[test]
+    fn limits_fill_at_the_limit_or_a_better_open() {
+        let b = bar("100", "105", "98", "101");
+        let buy = |l: &str| match_bar(TradeSide::Buy, OrderType::Limit, Some(dec(l)), None, false, &b);
+        assert_eq!(buy("99"), BarMatch::Fill(dec("99")));
+        assert_eq!(buy("97"), BarMatch::Rest);
+        // Gapped through: the open is already below the limit.
+        assert_eq!(buy("102"), BarMatch::Fill(dec("100")));
+        let sell = |l: &str| match_bar(TradeSide::Sell, OrderType::Limit, Some(dec(l)), None, false, &b);
+        assert_eq!(sell("104"), BarMatch::Fill(dec("104")));
+        assert_eq!(sell("95"), BarMatch::Fill(dec("100")));
+        assert_eq!(sell("106"), BarMatch::Rest);
+    }
+
+    #[test]
+    fn stops_fill_at_the_stop_or_a_worse_open() {
+        let b = bar("100", "105", "98", "101");
+        let stop = |side, s: &str| match_bar(side, OrderType::Stop, None, Some(dec(s)), false, &b);
+        assert_eq!(stop(TradeSide::Buy, "103"), BarMatch::Fill(dec("103")));
+        assert_eq!(stop(TradeSide::Buy, "95"), BarMatch::Fill(dec("100")));
+        assert_eq!(stop(TradeSide::Sell, "99"), BarMatch::Fill(dec("99")));
+        assert_eq!(stop(TradeSide::Sell, "102"), BarMatch::Fill(dec("100")));
+        assert_eq!(stop(TradeSide::Sell, "97"), BarMatch::Rest);
+    }
+
+    #[test]
+    fn stop_limits_rest_as_limits_when_triggered_past_the_limit() {
+        // Gaps up through a 102 stop with a 103 limit: triggered, no fill.
+        let gap = bar("104", "106", "103.5", "105");
+        let order = |triggered, b: &Candle| {
+            match_bar(TradeSide::Buy, OrderType::StopLimit, Some(dec("103")), Some(dec("102")), triggered, b)
+        };
+        assert_eq!(order(false, &gap), BarMatch::Triggered);
+        // Later bar comes back to the limit.
+        assert_eq!(order(true, &bar("104", "104", "102.5", "103")), BarMatch::Fill(dec("103")));
+        // Triggered within the limit: fills at the stop.
+        assert_eq!(order(false, &bar("101", "102.5", "100", "102")), BarMatch::Fill(dec("102")));
+    }
+
+    #[test]
+    fn validates_prices_for_the_order_type() {
+        let request = |order_type, limit: Option<&str>, stop: Option<&str>| OrderRequest {
+            side: TradeSide::Buy,
+            order_type,
+            quantity: dec("1"),
+            limit_price: limit.map(dec),
+            stop_price: stop.map(dec),
+            stop_loss: None,
+            take_profit: None,
+        };
+        assert!(request(OrderType::Limit, Some("100"), None).validate(dec("100")).is_ok());
+        assert!(request(OrderType::Limit, None, None).validate(dec("100")).is_err());
+        assert!(request(OrderType::Stop, Some("100"), Some("101")).validate(dec("100")).is_err());
+        assert!(request(OrderType::StopLimit, Some("103"), Some("102")).validate(dec("100")).is_ok());
+
+        let bracket = |sl: &str, tp: &str| OrderRequest {
+            stop_loss: Some(dec(sl)),
+            take_profit: Some(dec(tp)),
+            ..request(OrderType::Market, None, None)
+        };
+        assert!(bracket("95", "110").validate(dec("100")).is_ok());
+        assert!(bracket("101", "110").validate(dec("100")).is_err());
+        assert!(bracket("95", "99").validate(dec("100")).is_err());
+        let short = OrderRequest { side: TradeSide::Sell, ..bracket("95", "110") };
+        assert!(short.validate(dec("100")).is_err());
+    }
+}
@@ -0,0 +1,268 @@
+//! Next-bar predictions in bar-by-bar replays.
+//!
+//! Before stepping, a student may predict where an asset's next bar closes:
+//! a direction (`up`, `down` or `flat`), a target price, or both. The
+//! prediction is compared with the bar on screen when it was made and
+//! resolved by the replay socket when the asset's next bar is revealed.
+//!
+//! - A move within [`FLAT_BAND`] of the reference close counts as flat.
+//! - A direction scores 1 when it matches and 0 otherwise.
+//! - A target scores `1 - |target - close| / |move|`, floored at 0, where
+//!   the move is the distance from the reference close (at least the flat
+//!   band). Predicting "no change" scores 0, and an exact close scores 1.
+//! - A prediction's score is the mean of the parts it made.
+
+use chrono::NaiveDateTime;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Largest move, as a fraction of the reference close, that counts as flat.
+pub const FLAT_BAND: f64 = 0.0001;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Up,
+    Down,
+    Flat,
+}
+
+impl Direction {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "up" => Some(Direction::Up),
+            "down" => Some(Direction::Down),
+            "flat" => Some(Direction::Flat),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Flat => "flat",
+        }
+    }
+
+    /// How the close moved from `reference` to `actual`.
+    pub fn of_move(reference: f64, actual: f64) -> Self {
+        let change = actual - reference;
+        if change.abs() <= reference.abs() * FLAT_BAND {
+            Direction::Flat
+        } else if change > 0.0 {
+            Direction::Up
+        } else {
+            Direction::Down
+        }
+    }
+}
+
+/// How a prediction fared against the revealed close.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub actual_direction: Direction,
+    pub direction_correct: Option<bool>,
+    pub target_score: Option<f64>,
+    pub score: f64,
+}
+
+pub fn score(direction: Option<Direction>, target: Option<f64>, reference: f64, actual: f64) -> Outcome {
+    let actual_direction = Direction::of_move(reference, actual);
+    let direction_correct = direction.map(|d| d == actual_direction);
+    let target_score = target.map(|t| {
+        let scale = (actual - reference).abs().max(reference.abs() * FLAT_BAND);
+        if scale > 0.0 {
+            (1.0 - (t - actual).abs() / scale).max(0.0)
+        } else {
+            0.0
+        }
+    });
+    let parts: Vec<f64> = direction_correct
+        .map(|hit| if hit { 1.0 } else { 0.0 })
+        .into_iter()
+        .chain(target_score)
+        .collect();
+    Outcome {
+        actual_direction,
+        direction_correct,
+        target_score,
+        score: parts.iter().sum::<f64>() / parts.len().max(1) as f64,
+    }
+}
+
+pub const PREDICTION_COLUMNS: &str = "id, asset_id, direction, target_price, reference_time, \
+     reference_price, resolved_time, actual_price, actual_direction, direction_correct, \
+     target_score, score, created_at";
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Prediction {
+    pub id: Uuid,
+    pub asset_id: Uuid,
+    pub direction: Option<String>,
+    pub target_price: Option<Decimal>,
+    pub reference_time: NaiveDateTime,
+    pub reference_price: Decimal,
+    /// `None` until the asset's next bar is revealed.
+    pub resolved_time: Option<NaiveDateTime>,
+    pub actual_price: Option<Decimal>,
+    pub actual_direction: Option<String>,
+    pub direction_correct: Option<bool>,
+    pub target_score: Option<f64>,
+    pub score: Option<f64>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Prediction accuracy over a session's resolved predictions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PredictionSummary {
+    pub resolved: i64,
+    pub pending: i64,
+    pub direction_predictions: i64,
+    pub direction_hits: i64,
+    pub direction_accuracy: Option<f64>,
+    pub target_predictions: i64,
+    pub mean_target_score: Option<f64>,
+    /// Mean prediction score, 0 to 1.
+    pub score: Option<f64>,
+}
+
+/// Record a prediction for `asset_id`'s next bar, replacing an open one.
+pub async fn submit(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    replay_id: Uuid,
+    asset_id: Uuid,
+    direction: Option<Direction>,
+    target_price: Option<Decimal>,
+    reference_time: NaiveDateTime,
+    reference_price: Decimal,
+) -> sqlx::Result<Prediction> {
+    sqlx::query_as::<_, Prediction>(&format!(
+        "INSERT INTO replay_predictions
+             (replay_id, asset_id, direction, target_price, reference_time, reference_price)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         ON CONFLICT (replay_id, asset_id) WHERE resolved_time IS NULL DO UPDATE
+         SET direction = EXCLUDED.direction, target_price = EXCLUDED.target_price,
+             reference_time = EXCLUDED.reference_time, reference_price = EXCLUDED.reference_price,
+             created_at = NOW()
+         RETURNING {}",
+        PREDICTION_COLUMNS
+    ))
+    .bind(replay_id)
+    .bind(asset_id)
+    .bind(direction.map(Direction::as_str))
+    .bind(target_price)
+    .bind(reference_time)
+    .bind(reference_price)
+    .fetch_one(&mut **tx)
+    .await
+}
+
+/// Score `asset_id`'s open prediction against a revealed bar. Predictions
+/// made on that bar or later (after a resume) wait for the next one.
+pub async fn resolve(
+    pool: &PgPool,
+    replay_id: Uuid,
+    asset_id: Uuid,
+    bar_time: NaiveDateTime,
+    close: Decimal,
+) -> sqlx::Result<Option<Prediction>> {
+    let Some(open) = sqlx::query_as::<_, Prediction>(&format!(
+        "SELECT {} FROM replay_predictions
+         WHERE replay_id = $1 AND asset_id = $2 AND resolved_time IS NULL AND reference_time < $3",
+        PREDICTION_COLUMNS
+    ))
+    .bind(replay_id)
+    .bind(asset_id)
+    .bind(bar_time)
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let outcome = score(
+        open.direction.as_deref().and_then(Direction::parse),
+        open.target_price.and_then(|t| t.to_f64()),
+        open.reference_price.to_f64().unwrap_or_default(),
+        close.to_f64().unwrap_or_default(),
+    );
+    sqlx::query_as::<_, Prediction>(&format!(
+        "UPDATE replay_predictions
+         SET resolved_time = $2, actual_price = $3, actual_direction = $4,
+             direction_correct = $5, target_score = $6, score = $7
+         WHERE id = $1
+         RETURNING {}",
+        PREDICTION_COLUMNS
+    ))
+    .bind(open.id)
+    .bind(bar_time)
+    .bind(close)
+    .bind(outcome.actual_direction.as_str())
+    .bind(outcome.direction_correct)
+    .bind(outcome.target_score)
+    .bind(outcome.score)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn list(pool: &PgPool, replay_id: Uuid) -> sqlx::Result<Vec<Prediction>> {
+    sqlx::query_as::<_, Prediction>(&format!(
+        "SELECT {} FROM replay_predictions WHERE replay_id = $1
+         ORDER BY reference_time, created_at, id",
+        PREDICTION_COLUMNS
+    ))
+    .bind(replay_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn summary(pool: &PgPool, replay_id: Uuid) -> sqlx::Result<PredictionSummary> {
+    sqlx::query_as::<_, PredictionSummary>(
+        "SELECT
+             COUNT(resolved_time) AS resolved,
+             COUNT(*) - COUNT(resolved_time) AS pending,
+             COUNT(direction_correct) AS direction_predictions,
+             COUNT(*) FILTER (WHERE direction_correct) AS direction_hits,
+             AVG(CASE WHEN direction_correct THEN 1.0 ELSE 0.0 END::float8)
+                 FILTER (WHERE direction_correct IS NOT NULL) AS direction_accuracy,
+             COUNT(target_score) AS target_predictions,
+             AVG(target_score) AS mean_target_score,
+             AVG(score) AS score
+         FROM replay_predictions WHERE replay_id = $1",
+    )
+    .bind(replay_id)
+    .fetch_one(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directions_allow_a_flat_band() {
+        assert_eq!(Direction::of_move(1000.0, 1000.05), Direction::Flat);
+        assert_eq!(Direction::of_move(1000.0, 1000.2), Direction::Up);
+        assert_eq!(Direction::of_move(1000.0, 999.8), Direction::Down);
+    }
+
+    #[test]
+    fn scores_direction_and_target() {
+        let up = score(Some(Direction::Up), None, 100.0, 101.0);
+        assert_eq!((up.direction_correct, up.score), (Some(true), 1.0));
+
+        // Target halfway between the reference and the close.
+        let half = score(None, Some(100.5), 100.0, 101.0);
+        assert_eq!((half.actual_direction, half.target_score), (Direction::Up, Some(0.5)));
+        // "No change" earns nothing; overshooting by the whole move too.
+        assert_eq!(score(None, Some(100.0), 100.0, 101.0).target_score, Some(0.0));
+        assert_eq!(score(None, Some(102.5), 100.0, 101.0).target_score, Some(0.0));
+
+        let both = score(Some(Direction::Down), Some(100.75), 100.0, 101.0);
+        assert_eq!((both.direction_correct, both.score), (Some(false), 0.375));
+    }
+}
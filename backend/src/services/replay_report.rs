@@ -0,0 +1,475 @@
+//! End-of-replay performance report and trade journal.
+//!
+//! The report is rebuilt from the session's trade log and the stored candles
+//! of its assets. Realized P&L replays fills in the order they were placed,
+//! as the account booked them. Students can seek back and trade again, so
+//! the equity curve is marked separately: at every bar on the replay clock,
+//! with the fills at or before it.
+//! The benchmark splits the starting cash equally across the assets and buys
+//! each at its first close.
+//! Bar-by-bar sessions also carry their next-bar prediction accuracy.
+//!
+//! Ratios are fractions (0.05 = 5%). Annualized figures need at least a day
+//! of replay time. Sharpe scales per-bar returns by the number of bars the
+//! window holds per year, so market closures don't dilute it.
+
+use chrono::{Duration, NaiveDateTime};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::services::price_history;
+use crate::services::replay::ReplayMode;
+use crate::services::replay_predictions::{self, PredictionSummary};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EquityPoint {
+    pub timestamp: NaiveDateTime,
+    pub equity: f64,
+    /// Equal-weight buy-and-hold of the session's assets.
+    pub benchmark: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssetReturn {
+    pub asset_id: Uuid,
+    pub symbol: String,
+    pub first_close: f64,
+    pub last_close: f64,
+    pub buy_and_hold_return: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayReport {
+    pub start: Option<NaiveDateTime>,
+    pub end: Option<NaiveDateTime>,
+    pub bars: usize,
+    pub starting_cash: f64,
+    pub final_equity: f64,
+    pub total_return: f64,
+    pub annualized_return: Option<f64>,
+    /// Largest peak-to-trough fall of the equity curve.
+    pub max_drawdown: f64,
+    /// Annualized, risk-free rate 0.
+    pub sharpe: Option<f64>,
+    pub trades: usize,
+    /// Sells, each closing (part of) a position.
+    pub closed_trades: usize,
+    pub win_rate: Option<f64>,
+    pub avg_win: Option<f64>,
+    pub avg_loss: Option<f64>,
+    /// Share of bars with an open position.
+    pub exposure: f64,
+    pub benchmark_return: f64,
+    pub excess_return: f64,
+    pub assets: Vec<AssetReturn>,
+    /// Next-bar prediction accuracy, for bar-by-bar sessions.
+    #[serde(default)]
+    pub predictions: Option<PredictionSummary>,
+    pub equity_curve: Vec<EquityPoint>,
+}
+
+/// One asset's closes over the replay window.
+#[derive(Debug, Clone)]
+pub struct AssetBars {
+    pub asset_id: Uuid,
+    pub symbol: String,
+    pub closes: Vec<(NaiveDateTime, f64)>,
+}
+
+/// A fill from the trade log.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct TradeRow {
+    pub id: Uuid,
+    pub asset_id: Uuid,
+    pub order_id: Option<Uuid>,
+    pub side: String,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub timestamp: NaiveDateTime,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Position {
+    quantity: f64,
+    avg_cost: f64,
+}
+
+/// Holdings of one asset on the equity curve. After a seek back, a sell can
+/// sit before its buy in replay time, so `quantity` may dip below zero.
+#[derive(Debug, Default, Clone, Copy)]
+struct Mark {
+    quantity: f64,
+    /// Price of the latest fill, until the asset has a close.
+    price: f64,
+}
+
+fn price_and_quantity(trade: &TradeRow) -> (f64, f64) {
+    (
+        trade.price.to_f64().unwrap_or_default(),
+        trade.quantity.to_f64().unwrap_or_default(),
+    )
+}
+
+fn mean(xs: &[f64]) -> f64 {
+    xs.iter().sum::<f64>() / xs.len() as f64
+}
+
+/// Build the report. `trades` must be in the order they were placed; the
+/// returned map holds the P&L each sell realized, by trade id.
+pub fn analyze(
+    starting_cash: f64,
+    assets: &[AssetBars],
+    trades: &[TradeRow],
+) -> (ReplayReport, HashMap<Uuid, f64>) {
+    let mut clock: Vec<NaiveDateTime> = assets.iter().flat_map(|a| a.closes.iter().map(|c| c.0)).collect();
+    clock.sort();
+    clock.dedup();
+
+    // Realized P&L against the average cost the account held when each fill
+    // was booked
+    let mut positions: HashMap<Uuid, Position> = HashMap::new();
+    let mut realized: HashMap<Uuid, f64> = HashMap::new();
+    for trade in trades {
+        let (price, quantity) = price_and_quantity(trade);
+        let p = positions.entry(trade.asset_id).or_default();
+        if trade.side == "buy" {
+            p.avg_cost = (p.quantity * p.avg_cost + quantity * price) / (p.quantity + quantity);
+            p.quantity += quantity;
+        } else {
+            realized.insert(trade.id, (price - p.avg_cost) * quantity);
+            p.quantity -= quantity;
+            if p.quantity <= 0.0 {
+                *p = Position::default();
+            }
+        }
+    }
+
+    // The curve takes the same fills in replay-time order (stable, so fills
+    // on one bar keep their placement order)
+    let mut ordered: Vec<&TradeRow> = trades.iter().collect();
+    ordered.sort_by_key(|t| t.timestamp);
+
+    let first_close: Vec<Option<f64>> = assets.iter().map(|a| a.closes.first().map(|c| c.1)).collect();
+    let allocation = starting_cash / assets.len().max(1) as f64;
+
+    let mut cash = starting_cash;
+    let mut marks: HashMap<Uuid, Mark> = HashMap::new();
+    let mut last_close: Vec<Option<f64>> = vec![None; assets.len()];
+    let mut cursors = vec![0usize; assets.len()];
+    let mut next_trade = 0;
+    let mut curve = Vec::with_capacity(clock.len());
+    let mut exposed_bars = 0;
+
+    for &t in &clock {
+        for (i, asset) in assets.iter().enumerate() {
+            while cursors[i] < asset.closes.len() && asset.closes[cursors[i]].0 <= t {
+                last_close[i] = Some(asset.closes[cursors[i]].1);
+                cursors[i] += 1;
+            }
+        }
+        // Trades fill at the close of the bar on screen
+        while next_trade < ordered.len() && ordered[next_trade].timestamp <= t {
+            let trade = ordered[next_trade];
+            next_trade += 1;
+            let (price, quantity) = price_and_quantity(trade);
+            let signed = if trade.side == "buy" { quantity } else { -quantity };
+            let mark = marks.entry(trade.asset_id).or_default();
+            mark.quantity += signed;
+            mark.price = price;
+            cash -= signed * price;
+        }
+
+        let held: f64 = assets
+            .iter()
+            .enumerate()
+            .map(|(i, a)| {
+                let mark = marks.get(&a.asset_id).copied().unwrap_or_default();
+                mark.quantity * last_close[i].unwrap_or(mark.price)
+            })
+            .sum();
+        if marks.values().any(|m| m.quantity.abs() > 1e-9) {
+            exposed_bars += 1;
+        }
+        let benchmark = (0..assets.len())
+            .map(|i| match (first_close[i], last_close[i]) {
+                (Some(first), Some(last)) if first > 0.0 => allocation / first * last,
+                _ => allocation,
+            })
+            .sum();
+        curve.push(EquityPoint { timestamp: t, equity: cash + held, benchmark });
+    }
+
+    let final_equity = curve.last().map_or(starting_cash, |p| p.equity);
+    let total_return = final_equity / starting_cash - 1.0;
+    let benchmark_return = curve.last().map_or(0.0, |p| p.benchmark / starting_cash - 1.0);
+
+    let mut peak = f64::MIN;
+    let mut max_drawdown: f64 = 0.0;
+    for p in &curve {
+        peak = peak.max(p.equity);
+        if peak > 0.0 {
+            max_drawdown = max_drawdown.max((peak - p.equity) / peak);
+        }
+    }
+
+    let span = match (clock.first(), clock.last()) {
+        (Some(first), Some(last)) => *last - *first,
+        _ => Duration::zero(),
+    };
+    let years = span.num_seconds() as f64 / (365.25 * 86_400.0);
+    let annual = span >= Duration::days(1);
+    let returns: Vec<f64> = curve
+        .windows(2)
+        .filter(|w| w[0].equity > 0.0)
+        .map(|w| w[1].equity / w[0].equity - 1.0)
+        .collect();
+    let sharpe = if annual && returns.len() >= 2 {
+        let m = mean(&returns);
+        let var = returns.iter().map(|r| (r - m).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+        let bars_per_year = returns.len() as f64 / years;
+        (var > 0.0).then(|| m / var.sqrt() * bars_per_year.sqrt())
+    } else {
+        None
+    };
+
+    let pnl: Vec<f64> = realized.values().copied().collect();
+    let wins: Vec<f64> = pnl.iter().copied().filter(|p| *p > 0.0).collect();
+    let losses: Vec<f64> = pnl.iter().copied().filter(|p| *p < 0.0).collect();
+
+    let report = ReplayReport {
+        start: clock.first().copied(),
+        end: clock.last().copied(),
+        bars: clock.len(),
+        starting_cash,
+        final_equity,
+        total_return,
+        annualized_return: (annual && final_equity > 0.0).then(|| (1.0 + total_return).powf(1.0 / years) - 1.0),
+        max_drawdown,
+        sharpe,
+        trades: trades.len(),
+        closed_trades: pnl.len(),
+        win_rate: (!pnl.is_empty()).then(|| wins.len() as f64 / pnl.len() as f64),
+        avg_win: (!wins.is_empty()).then(|| mean(&wins)),
+        avg_loss: (!losses.is_empty()).then(|| mean(&losses)),
+        exposure: if clock.is_empty() { 0.0 } else { exposed_bars as f64 / clock.len() as f64 },
+        benchmark_return,
+        excess_return: total_return - benchmark_return,
+        assets: assets
+            .iter()
+            .filter_map(|a| {
+                let (first, last) = (a.closes.first()?.1, a.closes.last()?.1);
+                Some(AssetReturn {
+                    asset_id: a.asset_id,
+                    symbol: a.symbol.clone(),
+                    first_close: first,
+                    last_close: last,
+                    buy_and_hold_return: if first > 0.0 { last / first - 1.0 } else { 0.0 },
+                })
+            })
+            .collect(),
+        predictions: None,
+        equity_curve: curve,
+    };
+    (report, realized)
+}
+
+/// The session's trades in the order they were placed.
+pub async fn trades(pool: &PgPool, replay_id: Uuid) -> sqlx::Result<Vec<TradeRow>> {
+    sqlx::query_as::<_, TradeRow>(
+        "SELECT id, asset_id, order_id, side, price, quantity, timestamp, note
+         FROM replay_trades WHERE replay_id = $1
+         ORDER BY recorded_at, timestamp, id",
+    )
+    .bind(replay_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Build the session's report and P&L per trade from stored data. `None`
+/// if the session doesn't exist.
+pub async fn build(
+    pool: &PgPool,
+    replay_id: Uuid,
+) -> sqlx::Result<Option<(ReplayReport, HashMap<Uuid, f64>)>> {
+    #[derive(sqlx::FromRow)]
+    struct Session {
+        start_time: NaiveDateTime,
+        end_time: NaiveDateTime,
+        starting_cash: Decimal,
+        mode: String,
+    }
+
+    let Some(session) = sqlx::query_as::<_, Session>(
+        "SELECT start_time, end_time, starting_cash, mode FROM replay_sessions WHERE id = $1",
+    )
+    .bind(replay_id)
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let members: Vec<(Uuid, String)> = sqlx::query_as(
+        "SELECT rsa.asset_id, a.symbol FROM replay_session_assets rsa
+         JOIN assets a ON a.id = rsa.asset_id
+         WHERE rsa.replay_id = $1 ORDER BY rsa.position",
+    )
+    .bind(replay_id)
+    .fetch_all(pool)
+    .await?;
+    let mut assets = Vec::with_capacity(members.len());
+    for (asset_id, symbol) in members {
+        let candles = price_history::candles(pool, asset_id, session.start_time, session.end_time).await?;
+        assets.push(AssetBars {
+            asset_id,
+            symbol,
+            closes: candles
+                .iter()
+                .map(|c| (c.timestamp, c.close.to_f64().unwrap_or_default()))
+                .collect(),
+        });
+    }
+
+    let trades = trades(pool, replay_id).await?;
+    let starting_cash = session.starting_cash.to_f64().unwrap_or_default();
+    let (mut report, realized) = analyze(starting_cash, &assets, &trades);
+    if session.mode == ReplayMode::BarByBar.as_str() {
+        report.predictions = Some(replay_predictions::summary(pool, replay_id).await?);
+    }
+    Ok(Some((report, realized)))
+}
+
+/// Rebuild and store the session's report. `None` if the session doesn't
+/// exist or hasn't finished; a partial report would show closes ahead of
+/// the cursor.
+pub async fn generate(pool: &PgPool, replay_id: Uuid) -> sqlx::Result<Option<(ReplayReport, NaiveDateTime)>> {
+    let Some((report, _)) = build(pool, replay_id).await? else {
+        return Ok(None);
+    };
+    let generated_at: Option<NaiveDateTime> = sqlx::query_scalar(
+        "INSERT INTO replay_reports (replay_id, report, generated_at)
+         SELECT id, $2, NOW() AT TIME ZONE 'UTC' FROM replay_sessions WHERE id = $1 AND finished
+         ON CONFLICT (replay_id) DO UPDATE
+         SET report = EXCLUDED.report, generated_at = EXCLUDED.generated_at
+         RETURNING generated_at",
+    )
+    .bind(replay_id)
+    .bind(Json(&report))
+    .fetch_optional(pool)
+    .await?;
+    Ok(generated_at.map(|at| (report, at)))
+}
+
+/// The stored report, if one has been generated.
+pub async fn stored(pool: &PgPool, replay_id: Uuid) -> sqlx::Result<Option<(ReplayReport, NaiveDateTime)>> {
+    let row: Option<(Json<ReplayReport>, NaiveDateTime)> =
+        sqlx::query_as("SELECT report, generated_at FROM replay_reports WHERE replay_id = $1")
+            .bind(replay_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.map(|(report, at)| (report.0, at)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(minute: i64) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2026-10-16 05:00:00", "%Y-%m-%d %H:%M:%S").unwrap()
+            + Duration::minutes(minute)
+    }
+
+    fn trade(asset_id: Uuid, side: &str, price: i64, quantity: i64, minute: i64) -> TradeRow {
+        TradeRow {
+            id: Uuid::new_v4(),
+            asset_id,
+            order_id: None,
+            side: side.to_string(),
+            price: Decimal::from(price),
+            quantity: Decimal::from(quantity),
+            timestamp: at(minute),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn equity_curve_drawdown_and_trade_stats() {
+        let a = Uuid::from_u128(1);
+        let closes = [100.0, 110.0, 90.0, 120.0, 120.0];
+        let assets = [AssetBars {
+            asset_id: a,
+            symbol: "A".to_string(),
+            closes: closes.iter().enumerate().map(|(i, c)| (at(i as i64), *c)).collect(),
+        }];
+        // Buy 10 at 100, sell 5 at 90 (loss), sell 5 at 120 (win).
+        let trades = [
+            trade(a, "buy", 100, 10, 0),
+            trade(a, "sell", 90, 5, 2),
+            trade(a, "sell", 120, 5, 3),
+        ];
+        let (report, realized) = analyze(1000.0, &assets, &trades);
+
+        let equity: Vec<f64> = report.equity_curve.iter().map(|p| p.equity).collect();
+        assert_eq!(rounded(&equity), vec![1000.0, 1100.0, 900.0, 1050.0, 1050.0]);
+        assert!((report.total_return - 0.05).abs() < 1e-9);
+        assert!((report.max_drawdown - 200.0 / 1100.0).abs() < 1e-9);
+        assert_eq!((report.closed_trades, report.win_rate), (2, Some(0.5)));
+        assert_eq!((report.avg_win, report.avg_loss), (Some(100.0), Some(-50.0)));
+        assert_eq!(realized[&trades[1].id], -50.0);
+        // Holding during the first three bars only.
+        assert!((report.exposure - 0.6).abs() < 1e-9);
+        // Buy-and-hold of A: 100 -> 120.
+        assert!((report.benchmark_return - 0.2).abs() < 1e-9);
+        assert!((report.excess_return + 0.15).abs() < 1e-9);
+        // An hour of replay is too short to annualize.
+        assert_eq!((report.annualized_return, report.sharpe), (None, None));
+    }
+
+    #[test]
+    fn benchmark_splits_cash_across_assets() {
+        let (a, b) = (Uuid::from_u128(1), Uuid::from_u128(2));
+        let bars = |id, symbol: &str, closes: &[f64]| AssetBars {
+            asset_id: id,
+            symbol: symbol.to_string(),
+            closes: closes.iter().enumerate().map(|(i, c)| (at(i as i64), *c)).collect(),
+        };
+        let assets = [bars(a, "A", &[100.0, 150.0]), bars(b, "B", &[50.0, 50.0])];
+        let (report, _) = analyze(1000.0, &assets, &[]);
+        assert!((report.benchmark_return - 0.25).abs() < 1e-9);
+        assert_eq!(report.total_return, 0.0);
+        assert_eq!(report.exposure, 0.0);
+        assert_eq!(report.assets[0].buy_and_hold_return, 0.5);
+    }
+
+    #[test]
+    fn seek_back_sells_realize_against_the_buy_they_follow() {
+        let a = Uuid::from_u128(1);
+        let assets = [AssetBars {
+            asset_id: a,
+            symbol: "A".to_string(),
+            closes: [100.0, 105.0, 110.0].iter().enumerate().map(|(i, c)| (at(i as i64), *c)).collect(),
+        }];
+        // Buy 1 at 110 on the last bar, then seek back and sell it at 100
+        let trades = [trade(a, "buy", 110, 1, 2), trade(a, "sell", 100, 1, 0)];
+        let (report, realized) = analyze(1000.0, &assets, &trades);
+
+        assert_eq!(realized[&trades[1].id], -10.0);
+        assert_eq!((report.win_rate, report.avg_loss), (Some(0.0), Some(-10.0)));
+        // Cash ends 10 lower with nothing held, as in the account
+        assert!((report.final_equity - 990.0).abs() < 1e-9);
+        // In replay time the sell comes first: short one unit until the buy
+        let equity: Vec<f64> = report.equity_curve.iter().map(|p| p.equity).collect();
+        assert_eq!(rounded(&equity), vec![1000.0, 995.0, 990.0]);
+        assert!((report.exposure - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    fn rounded(xs: &[f64]) -> Vec<f64> {
+        xs.iter().map(|x| (x * 1e6).round() / 1e6).collect()
+    }
+}
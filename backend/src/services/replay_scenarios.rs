@@ -0,0 +1,476 @@
+//! Curated replay scenarios and blind mode.
+//!
+//! Admins save replay windows (a flash crash, a budget-day rally) with the
+//! assets, starting cash and learning goals a student should play them with.
+//! Students start sessions from them; each student's first session of a
+//! scenario is ranked on its leaderboard once it finishes.
+//!
+//! A blind scenario hides what the student could recognise from memory
+//! until the replay ends: replay times are shifted onto [`blind_epoch`],
+//! symbols become `Asset A`, `Asset B`, ... and asset ids are swapped for
+//! per-session stand-ins. Prices are indexed so each asset's last close
+//! before the window reads 100, and quantities scale the other way so that
+//! values and cash stay true. [`BlindMask`] rewrites API and socket payloads
+//! by field name, so every response passes through it before leaving.
+
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::price_history;
+
+/// Where blind replays appear to start (a Monday).
+pub fn blind_epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2000, 1, 3)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .expect("valid date")
+}
+
+/// Fields holding replay times; wall-clock fields like `created_at` are left alone.
+const TIME_FIELDS: &[&str] = &[
+    "timestamp",
+    "start_time",
+    "end_time",
+    "cursor_time",
+    "active_from",
+    "filled_at",
+    "reference_time",
+    "resolved_time",
+];
+
+/// Per-share prices, indexed in blind replays.
+const PRICE_FIELDS: &[&str] = &[
+    "price",
+    "limit_price",
+    "stop_price",
+    "fill_price",
+    "avg_cost",
+    "target_price",
+    "reference_price",
+    "actual_price",
+    "best_bid",
+    "best_ask",
+    "mid",
+    "spread",
+];
+
+/// Share counts, scaled against the index in blind replays.
+const QUANTITY_FIELDS: &[&str] = &["quantity", "total_buy_quantity", "total_sell_quantity"];
+
+/// What a blind asset's reference close reads as.
+const BLIND_INDEX: Decimal = Decimal::ONE_HUNDRED;
+
+pub const SCENARIO_COLUMNS: &str = "s.id, s.title, s.description, s.learning_goals,
+     s.start_time, s.end_time, s.starting_cash, s.blind, s.is_active,
+     ARRAY(SELECT sa.asset_id FROM replay_scenario_assets sa
+           WHERE sa.scenario_id = s.id ORDER BY sa.position) AS asset_ids,
+     ARRAY(SELECT a.symbol FROM replay_scenario_assets sa JOIN assets a ON a.id = sa.asset_id
+           WHERE sa.scenario_id = s.id ORDER BY sa.position) AS symbols,
+     s.created_by, s.created_at, s.updated_at";
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Scenario {
+    pub id: Uuid,
+    pub title: String,
+    pub description: String,
+    pub learning_goals: Vec<String>,
+    pub start_time: NaiveDateTime,
+    pub end_time: NaiveDateTime,
+    pub starting_cash: Decimal,
+    pub blind: bool,
+    pub is_active: bool,
+    pub asset_ids: Vec<Uuid>,
+    pub symbols: Vec<String>,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+pub async fn load(pool: &PgPool, scenario_id: Uuid) -> sqlx::Result<Option<Scenario>> {
+    sqlx::query_as::<_, Scenario>(&format!(
+        "SELECT {} FROM replay_scenarios s WHERE s.id = $1",
+        SCENARIO_COLUMNS
+    ))
+    .bind(scenario_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Every scenario (or only active ones), newest first.
+pub async fn list(pool: &PgPool, active_only: bool) -> sqlx::Result<Vec<Scenario>> {
+    sqlx::query_as::<_, Scenario>(&format!(
+        "SELECT {} FROM replay_scenarios s
+         WHERE s.is_active OR NOT $1
+         ORDER BY s.created_at DESC, s.id",
+        SCENARIO_COLUMNS
+    ))
+    .bind(active_only)
+    .fetch_all(pool)
+    .await
+}
+
+/// `Asset A` for the first asset of a blind replay, `Asset B` for the second, ...
+pub fn alias(position: usize) -> String {
+    format!("Asset {}", (b'A' + (position % 26) as u8) as char)
+}
+
+/// One asset of a blind session and its stand-ins.
+#[derive(Debug, Clone)]
+pub struct BlindAsset {
+    pub asset_id: Uuid,
+    pub alias_id: Uuid,
+    pub symbol: String,
+    pub alias: String,
+    /// The close shown as [`BLIND_INDEX`].
+    pub reference: Decimal,
+}
+
+/// Hides a blind session's dates, assets and price levels in what it sends.
+/// Prices and quantities are rewritten inside objects whose `asset_id` (or
+/// an enclosing one's) is a masked asset.
+#[derive(Debug, Clone)]
+pub struct BlindMask {
+    start: NaiveDateTime,
+    assets: Vec<BlindAsset>,
+}
+
+impl BlindMask {
+    pub fn new(start: NaiveDateTime, assets: Vec<BlindAsset>) -> Self {
+        Self { start, assets }
+    }
+
+    /// The replay time `t` as the student sees it.
+    pub fn time(&self, t: NaiveDateTime) -> NaiveDateTime {
+        blind_epoch() + (t - self.start)
+    }
+
+    /// The replay time behind a time the student sent.
+    pub fn real_time(&self, shown: NaiveDateTime) -> NaiveDateTime {
+        self.start + (shown - blind_epoch())
+    }
+
+    /// The asset behind a stand-in id; other ids pass through.
+    pub fn real_asset(&self, asset_id: Uuid) -> Uuid {
+        self.assets
+            .iter()
+            .find(|a| a.alias_id == asset_id)
+            .map_or(asset_id, |a| a.asset_id)
+    }
+
+    /// `asset_id`'s price `p` as the student sees it.
+    pub fn price(&self, asset_id: Uuid, p: Decimal) -> Decimal {
+        self.reference(asset_id)
+            .map_or(p, |r| (p * BLIND_INDEX / r).round_dp(4))
+    }
+
+    /// The price behind one the student sent for `asset_id`.
+    pub fn real_price(&self, asset_id: Uuid, shown: Decimal) -> Decimal {
+        self.reference(asset_id)
+            .map_or(shown, |r| (shown * r / BLIND_INDEX).round_dp(4))
+    }
+
+    /// `asset_id`'s quantity `q` as the student sees it.
+    pub fn quantity(&self, asset_id: Uuid, q: Decimal) -> Decimal {
+        self.reference(asset_id)
+            .map_or(q, |r| (q * r / BLIND_INDEX).round_dp(4))
+    }
+
+    /// The quantity behind one the student sent for `asset_id`.
+    pub fn real_quantity(&self, asset_id: Uuid, shown: Decimal) -> Decimal {
+        self.reference(asset_id)
+            .map_or(shown, |r| (shown * BLIND_INDEX / r).round_dp(4))
+    }
+
+    fn reference(&self, asset_id: Uuid) -> Option<Decimal> {
+        self.assets
+            .iter()
+            .find(|a| a.asset_id == asset_id)
+            .map(|a| a.reference)
+    }
+
+    /// Rewrite replay times, asset ids, symbols, prices and quantities
+    /// anywhere in `value`.
+    pub fn apply(&self, value: &mut Value) {
+        self.apply_for(value, None);
+    }
+
+    /// `asset` is the masked asset the enclosing object is about, if any.
+    fn apply_for(&self, value: &mut Value, asset: Option<Uuid>) {
+        match value {
+            Value::Object(map) => {
+                let asset = map
+                    .get("asset_id")
+                    .and_then(Value::as_str)
+                    .and_then(|s| self.assets.iter().find(|a| a.asset_id.to_string() == s))
+                    .map(|a| a.asset_id)
+                    .or(asset);
+                for (key, field) in map.iter_mut() {
+                    match (key.as_str(), asset) {
+                        (k, _) if TIME_FIELDS.contains(&k) => self.mask_time(field),
+                        (k, Some(id)) if PRICE_FIELDS.contains(&k) => {
+                            self.mask_number(field, |p| self.price(id, p))
+                        }
+                        (k, Some(id)) if QUANTITY_FIELDS.contains(&k) => {
+                            self.mask_number(field, |q| self.quantity(id, q))
+                        }
+                        // RSI reads the same at any price level
+                        ("indicators", Some(id)) => {
+                            if let Value::Object(values) = field {
+                                for (name, v) in values.iter_mut() {
+                                    if !name.starts_with("rsi") {
+                                        self.mask_number(v, |p| self.price(id, p));
+                                    }
+                                }
+                            }
+                        }
+                        ("asset_id", _) => self.mask_asset(field),
+                        ("symbol", _) => self.mask_symbol(field),
+                        ("asset_ids" | "symbols", _) => {
+                            if let Value::Array(items) = field {
+                                for item in items {
+                                    self.mask_asset(item);
+                                    self.mask_symbol(item);
+                                }
+                            }
+                        }
+                        _ => self.apply_for(field, asset),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.apply_for(item, asset)),
+            _ => {}
+        }
+    }
+
+    fn mask_number(&self, field: &mut Value, f: impl Fn(Decimal) -> Decimal) {
+        let Some(n) = field.as_f64().and_then(|n| Decimal::try_from(n).ok()) else {
+            return;
+        };
+        if let Some(shown) = f(n).normalize().to_f64() {
+            *field = Value::from(shown);
+        }
+    }
+
+    /// Times arrive as serde's `2026-10-16T05:00:00` or `to_string`'s
+    /// `2026-10-16 05:00:00`; the masked time keeps the format.
+    fn mask_time(&self, field: &mut Value) {
+        let Value::String(s) = field else { return };
+        if let Ok(t) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f") {
+            *field = serde_json::to_value(self.time(t)).unwrap_or(Value::Null);
+        } else if let Ok(t) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S") {
+            *s = self.time(t).to_string();
+        }
+    }
+
+    fn mask_asset(&self, field: &mut Value) {
+        let Value::String(s) = field else { return };
+        if let Some(asset) = self.assets.iter().find(|a| a.asset_id.to_string() == *s) {
+            *s = asset.alias_id.to_string();
+        }
+    }
+
+    fn mask_symbol(&self, field: &mut Value) {
+        let Value::String(s) = field else { return };
+        if let Some(asset) = self.assets.iter().find(|a| a.symbol == *s) {
+            *s = asset.alias.clone();
+        }
+    }
+}
+
+/// The mask for a session of a blind scenario that hasn't finished; `None`
+/// for every other session.
+pub async fn blind_mask(pool: &PgPool, replay_id: Uuid) -> sqlx::Result<Option<BlindMask>> {
+    let window: Option<(NaiveDateTime, NaiveDateTime)> = sqlx::query_as(
+        "SELECT rs.start_time, rs.end_time FROM replay_sessions rs
+         JOIN replay_scenarios s ON s.id = rs.scenario_id
+         WHERE rs.id = $1 AND s.blind AND NOT rs.finished",
+    )
+    .bind(replay_id)
+    .fetch_optional(pool)
+    .await?;
+    let Some((start, end)) = window else {
+        return Ok(None);
+    };
+
+    let assets: Vec<(Uuid, Uuid, String)> = sqlx::query_as(
+        "SELECT rsa.asset_id, rsa.alias_id, a.symbol FROM replay_session_assets rsa
+         JOIN assets a ON a.id = rsa.asset_id
+         WHERE rsa.replay_id = $1 ORDER BY rsa.position",
+    )
+    .bind(replay_id)
+    .fetch_all(pool)
+    .await?;
+    let mut blind = Vec::with_capacity(assets.len());
+    for (i, (asset_id, alias_id, symbol)) in assets.into_iter().enumerate() {
+        // The close going into the window, or its last one without history before it
+        let reference = match price_history::close_at_or_before(pool, asset_id, start).await? {
+            Some(close) => Some(close),
+            None => price_history::close_at_or_before(pool, asset_id, end).await?,
+        }
+        .filter(|c| *c > Decimal::ZERO)
+        .unwrap_or(BLIND_INDEX);
+        blind.push(BlindAsset { asset_id, alias_id, symbol, alias: alias(i), reference });
+    }
+    Ok(Some(BlindMask::new(start, blind)))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct LeaderboardEntry {
+    pub rank: i32,
+    pub user: String,
+    pub total_return: f64,
+    pub final_equity: f64,
+    pub max_drawdown: f64,
+    pub finished_at: NaiveDateTime,
+}
+
+/// Students ranked by the return of their first session of the scenario,
+/// counted once it has finished and its report is stored.
+pub async fn leaderboard(pool: &PgPool, scenario_id: Uuid, limit: i64) -> sqlx::Result<Vec<LeaderboardEntry>> {
+    sqlx::query_as::<_, LeaderboardEntry>(
+        r#"
+        WITH first_attempt AS (
+            SELECT DISTINCT ON (rs.user_id) rs.id, rs.user_id, rs.finished
+            FROM replay_sessions rs
+            WHERE rs.scenario_id = $1
+            ORDER BY rs.user_id, rs.created_at, rs.id
+        ),
+        ranked AS (
+            SELECT
+                f.user_id,
+                (rr.report->>'total_return')::float8 AS total_return,
+                (rr.report->>'final_equity')::float8 AS final_equity,
+                (rr.report->>'max_drawdown')::float8 AS max_drawdown,
+                rr.generated_at AS finished_at
+            FROM first_attempt f
+            JOIN replay_reports rr ON rr.replay_id = f.id
+            WHERE f.finished
+        )
+        SELECT
+            RANK() OVER (ORDER BY r.total_return DESC)::int AS rank,
+            up.display_name AS user,
+            r.total_return, r.final_equity, r.max_drawdown, r.finished_at
+        FROM ranked r
+        INNER JOIN user_profiles up ON up.user_id = r.user_id
+        ORDER BY rank, r.finished_at
+        LIMIT $2
+        "#,
+    )
+    .bind(scenario_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn mask() -> BlindMask {
+        let start = NaiveDateTime::parse_from_str("2026-10-16 05:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        BlindMask::new(
+            start,
+            vec![
+                BlindAsset {
+                    asset_id: Uuid::from_u128(1),
+                    alias_id: Uuid::from_u128(101),
+                    symbol: "INFY".to_string(),
+                    alias: alias(0),
+                    reference: Decimal::new(1500, 0),
+                },
+                BlindAsset {
+                    asset_id: Uuid::from_u128(2),
+                    alias_id: Uuid::from_u128(102),
+                    symbol: "TCS".to_string(),
+                    alias: alias(1),
+                    reference: Decimal::new(4000, 0),
+                },
+            ],
+        )
+    }
+
+    #[test]
+    fn hides_replay_times_assets_and_symbols() {
+        let mut frame = json!({
+            "type": "tick",
+            "asset_id": Uuid::from_u128(2),
+            "symbol": "TCS",
+            "timestamp": "2026-10-16 05:30:00",
+            "order": {"active_from": "2026-10-16T05:01:00", "filled_at": null, "created_at": "2026-10-18T09:00:00"},
+            "asset_ids": [Uuid::from_u128(1), Uuid::from_u128(2)],
+            "symbols": ["INFY", "TCS"],
+        });
+        mask().apply(&mut frame);
+        assert_eq!(
+            frame,
+            json!({
+                "type": "tick",
+                "asset_id": Uuid::from_u128(102),
+                "symbol": "Asset B",
+                "timestamp": "2000-01-03 00:30:00",
+                "order": {"active_from": "2000-01-03T00:01:00", "filled_at": null, "created_at": "2026-10-18T09:00:00"},
+                "asset_ids": [Uuid::from_u128(101), Uuid::from_u128(102)],
+                "symbols": ["Asset A", "Asset B"],
+            })
+        );
+    }
+
+    #[test]
+    fn maps_student_input_back() {
+        let m = mask();
+        let shown = NaiveDateTime::parse_from_str("2000-01-03 00:45:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(m.real_time(shown).to_string(), "2026-10-16 05:45:00");
+        assert_eq!(m.time(m.real_time(shown)), shown);
+        assert_eq!(m.real_asset(Uuid::from_u128(101)), Uuid::from_u128(1));
+        assert_eq!(m.real_asset(Uuid::from_u128(7)), Uuid::from_u128(7));
+
+        let infy = Uuid::from_u128(1);
+        assert_eq!(m.real_price(infy, Decimal::new(102, 0)), Decimal::new(1530, 0));
+        assert_eq!(m.real_quantity(infy, Decimal::new(150, 0)), Decimal::new(10, 0));
+        assert_eq!(m.quantity(infy, m.real_quantity(infy, Decimal::new(150, 0))), Decimal::new(150, 0));
+        assert_eq!(m.price(Uuid::from_u128(7), Decimal::new(1530, 0)), Decimal::new(1530, 0));
+    }
+
+    #[test]
+    fn indexes_prices_and_scales_quantities_per_asset() {
+        let mut frame = json!({
+            "portfolio": {
+                "cash": 85000.0,
+                "positions": [
+                    {"asset_id": Uuid::from_u128(1), "quantity": 10.0, "avg_cost": 1500.0, "price": 1530.0, "market_value": 15300.0},
+                    {"asset_id": Uuid::from_u128(2), "quantity": 2.0, "avg_cost": 4000.0, "price": 3960.0, "market_value": 7920.0},
+                ],
+            },
+            "tick": {
+                "asset_id": Uuid::from_u128(2),
+                "price": 3960.0,
+                "indicators": {"sma_20": 4020.0, "rsi_14": 41.5},
+                "depth": {"bids": [{"price": 3959.0, "quantity": 40, "orders": 3}], "mid": 3960.0, "spread_bps": 5.0},
+            },
+        });
+        mask().apply(&mut frame);
+        assert_eq!(
+            frame,
+            json!({
+                "portfolio": {
+                    "cash": 85000.0,
+                    "positions": [
+                        {"asset_id": Uuid::from_u128(101), "quantity": 150.0, "avg_cost": 100.0, "price": 102.0, "market_value": 15300.0},
+                        {"asset_id": Uuid::from_u128(102), "quantity": 80.0, "avg_cost": 100.0, "price": 99.0, "market_value": 7920.0},
+                    ],
+                },
+                "tick": {
+                    "asset_id": Uuid::from_u128(102),
+                    "price": 99.0,
+                    "indicators": {"sma_20": 100.5, "rsi_14": 41.5},
+                    "depth": {"bids": [{"price": 98.975, "quantity": 1600.0, "orders": 3}], "mid": 99.0, "spread_bps": 5.0},
+                },
+            })
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! Tiered retention for `market_prices`.
+//!
+//! 1-minute candles older than `minute_days` are rolled up into 1-hour and
+//! 1-day buckets and then deleted; hourly rollups older than `hour_days` are
+//! deleted too (the daily rollup already covers them). Daily rollups are kept
+//! forever. Work is done one UTC day per transaction, so a crash never leaves
+//! minute rows deleted without their rollups. Order-book snapshots are simply
+//! deleted after `depth_days`.
+
+use anyhow::Result;
+use chrono::{Duration as ChronoDuration, NaiveDateTime, Utc};
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{error, info};
+
+use crate::services::price_history::Resolution;
+
+/// How often the retention job wakes up.
+const RETENTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+pub struct RetentionJob {
+    pool: PgPool,
+    minute_days: i64,
+    hour_days: i64,
+    depth_days: i64,
+}
+
+impl RetentionJob {
+    pub fn new(pool: PgPool, minute_days: i64, hour_days: i64, depth_days: i64) -> Self {
+        Self {
+            pool,
+            minute_days,
+            hour_days,
+            depth_days,
+        }
+    }
+
+    /// Main loop — runs once at startup, then hourly.
+    pub async fn run(self) {
+        info!(
+            "Retention job started (1m kept {} days, 1h kept {} days)",
+            self.minute_days, self.hour_days
+        );
+        loop {
+            if let Err(e) = self.tick().await {
+                error!("Retention job error: {:?}", e);
+            }
+            tokio::time::sleep(RETENTION_INTERVAL).await;
+        }
+    }
+
+    async fn tick(&self) -> Result<()> {
+        let today = Utc::now().naive_utc().date().and_hms_opt(0, 0, 0).expect("midnight");
+        self.roll_up_minutes(today - ChronoDuration::days(self.minute_days))
+            .await?;
+        self.prune_hours(today - ChronoDuration::days(self.hour_days))
+            .await?;
+        self.prune_depth(today - ChronoDuration::days(self.depth_days))
+            .await?;
+        Ok(())
+    }
+
+    /// Roll every whole UTC day of minute candles before `cutoff` into the
+    /// hourly and daily tables, then delete those minutes.
+    async fn roll_up_minutes(&self, cutoff: NaiveDateTime) -> Result<()> {
+        let oldest: Option<NaiveDateTime> =
+            sqlx::query_scalar("SELECT MIN(timestamp) FROM market_prices")
+                .fetch_one(&self.pool)
+                .await?;
+        let Some(oldest) = oldest else {
+            return Ok(());
+        };
+
+        let mut day = oldest.date().and_hms_opt(0, 0, 0).expect("midnight");
+        let (mut days, mut deleted) = (0, 0);
+        while day < cutoff {
+            let next = day + ChronoDuration::days(1);
+            let mut tx = self.pool.begin().await?;
+            for resolution in [Resolution::Hour, Resolution::Day] {
+                sqlx::query(&rollup_sql(resolution))
+                    .bind(day)
+                    .bind(next)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            deleted += sqlx::query(
+                "DELETE FROM market_prices WHERE timestamp >= $1 AND timestamp < $2",
+            )
+            .bind(day)
+            .bind(next)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            tx.commit().await?;
+            days += 1;
+            day = next;
+        }
+
+        if deleted > 0 {
+            info!(
+                "Retention: rolled up {} day(s) of 1-minute candles, deleted {} rows",
+                days, deleted
+            );
+        }
+        Ok(())
+    }
+
+    async fn prune_hours(&self, cutoff: NaiveDateTime) -> Result<()> {
+        let deleted = sqlx::query("DELETE FROM market_prices_1h WHERE timestamp < $1")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        if deleted > 0 {
+            info!("Retention: deleted {} hourly rollup rows", deleted);
+        }
+        Ok(())
+    }
+
+    async fn prune_depth(&self, cutoff: NaiveDateTime) -> Result<()> {
+        let deleted = sqlx::query("DELETE FROM market_depth_snapshots WHERE timestamp < $1")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        if deleted > 0 {
+            info!("Retention: deleted {} order-book snapshots", deleted);
+        }
+        Ok(())
+    }
+}
+
+/// Aggregate minute rows in `[$1, $2)` into `resolution` buckets. A bucket
+/// that already exists (late minute rows) is merged rather than replaced.
+fn rollup_sql(resolution: Resolution) -> String {
+    format!(
+        r#"
+        INSERT INTO {table} (asset_id, timestamp, open, high, low, close, volume)
+        SELECT asset_id,
+               date_trunc('{unit}', timestamp) AS bucket,
+               (array_agg(open ORDER BY timestamp ASC))[1],
+               MAX(high),
+               MIN(low),
+               (array_agg(close ORDER BY timestamp DESC))[1],
+               SUM(volume)
+        FROM market_prices
+        WHERE timestamp >= $1 AND timestamp < $2
+        GROUP BY asset_id, bucket
+        ON CONFLICT (asset_id, timestamp) DO UPDATE SET
+            high   = GREATEST({table}.high, EXCLUDED.high),
+            low    = LEAST({table}.low, EXCLUDED.low),
+            close  = EXCLUDED.close,
+            volume = COALESCE({table}.volume, 0) + COALESCE(EXCLUDED.volume, 0)
+        "#,
+        table = resolution.table(),
+        unit = resolution.trunc_unit(),
+    )
+}
@@ -2,56 +2,97 @@
 //!
 //! Runs on every startup. Guarantees the app is never "empty":
 //!   1. Required assets exist (idempotent upsert by symbol).
-//!   2. At least ~7 days of deterministic 5-minute OHLC history exists per asset.
+//!   2. Every asset has seed price-model parameters, `SEED_HISTORY_DAYS` of
+//!      1-minute OHLC history, and no missing buckets over the gap-repair
+//!      lookback.
 //!   3. At least 3 active contests (end_time > now) exist across all 3 tracks,
 //!      each with their asset pool wired. If none exist, fresh ones are created
-//!      with start/end times anchored to "now" so the demo flow is always live.
+//!      with start/end times anchored to "now" so the demo flow is always live,
+//!      or to the next trading session when the pool's exchange is closed.
 //!
 //! Safe to re-run — it only fills gaps, never destroys user data.
 
 use anyhow::Result;
-use chrono::{Duration as ChronoDuration, Utc};
+use chrono::{Duration as ChronoDuration, NaiveDateTime, Utc};
 use rust_decimal::Decimal;
 use sqlx::PgPool;
-use tracing::info;
+use std::collections::HashMap;
+use tracing::{info, warn};
 use uuid::Uuid;
 
-use crate::services::market_data::{backfill_history, SeedAsset};
+use crate::services::gap_repair::Trigger;
+use crate::services::market_data::{backfill_range, SeedAsset};
+use crate::services::{GapRepairer, TradingCalendar};
 
 /// Canonical MVP asset catalogue. Matches the three contest tracks:
-///   crypto : BTC, ETH, SOL
-///   etf    : NIFTYBEES, BANKBEES
+///   crypto : BTC, ETH, SOL                (quoted in USD)
+///   etf    : NIFTYBEES, BANKBEES          (quoted in INR)
 ///   index  : NIFTY50, BANKNIFTY
 ///   equity : INFY, TCS, RELIANCE   (used in basket contests)
-const ASSETS: &[(&str, &str, &str, &str)] = &[
-    ("BTC",       "Bitcoin",                      "crypto", "BINANCE"),
-    ("ETH",       "Ethereum",                     "crypto", "BINANCE"),
-    ("SOL",       "Solana",                       "crypto", "BINANCE"),
-    ("NIFTY50",   "Nifty 50 Index",               "index",  "NSE"),
-    ("BANKNIFTY", "Bank Nifty Index",             "index",  "NSE"),
-    ("INFY",      "Infosys Ltd",                  "equity", "NSE"),
-    ("TCS",       "Tata Consultancy Services",    "equity", "NSE"),
-    ("RELIANCE",  "Reliance Industries",          "equity", "NSE"),
-    ("NIFTYBEES", "Nifty BeES ETF",               "etf",    "NSE"),
-    ("BANKBEES",  "Bank BeES ETF",                "etf",    "NSE"),
+/// plus the USDINR FX series used to convert between the two currencies.
+/// Only inserted when missing; the admin asset API owns them afterwards.
+const ASSETS: &[(&str, &str, &str, &str, &str)] = &[
+    ("BTC",       "Bitcoin",                      "crypto", "BINANCE", "USD"),
+    ("ETH",       "Ethereum",                     "crypto", "BINANCE", "USD"),
+    ("SOL",       "Solana",                       "crypto", "BINANCE", "USD"),
+    ("NIFTY50",   "Nifty 50 Index",               "index",  "NSE",     "INR"),
+    ("BANKNIFTY", "Bank Nifty Index",             "index",  "NSE",     "INR"),
+    ("INFY",      "Infosys Ltd",                  "equity", "NSE",     "INR"),
+    ("TCS",       "Tata Consultancy Services",    "equity", "NSE",     "INR"),
+    ("RELIANCE",  "Reliance Industries",          "equity", "NSE",     "INR"),
+    ("NIFTYBEES", "Nifty BeES ETF",               "etf",    "NSE",     "INR"),
+    ("BANKBEES",  "Bank BeES ETF",                "etf",    "NSE",     "INR"),
+    ("USDINR",    "US Dollar / Indian Rupee",     "fx",     "FX",      "INR"),
+];
+
+/// Kite instrument tokens set on assets the seeder creates.
+const INSTRUMENT_TOKENS: &[(&str, i64)] = &[("NIFTY50", 256265), ("INFY", 408065)];
+
+/// Default seed price-model parameters, keyed by symbol. Only inserted when an
+/// asset has no `asset_price_models` row yet, so tuned values in the DB win.
+///   (symbol, base_price, drift, volatility, regime_mult, regime_prob,
+///    jumps/day, jump_mean, jump_stddev, factor, factor_correlation)
+#[allow(clippy::type_complexity)]
+const PRICE_MODELS: &[(&str, f64, f64, f64, f64, f64, f64, f64, f64, &str, f64)] = &[
+    ("BTC",       65_000.0, 0.20, 0.50, 1.6, 0.15, 0.15, -0.004, 0.020, "crypto", 0.85),
+    ("ETH",        3_500.0, 0.20, 0.65, 1.6, 0.15, 0.15, -0.004, 0.025, "crypto", 0.85),
+    ("SOL",          150.0, 0.25, 0.80, 1.6, 0.15, 0.20, -0.006, 0.030, "crypto", 0.75),
+    ("NIFTY50",   22_000.0, 0.11, 0.14, 1.8, 0.15, 0.03,  0.000, 0.012, "nse",    0.95),
+    ("BANKNIFTY", 48_000.0, 0.12, 0.18, 1.8, 0.15, 0.04,  0.000, 0.015, "nse",    0.85),
+    ("INFY",       1_500.0, 0.10, 0.24, 1.8, 0.15, 0.06,  0.000, 0.030, "nse",    0.60),
+    ("TCS",        3_800.0, 0.10, 0.22, 1.8, 0.15, 0.05,  0.000, 0.025, "nse",    0.60),
+    ("RELIANCE",   2_800.0, 0.12, 0.26, 1.8, 0.15, 0.06,  0.000, 0.030, "nse",    0.65),
+    ("NIFTYBEES",    250.0, 0.11, 0.14, 1.8, 0.15, 0.03,  0.000, 0.012, "nse",    0.95),
+    ("BANKBEES",     500.0, 0.12, 0.18, 1.8, 0.15, 0.04,  0.000, 0.015, "nse",    0.85),
+    ("USDINR",        83.0, 0.02, 0.05, 1.5, 0.10, 0.02,  0.000, 0.004, "fx",     0.00),
 ];
 
 /// Full bootstrap. Logs what it did.
-pub async fn bootstrap(pool: &PgPool) -> Result<()> {
+pub async fn bootstrap(
+    pool: &PgPool,
+    calendar: &TradingCalendar,
+    repairer: &GapRepairer,
+    history_days: i64,
+) -> Result<()> {
     ensure_assets(pool).await?;
+    ensure_price_models(pool).await?;
     let assets = load_assets(pool).await?;
-    ensure_history(pool, &assets).await?;
-    ensure_contests(pool, &assets).await?;
+    ensure_history(pool, calendar, repairer, &assets, history_days).await?;
+    ensure_contests(pool, calendar, &assets).await?;
     Ok(())
 }
 
 async fn ensure_assets(pool: &PgPool) -> Result<()> {
     let mut inserted = 0;
-    for (symbol, name, asset_type, exchange) in ASSETS {
+    for (symbol, name, asset_type, exchange, currency) in ASSETS {
+        let token = INSTRUMENT_TOKENS
+            .iter()
+            .find(|(s, _)| s == symbol)
+            .map(|(_, token)| *token);
         let res = sqlx::query(
             r#"
-            INSERT INTO assets (symbol, name, asset_type, exchange, is_active)
-            VALUES ($1, $2, $3, $4, true)
+            INSERT INTO assets (symbol, name, asset_type, exchange, currency, instrument_token, is_active)
+            VALUES ($1, $2, $3, $4, $5, $6, true)
             ON CONFLICT (symbol) DO NOTHING
             "#,
         )
@@ -59,6 +100,8 @@ async fn ensure_assets(pool: &PgPool) -> Result<()> {
         .bind(name)
         .bind(asset_type)
         .bind(exchange)
+        .bind(currency)
+        .bind(token)
         .execute(pool)
         .await?;
         inserted += res.rows_affected();
@@ -69,34 +112,114 @@ async fn ensure_assets(pool: &PgPool) -> Result<()> {
     Ok(())
 }
 
-async fn load_assets(pool: &PgPool) -> Result<Vec<SeedAsset>> {
-    let rows = sqlx::query_as::<_, SeedAsset>(
-        "SELECT id, symbol FROM assets WHERE is_active = true",
-    )
-    .fetch_all(pool)
-    .await?;
-    Ok(rows)
+async fn ensure_price_models(pool: &PgPool) -> Result<()> {
+    let mut inserted = 0;
+    for (symbol, base, drift, vol, regime_mult, regime_prob, jumps, jump_mean, jump_sd, factor, rho) in
+        PRICE_MODELS
+    {
+        let res = sqlx::query(
+            r#"
+            INSERT INTO asset_price_models
+                (asset_id, base_price, drift, volatility, regime_multiplier, regime_probability,
+                 jumps_per_day, jump_mean, jump_stddev, factor, factor_correlation)
+            SELECT id, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11
+            FROM assets WHERE symbol = $1
+            ON CONFLICT (asset_id) DO NOTHING
+            "#,
+        )
+        .bind(symbol)
+        .bind(base)
+        .bind(drift)
+        .bind(vol)
+        .bind(regime_mult)
+        .bind(regime_prob)
+        .bind(jumps)
+        .bind(jump_mean)
+        .bind(jump_sd)
+        .bind(factor)
+        .bind(rho)
+        .execute(pool)
+        .await?;
+        inserted += res.rows_affected();
+    }
+    if inserted > 0 {
+        info!("Seeder: inserted {} price model(s)", inserted);
+    }
+    Ok(())
 }
 
-async fn ensure_history(pool: &PgPool, assets: &[SeedAsset]) -> Result<()> {
-    // Count existing rows; backfill if sparse.
-    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM market_prices")
-        .fetch_one(pool)
-        .await?;
+async fn load_assets(pool: &PgPool) -> Result<Vec<SeedAsset>> {
+    SeedAsset::load_active(pool).await
+}
 
-    // 3 days * 1440 buckets/day * 10 assets ≈ 43k rows expected.
-    if count < 20_000 {
+async fn ensure_history(
+    pool: &PgPool,
+    calendar: &TradingCalendar,
+    repairer: &GapRepairer,
+    assets: &[SeedAsset],
+    days: i64,
+) -> Result<()> {
+    // Bulk-seed older history for assets whose candles start later than the
+    // configured depth (a fresh database, a new asset, a larger depth). The
+    // repair window is left to the active provider below.
+    let (window_start, _) = repairer.window();
+    let start = window_start.min(Utc::now().naive_utc() - ChronoDuration::days(days));
+    let mut shallow = Vec::new();
+    for asset in assets {
+        let earliest: Option<NaiveDateTime> =
+            sqlx::query_scalar("SELECT MIN(timestamp) FROM market_prices WHERE asset_id = $1")
+                .bind(asset.id)
+                .fetch_one(pool)
+                .await?;
+        let mut first_open = start;
+        while first_open < window_start && !asset.is_trading(calendar, first_open) {
+            first_open += ChronoDuration::minutes(1);
+        }
+        if first_open < window_start && earliest.is_none_or(|e| e > first_open) {
+            shallow.push(asset.clone());
+        }
+    }
+    if !shallow.is_empty() {
         info!(
-            "Seeder: market_prices sparse ({} rows), backfilling 3 days of 1-min candles...",
-            count
+            "Seeder: backfilling {} asset(s) from {} to the repair window...",
+            shallow.len(),
+            start
         );
-        let written = backfill_history(pool, assets, 3).await?;
+        let written =
+            backfill_range(pool, calendar, &shallow, start, window_start - ChronoDuration::minutes(1)).await?;
         info!("Seeder: backfilled {} candle rows", written);
     }
+
+    // Per-asset: catches newly added assets and outages, not just an empty table.
+    let reports = repairer.repair_all(assets, Trigger::Bootstrap).await?;
+    let filled: u64 = reports.iter().map(|r| r.filled_rows).sum();
+    if filled > 0 {
+        info!(
+            "Seeder: repaired {} asset(s), backfilled {} candle rows",
+            reports.len(),
+            filled
+        );
+    }
     Ok(())
 }
 
-async fn ensure_contests(pool: &PgPool, assets: &[SeedAsset]) -> Result<()> {
+/// A demo contest the seeder creates, with its window relative to "now".
+struct ContestPlan {
+    title: &'static str,
+    track: &'static str,
+    entry_fee: Decimal,
+    virtual_capital: Decimal,
+    starts_in_minutes: i64,
+    duration_minutes: i64,
+    status: &'static str,
+    symbols: &'static [&'static str],
+}
+
+async fn ensure_contests(
+    pool: &PgPool,
+    calendar: &TradingCalendar,
+    assets: &[SeedAsset],
+) -> Result<()> {
     // Do we have at least one contest whose end_time is still in the future?
     let future_count: i64 = sqlx::query_scalar(
         "SELECT COUNT(*) FROM contests WHERE end_time > NOW()
@@ -111,58 +234,94 @@ async fn ensure_contests(pool: &PgPool, assets: &[SeedAsset]) -> Result<()> {
 
     info!("Seeder: fewer than 3 active contests — creating fresh ones");
 
-    let by_symbol: std::collections::HashMap<&str, Uuid> = assets
-        .iter()
-        .map(|a| (a.symbol.as_str(), a.id))
-        .collect();
+    let by_symbol: HashMap<&str, &SeedAsset> =
+        assets.iter().map(|a| (a.symbol.as_str(), a)).collect();
 
     let now = Utc::now().naive_utc();
 
     // Contest 1 — Crypto, joins NOW, starts in 5 min, ends in 1h05m.
     // This is the "happy path" you can drive end-to-end right after login.
-    let c1 = insert_contest(
-        pool,
-        "BTC vs ETH — Quick Battle",
-        "crypto",
-        Decimal::new(5000, 2),    // 50.00 entry
-        Decimal::new(10000000, 2), // 100,000.00 virtual capital
-        now,                               // joining open right now
-        now + ChronoDuration::minutes(5),  // starts in 5 min
-        now + ChronoDuration::minutes(65), // ends 1h later
-        "joining_open",
-    )
-    .await?;
-    link_assets(pool, c1, &["BTC", "ETH"], &by_symbol).await?;
-
     // Contest 2 — ETF, upcoming, joins open in 10 min.
-    let c2 = insert_contest(
-        pool,
-        "Index Fund Duel",
-        "etf",
-        Decimal::new(3000, 2),
-        Decimal::new(10000000, 2),
-        now + ChronoDuration::minutes(10),
-        now + ChronoDuration::minutes(20),
-        now + ChronoDuration::minutes(80),
-        "upcoming",
-    )
-    .await?;
-    link_assets(pool, c2, &["NIFTYBEES", "BANKBEES"], &by_symbol).await?;
-
     // Contest 3 — Basket, joining_open, longer window so multiple users can join.
-    let c3 = insert_contest(
-        pool,
-        "Blue Chip Trio",
-        "basket",
-        Decimal::new(7500, 2),
-        Decimal::new(15000000, 2),
-        now,
-        now + ChronoDuration::minutes(15),
-        now + ChronoDuration::minutes(90),
-        "joining_open",
-    )
-    .await?;
-    link_assets(pool, c3, &["INFY", "TCS", "RELIANCE"], &by_symbol).await?;
+    //
+    // NSE pools only trade 09:15–15:30 IST, so their windows are moved to the
+    // next session that can hold them when "now" falls outside market hours.
+    let plans = [
+        ContestPlan {
+            title: "BTC vs ETH — Quick Battle",
+            track: "crypto",
+            entry_fee: Decimal::new(5000, 2),           // 50.00 entry
+            virtual_capital: Decimal::new(10000000, 2), // 100,000.00 virtual capital
+            starts_in_minutes: 5,
+            duration_minutes: 60,
+            status: "joining_open",
+            symbols: &["BTC", "ETH"],
+        },
+        ContestPlan {
+            title: "Index Fund Duel",
+            track: "etf",
+            entry_fee: Decimal::new(3000, 2),
+            virtual_capital: Decimal::new(10000000, 2),
+            starts_in_minutes: 20,
+            duration_minutes: 60,
+            status: "upcoming",
+            symbols: &["NIFTYBEES", "BANKBEES"],
+        },
+        ContestPlan {
+            title: "Blue Chip Trio",
+            track: "basket",
+            entry_fee: Decimal::new(7500, 2),
+            virtual_capital: Decimal::new(15000000, 2),
+            starts_in_minutes: 15,
+            duration_minutes: 75,
+            status: "joining_open",
+            symbols: &["INFY", "TCS", "RELIANCE"],
+        },
+    ];
+
+    for plan in plans {
+        let exchanges: Vec<&str> = plan
+            .symbols
+            .iter()
+            .filter_map(|s| by_symbol.get(s))
+            .filter_map(|a| a.exchange.as_deref())
+            .collect();
+        let duration = ChronoDuration::minutes(plan.duration_minutes);
+        let planned = now + ChronoDuration::minutes(plan.starts_in_minutes);
+
+        let (start, status) = match calendar.first_fit(&exchanges, planned, duration) {
+            Some(start) if start == planned => (start, plan.status),
+            Some(start) => {
+                warn!(
+                    "Seeder: markets closed for '{}' at {}; scheduling it for {} instead",
+                    plan.title, planned, start
+                );
+                // Far-off contests wait in `upcoming`; the executor opens
+                // joining 15 minutes before start.
+                (start, "upcoming")
+            }
+            None => {
+                warn!(
+                    "Seeder: no trading session fits '{}' ({} min) in the next weeks; skipping",
+                    plan.title, plan.duration_minutes
+                );
+                continue;
+            }
+        };
+
+        let id = insert_contest(
+            pool,
+            plan.title,
+            plan.track,
+            plan.entry_fee,
+            plan.virtual_capital,
+            start,
+            start + duration,
+            status,
+        )
+        .await?;
+        link_assets(pool, id, plan.symbols, &by_symbol).await?;
+    }
 
     Ok(())
 }
@@ -174,7 +333,6 @@ async fn insert_contest(
     track: &str,
     entry_fee: Decimal,
     virtual_capital: Decimal,
-    _join_open_time: chrono::NaiveDateTime,
     start_time: chrono::NaiveDateTime,
     end_time: chrono::NaiveDateTime,
     status: &str,
@@ -205,11 +363,11 @@ async fn link_assets(
     pool: &PgPool,
     contest_id: Uuid,
     symbols: &[&str],
-    by_symbol: &std::collections::HashMap<&str, Uuid>,
+    by_symbol: &HashMap<&str, &SeedAsset>,
 ) -> Result<()> {
     for sym in symbols {
         let asset_id = match by_symbol.get(sym) {
-            Some(id) => *id,
+            Some(a) => a.id,
             None => {
                 tracing::warn!("Seeder: asset '{}' not found; skipping link", sym);
                 continue;
@@ -0,0 +1,326 @@
+//! Data-quality checks for live ticks.
+//!
+//! Every tick from the feed is checked against the last accepted tick of its
+//! instrument before it may touch `market_prices`. Suspect ticks are written
+//! to `quarantined_ticks` with the rules they broke, and stay there until an
+//! admin approves (merges them into the minute candle) or rejects them.
+//!
+//! A genuine repricing (news, circuit) would otherwise quarantine everything
+//! after it, so once `CONFIRM_TICKS` consecutive jump-only ticks agree on a
+//! new level the instrument re-anchors there.
+
+use chrono::{Duration, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::VecDeque;
+use uuid::Uuid;
+
+/// Log returns kept per instrument for the sigma rule.
+const RETURN_WINDOW: usize = 120;
+/// The sigma rule only applies once this many returns are known.
+const MIN_SIGMA_SAMPLES: usize = 30;
+/// Consecutive agreeing jump ticks that confirm a new price level.
+const CONFIRM_TICKS: u32 = 5;
+/// Day high/low from the exchange may lag the LTP by a tick or two.
+const OHLC_TOLERANCE: f64 = 0.001;
+
+#[derive(Debug, Clone, Copy)]
+pub struct QualityRules {
+    /// Largest accepted move from the last accepted price, in %.
+    pub max_jump_pct: f64,
+    /// Largest accepted log return in standard deviations of recent returns.
+    pub max_jump_sigma: f64,
+    /// How far ahead of our clock an exchange timestamp may be.
+    pub max_future_seconds: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Violation {
+    /// LTP is NaN/infinite or cannot be represented as a decimal.
+    InvalidPrice,
+    NonPositivePrice,
+    FutureTimestamp,
+    OutOfOrder,
+    JumpPct,
+    JumpSigma,
+    /// Day open/LTP outside the day's low–high range, or low above high.
+    OhlcInvariant,
+}
+
+impl Violation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Violation::InvalidPrice => "invalid_price",
+            Violation::NonPositivePrice => "non_positive_price",
+            Violation::FutureTimestamp => "future_timestamp",
+            Violation::OutOfOrder => "out_of_order",
+            Violation::JumpPct => "jump_pct",
+            Violation::JumpSigma => "jump_sigma",
+            Violation::OhlcInvariant => "ohlc_invariant",
+        }
+    }
+
+    fn is_jump(self) -> bool {
+        matches!(self, Violation::JumpPct | Violation::JumpSigma)
+    }
+}
+
+/// The parts of a feed tick the rules look at.
+#[derive(Debug, Clone, Copy)]
+pub struct TickSample {
+    pub ltp: f64,
+    pub exchange_time: Option<NaiveDateTime>,
+    /// Day open, high and low as reported by the exchange.
+    pub day_ohlc: Option<(f64, f64, f64)>,
+}
+
+/// Rolling per-instrument state: what the next tick is compared against.
+#[derive(Debug, Default)]
+pub struct InstrumentState {
+    last_price: Option<f64>,
+    last_exchange_time: Option<NaiveDateTime>,
+    returns: VecDeque<f64>,
+    /// Level and count of consecutive jump-only ticks seen since the last
+    /// accepted one.
+    pending_level: Option<(f64, u32)>,
+}
+
+impl InstrumentState {
+    /// State anchored at a previously stored price (e.g. after a restart).
+    pub fn anchored(price: f64) -> Self {
+        Self {
+            last_price: (price > 0.0).then_some(price),
+            ..Self::default()
+        }
+    }
+
+    pub fn last_price(&self) -> Option<f64> {
+        self.last_price
+    }
+
+    /// Check `tick` and update the state. Returns the violated rules; an
+    /// empty list means the tick was accepted.
+    pub fn observe(&mut self, rules: &QualityRules, tick: &TickSample, now: NaiveDateTime) -> Vec<Violation> {
+        let mut violations = self.check(rules, tick, now);
+
+        if !violations.is_empty() && violations.iter().all(|v| v.is_jump()) && self.confirms_new_level(rules, tick.ltp) {
+            self.returns.clear();
+            violations.clear();
+        }
+
+        if violations.is_empty() {
+            self.accept(tick);
+        } else if !violations.iter().all(|v| v.is_jump()) {
+            self.pending_level = None;
+        }
+        violations
+    }
+
+    fn check(&self, rules: &QualityRules, tick: &TickSample, now: NaiveDateTime) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let price = tick.ltp;
+
+        if !price.is_finite() {
+            violations.push(Violation::InvalidPrice);
+        } else if price <= 0.0 {
+            violations.push(Violation::NonPositivePrice);
+        }
+
+        if let Some(at) = tick.exchange_time {
+            if at > now + Duration::seconds(rules.max_future_seconds) {
+                violations.push(Violation::FutureTimestamp);
+            }
+            if self.last_exchange_time.is_some_and(|last| at < last) {
+                violations.push(Violation::OutOfOrder);
+            }
+        }
+
+        if let Some((open, high, low)) = tick.day_ohlc {
+            // Before the open the exchange sends zeros; nothing to check.
+            if high > 0.0 && low > 0.0 {
+                let within = |x: f64| x >= low * (1.0 - OHLC_TOLERANCE) && x <= high * (1.0 + OHLC_TOLERANCE);
+                if low > high || (open > 0.0 && !within(open)) || (price > 0.0 && !within(price)) {
+                    violations.push(Violation::OhlcInvariant);
+                }
+            }
+        }
+
+        if let (Some(last), true) = (self.last_price, price.is_finite() && price > 0.0) {
+            if ((price / last - 1.0) * 100.0).abs() > rules.max_jump_pct {
+                violations.push(Violation::JumpPct);
+            }
+            if let Some(sigma) = self.sigma() {
+                if sigma > 0.0 && (price / last).ln().abs() > rules.max_jump_sigma * sigma {
+                    violations.push(Violation::JumpSigma);
+                }
+            }
+        }
+
+        violations
+    }
+
+    /// Standard deviation of recent log returns, once enough are known.
+    fn sigma(&self) -> Option<f64> {
+        let n = self.returns.len();
+        if n < MIN_SIGMA_SAMPLES {
+            return None;
+        }
+        let mean = self.returns.iter().sum::<f64>() / n as f64;
+        let variance = self.returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+        Some(variance.sqrt())
+    }
+
+    /// Track a jump-only tick; true once enough of them agree (within the
+    /// jump threshold) on the same level.
+    fn confirms_new_level(&mut self, rules: &QualityRules, price: f64) -> bool {
+        let count = match self.pending_level {
+            Some((level, count)) if ((price / level - 1.0) * 100.0).abs() <= rules.max_jump_pct => count + 1,
+            _ => 1,
+        };
+        self.pending_level = Some((price, count));
+        count >= CONFIRM_TICKS
+    }
+
+    fn accept(&mut self, tick: &TickSample) {
+        if let Some(last) = self.last_price {
+            if self.returns.len() == RETURN_WINDOW {
+                self.returns.pop_front();
+            }
+            self.returns.push_back((tick.ltp / last).ln());
+        }
+        self.last_price = Some(tick.ltp);
+        if tick.exchange_time.is_some() {
+            self.last_exchange_time = tick.exchange_time;
+        }
+        self.pending_level = None;
+    }
+}
+
+/// A tick held back from `market_prices`.
+pub struct QuarantinedTick<'a> {
+    pub asset_id: Uuid,
+    pub instrument_token: i64,
+    pub received_at: NaiveDateTime,
+    pub exchange_time: Option<NaiveDateTime>,
+    /// `None` when the feed price cannot be stored (NaN, out of range).
+    pub price: Option<Decimal>,
+    pub volume: Option<Decimal>,
+    pub reference_price: Option<f64>,
+    pub violations: &'a [Violation],
+    /// The tick as received, for review.
+    pub raw: serde_json::Value,
+}
+
+pub async fn quarantine(pool: &PgPool, tick: &QuarantinedTick<'_>) -> sqlx::Result<()> {
+    let reasons: Vec<&str> = tick.violations.iter().map(|v| v.as_str()).collect();
+    sqlx::query(
+        r#"
+        INSERT INTO quarantined_ticks
+            (asset_id, instrument_token, received_at, exchange_time, price, volume,
+             reference_price, reasons, raw)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+    )
+    .bind(tick.asset_id)
+    .bind(tick.instrument_token)
+    .bind(tick.received_at)
+    .bind(tick.exchange_time)
+    .bind(tick.price)
+    .bind(tick.volume)
+    .bind(tick.reference_price.and_then(Decimal::from_f64_retain).map(|d| d.round_dp(4)))
+    .bind(&reasons)
+    .bind(&tick.raw)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    const RULES: QualityRules = QualityRules {
+        max_jump_pct: 10.0,
+        max_jump_sigma: 8.0,
+        max_future_seconds: 5,
+    };
+
+    fn at(secs: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 1, 6).unwrap().and_hms_opt(4, 0, 0).unwrap() + Duration::seconds(secs as i64)
+    }
+
+    fn tick(ltp: f64, secs: u32) -> TickSample {
+        TickSample {
+            ltp,
+            exchange_time: Some(at(secs)),
+            day_ohlc: None,
+        }
+    }
+
+    #[test]
+    fn rejects_bad_prices_and_timestamps() {
+        let mut state = InstrumentState::anchored(100.0);
+        let now = at(100);
+        assert_eq!(state.observe(&RULES, &tick(0.0, 10), now), vec![Violation::NonPositivePrice]);
+        assert_eq!(state.observe(&RULES, &tick(f64::NAN, 10), now), vec![Violation::InvalidPrice]);
+        assert_eq!(state.observe(&RULES, &tick(100.5, 200), now), vec![Violation::FutureTimestamp]);
+        assert!(state.observe(&RULES, &tick(100.5, 50), now).is_empty());
+        assert_eq!(state.observe(&RULES, &tick(100.6, 40), now), vec![Violation::OutOfOrder]);
+        assert_eq!(state.last_price(), Some(100.5));
+    }
+
+    #[test]
+    fn flags_ohlc_invariant_violations() {
+        let mut state = InstrumentState::default();
+        let mut t = tick(120.0, 1);
+        t.day_ohlc = Some((100.0, 110.0, 95.0));
+        assert_eq!(state.observe(&RULES, &t, at(1)), vec![Violation::OhlcInvariant]);
+        t.day_ohlc = Some((100.0, 90.0, 95.0));
+        assert!(state.observe(&RULES, &t, at(1)).contains(&Violation::OhlcInvariant));
+        t.ltp = 105.0;
+        t.day_ohlc = Some((100.0, 110.0, 95.0));
+        assert!(state.observe(&RULES, &t, at(1)).is_empty());
+        t.day_ohlc = Some((0.0, 0.0, 0.0));
+        assert!(state.observe(&RULES, &t, at(1)).is_empty());
+    }
+
+    #[test]
+    fn sigma_rule_catches_spikes_within_pct_limit() {
+        let mut state = InstrumentState::anchored(100.0);
+        let mut price = 100.0;
+        for i in 0..MIN_SIGMA_SAMPLES as u32 {
+            price *= if i % 2 == 0 { 1.0005 } else { 0.9995 };
+            assert!(state.observe(&RULES, &tick(price, i), at(1_000)).is_empty());
+        }
+        // 3% is inside the 10% limit but far beyond 8σ of ±0.05% moves.
+        assert_eq!(state.observe(&RULES, &tick(price * 1.03, 100), at(1_000)), vec![Violation::JumpSigma]);
+        assert_eq!(
+            state.observe(&RULES, &tick(price * 1.5, 101), at(1_000)),
+            vec![Violation::JumpPct, Violation::JumpSigma]
+        );
+    }
+
+    #[test]
+    fn confirmed_new_level_is_accepted() {
+        let mut state = InstrumentState::anchored(100.0);
+        for i in 0..CONFIRM_TICKS - 1 {
+            assert_eq!(state.observe(&RULES, &tick(130.0, i), at(100)), vec![Violation::JumpPct]);
+        }
+        assert!(state.observe(&RULES, &tick(130.5, 10), at(100)).is_empty());
+        assert_eq!(state.last_price(), Some(130.5));
+        assert!(state.observe(&RULES, &tick(131.0, 11), at(100)).is_empty());
+    }
+
+    #[test]
+    fn isolated_spikes_do_not_reanchor() {
+        let mut state = InstrumentState::anchored(100.0);
+        for i in 0..CONFIRM_TICKS * 2 {
+            let spike = if i % 2 == 0 { 150.0 } else { 60.0 };
+            assert!(!state.observe(&RULES, &tick(spike, i), at(100)).is_empty());
+        }
+        assert_eq!(state.last_price(), Some(100.0));
+    }
+}
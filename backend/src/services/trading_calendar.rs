@@ -0,0 +1,323 @@
+//! Exchange trading calendar.
+//!
+//! Knows when each exchange is open so seed/backfill generators only emit
+//! candles during real sessions and contests are not scheduled at 3 a.m.
+//!   - NSE / BSE : 09:15–15:30 IST (UTC+05:30, no DST), Monday–Friday,
+//!     minus the holidays listed in the holiday file.
+//!   - anything else (BINANCE, unknown) : open 24/7.
+//!
+//! All inputs and outputs are naive UTC timestamps, like the rest of the DB.
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use tracing::{info, warn};
+
+/// IST is a fixed UTC+05:30 offset.
+const IST_OFFSET_MINUTES: i64 = 330;
+
+/// How far ahead `current_or_next_session` / `first_fit` search before giving up.
+const MAX_LOOKAHEAD_DAYS: i64 = 30;
+
+/// Regular session of an exchange, in exchange-local time.
+#[derive(Debug, Clone, Copy)]
+struct SessionHours {
+    open: NaiveTime,
+    close: NaiveTime,
+    utc_offset_minutes: i64,
+}
+
+/// One trading session, expressed in UTC.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Session {
+    /// Exchange-local trading date.
+    pub date: NaiveDate,
+    pub open: NaiveDateTime,
+    pub close: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Holiday {
+    pub date: NaiveDate,
+    pub description: String,
+}
+
+#[derive(Debug, Default)]
+pub struct TradingCalendar {
+    /// Holidays keyed by upper-case exchange code.
+    holidays: HashMap<String, BTreeMap<NaiveDate, String>>,
+}
+
+impl TradingCalendar {
+    /// Load holidays from a CSV file of `date,exchange,description` lines
+    /// (`#` starts a comment). A missing file is not fatal: the calendar then
+    /// only knows about weekends.
+    pub fn load(path: &str) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => match Self::parse(&contents) {
+                Ok(calendar) => {
+                    let count: usize = calendar.holidays.values().map(|h| h.len()).sum();
+                    info!("Trading calendar: loaded {} holiday(s) from {}", count, path);
+                    calendar
+                }
+                Err(e) => {
+                    warn!("Trading calendar: could not parse {} ({:#}); weekends only", path, e);
+                    Self::default()
+                }
+            },
+            Err(e) => {
+                warn!("Trading calendar: could not read {} ({}); weekends only", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    fn parse(contents: &str) -> Result<Self> {
+        let mut holidays: HashMap<String, BTreeMap<NaiveDate, String>> = HashMap::new();
+        for (n, raw) in contents.lines().enumerate() {
+            let line = raw.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut cols = line.splitn(3, ',').map(str::trim);
+            let date = cols.next().unwrap_or("");
+            let exchange = cols
+                .next()
+                .filter(|e| !e.is_empty())
+                .with_context(|| format!("line {}: missing exchange", n + 1))?;
+            let description = cols.next().unwrap_or("").to_string();
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .with_context(|| format!("line {}: invalid date '{}'", n + 1, date))?;
+            holidays
+                .entry(exchange.to_ascii_uppercase())
+                .or_default()
+                .insert(date, description);
+        }
+        Ok(Self { holidays })
+    }
+
+    fn hours(exchange: &str) -> Option<SessionHours> {
+        match exchange.to_ascii_uppercase().as_str() {
+            "NSE" | "BSE" => Some(SessionHours {
+                open: NaiveTime::from_hms_opt(9, 15, 0).expect("valid time"),
+                close: NaiveTime::from_hms_opt(15, 30, 0).expect("valid time"),
+                utc_offset_minutes: IST_OFFSET_MINUTES,
+            }),
+            _ => None,
+        }
+    }
+
+    /// True when the exchange trades around the clock (crypto venues).
+    pub fn is_24x7(exchange: &str) -> bool {
+        Self::hours(exchange).is_none()
+    }
+
+    /// Timezone label for API responses.
+    pub fn timezone(exchange: &str) -> &'static str {
+        if Self::is_24x7(exchange) {
+            "UTC"
+        } else {
+            "Asia/Kolkata"
+        }
+    }
+
+    pub fn is_trading_day(&self, exchange: &str, date: NaiveDate) -> bool {
+        if Self::is_24x7(exchange) {
+            return true;
+        }
+        if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+            return false;
+        }
+        !self
+            .holidays
+            .get(&exchange.to_ascii_uppercase())
+            .is_some_and(|h| h.contains_key(&date))
+    }
+
+    /// Session on the given exchange-local date, if the exchange trades that
+    /// day. 24/7 exchanges return the whole UTC day.
+    pub fn session_on(&self, exchange: &str, date: NaiveDate) -> Option<Session> {
+        if !self.is_trading_day(exchange, date) {
+            return None;
+        }
+        let Some(hours) = Self::hours(exchange) else {
+            let open = date.and_time(NaiveTime::MIN);
+            return Some(Session {
+                date,
+                open,
+                close: open + Duration::days(1),
+            });
+        };
+        let offset = Duration::minutes(hours.utc_offset_minutes);
+        Some(Session {
+            date,
+            open: date.and_time(hours.open) - offset,
+            close: date.and_time(hours.close) - offset,
+        })
+    }
+
+    /// Whether a candle stamped `at` (UTC) falls inside a session.
+    /// Sessions are half-open: the 15:30 IST bucket is already closed.
+    pub fn is_open(&self, exchange: &str, at: NaiveDateTime) -> bool {
+        if Self::is_24x7(exchange) {
+            return true;
+        }
+        self.session_on(exchange, self.local_date(exchange, at))
+            .is_some_and(|s| s.open <= at && at < s.close)
+    }
+
+    /// Sessions whose local date lies in `[from, to]`.
+    pub fn sessions(&self, exchange: &str, from: NaiveDate, to: NaiveDate) -> Vec<Session> {
+        from.iter_days()
+            .take_while(|d| *d <= to)
+            .filter_map(|d| self.session_on(exchange, d))
+            .collect()
+    }
+
+    /// Holidays of the exchange whose date lies in `[from, to]`.
+    pub fn holidays(&self, exchange: &str, from: NaiveDate, to: NaiveDate) -> Vec<Holiday> {
+        self.holidays
+            .get(&exchange.to_ascii_uppercase())
+            .map(|h| {
+                h.range(from..=to)
+                    .map(|(date, description)| Holiday {
+                        date: *date,
+                        description: description.clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The session that is open at `at`, or the next one to open after it.
+    pub fn current_or_next_session(&self, exchange: &str, at: NaiveDateTime) -> Option<Session> {
+        let start = self.local_date(exchange, at);
+        start
+            .iter_days()
+            .take(MAX_LOOKAHEAD_DAYS as usize)
+            .filter_map(|d| self.session_on(exchange, d))
+            .find(|s| at < s.close)
+    }
+
+    /// Earliest start `>= earliest` such that `[start, start + duration]`
+    /// lies inside one session of every listed exchange.
+    pub fn first_fit(
+        &self,
+        exchanges: &[&str],
+        earliest: NaiveDateTime,
+        duration: Duration,
+    ) -> Option<NaiveDateTime> {
+        let mut candidate = earliest;
+        let horizon = earliest + Duration::days(MAX_LOOKAHEAD_DAYS);
+        while candidate < horizon {
+            let mut moved = false;
+            for exchange in exchanges.iter().filter(|e| !Self::is_24x7(e)) {
+                let session = self.current_or_next_session(exchange, candidate)?;
+                if candidate < session.open || candidate + duration > session.close {
+                    let next = if candidate < session.open {
+                        session.open
+                    } else {
+                        // Doesn't fit in this session — try the next one.
+                        self.current_or_next_session(exchange, session.close)?.open
+                    };
+                    candidate = candidate.max(next);
+                    moved = true;
+                }
+            }
+            if !moved {
+                return Some(candidate);
+            }
+        }
+        None
+    }
+
+    /// Number of minute buckets in `[start, end)` during which the exchange
+    /// is open.
+    pub fn open_minutes(&self, exchange: &str, start: NaiveDateTime, end: NaiveDateTime) -> i64 {
+        if end <= start {
+            return 0;
+        }
+        if Self::is_24x7(exchange) {
+            return (end - start).num_minutes();
+        }
+        self.sessions(exchange, self.local_date(exchange, start), self.local_date(exchange, end))
+            .iter()
+            .map(|s| (s.close.min(end) - s.open.max(start)).num_minutes().max(0))
+            .sum()
+    }
+
+    fn local_date(&self, exchange: &str, at: NaiveDateTime) -> NaiveDate {
+        let offset = Self::hours(exchange).map_or(0, |h| h.utc_offset_minutes);
+        (at + Duration::minutes(offset)).date()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn calendar() -> TradingCalendar {
+        TradingCalendar::parse(
+            "# date,exchange,description\n2026-01-26,NSE,Republic Day\n\n2026-03-03,nse,Holi # festival\n",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn nse_session_is_0915_to_1530_ist() {
+        let cal = calendar();
+        // Tuesday 2026-01-27: 09:15 IST = 03:45 UTC, 15:30 IST = 10:00 UTC.
+        assert!(!cal.is_open("NSE", utc("2026-01-27 03:44")));
+        assert!(cal.is_open("NSE", utc("2026-01-27 03:45")));
+        assert!(cal.is_open("NSE", utc("2026-01-27 09:59")));
+        assert!(!cal.is_open("NSE", utc("2026-01-27 10:00")));
+    }
+
+    #[test]
+    fn weekends_and_holidays_are_closed_but_crypto_is_not() {
+        let cal = calendar();
+        assert!(!cal.is_open("NSE", utc("2026-01-26 05:00")), "Republic Day");
+        assert!(!cal.is_open("NSE", utc("2026-03-03 05:00")), "Holi, lower-case exchange");
+        assert!(!cal.is_open("NSE", utc("2026-01-31 05:00")), "Saturday");
+        assert!(cal.is_open("BINANCE", utc("2026-01-31 05:00")));
+        assert!(cal.is_open("BINANCE", utc("2026-01-26 22:00")));
+    }
+
+    #[test]
+    fn first_fit_skips_to_next_session_that_fits() {
+        let cal = calendar();
+        // Friday 2026-01-23 at 09:30 UTC: only 30 min left, the weekend and
+        // the Monday holiday follow, so a 60-minute window lands on Tuesday.
+        let start = cal
+            .first_fit(&["NSE", "BINANCE"], utc("2026-01-23 09:30"), Duration::minutes(60))
+            .unwrap();
+        assert_eq!(start, utc("2026-01-27 03:45"));
+
+        let crypto = cal
+            .first_fit(&["BINANCE"], utc("2026-01-24 02:00"), Duration::minutes(60))
+            .unwrap();
+        assert_eq!(crypto, utc("2026-01-24 02:00"));
+    }
+
+    #[test]
+    fn open_minutes_counts_only_sessions() {
+        let cal = calendar();
+        // Fri 2026-01-23 09:00 UTC → Tue 2026-01-27 04:00 UTC: one hour of
+        // Friday, the weekend and Republic Day, then 15 minutes of Tuesday.
+        let (start, end) = (utc("2026-01-23 09:00"), utc("2026-01-27 04:00"));
+        assert_eq!(cal.open_minutes("NSE", start, end), 60 + 15);
+        assert_eq!(cal.open_minutes("BINANCE", start, end), (end - start).num_minutes());
+        assert_eq!(cal.open_minutes("NSE", end, start), 0);
+    }
+
+    #[test]
+    fn parse_rejects_bad_dates() {
+        assert!(TradingCalendar::parse("2026-13-01,NSE,bad").is_err());
+        assert!(TradingCalendar::parse("2026-01-01").is_err());
+    }
+}